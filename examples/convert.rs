@@ -0,0 +1,15 @@
+use orderbook::data::{csv_to_binary, RecordKind};
+
+fn main() {
+    let ticker_id = orderbook::utils::tick::ALRS.ticker_id;
+    let ob_count = csv_to_binary("data/ob.csv", "data/ob.bin", RecordKind::Snap, ticker_id)
+        .expect("Snapshot conversion failed");
+    let trade_count = csv_to_binary(
+        "data/orders.csv",
+        "data/orders.bin",
+        RecordKind::Trade,
+        ticker_id,
+    )
+    .expect("Trade conversion failed");
+    println!("Converted {ob_count} snapshots and {trade_count} trades");
+}
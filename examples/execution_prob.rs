@@ -1,21 +1,42 @@
 use orderbook::{
-    backtest::FixPriceStrategy, engine::OrderBook, engine::TradingAccount,
-    experiments::execution_flow, management::OrderManagementSystem,
+    backtest::FixPriceStrategy, data::CleaningPolicy, engine::Money, engine::OrderBook,
+    engine::QueueModel, engine::TradingAccount, experiments::execution_flow,
+    management::OrderManagementSystem,
 };
 
 fn main() {
     let ob_path = "/opt/Zenpy/jupyter/data/voskhod/RUST_OB/ob/ob_ALRS.2023-11-01.csv";
     let orders_path = "/opt/Zenpy/jupyter/data/voskhod/RUST_OB/orders/orders_ALRS.2023-11-01.csv";
-    let mut ob = OrderBook::default();
-    let mut strat = FixPriceStrategy {
-        buy_tick_criterion: Some(-2),
-        sell_tick_criterion: None,
-        qty: 1,
-        ..Default::default()
-    };
+    let models = [
+        QueueModel::Pessimistic,
+        QueueModel::Optimistic,
+        QueueModel::Proportional,
+        QueueModel::probabilistic(42),
+    ];
+    let mut reports = Vec::new();
+    for queue_model in models {
+        let mut ob = OrderBook::default();
+        let mut strat = FixPriceStrategy {
+            buy_tick_criterion: Some(-2),
+            sell_tick_criterion: None,
+            qty: 1,
+            ticker: orderbook::utils::tick::ALRS,
+            ..Default::default()
+        };
 
-    let money_account = TradingAccount::new(0.0);
-    let mut oms = OrderManagementSystem::new(&mut strat, money_account);
+        let money_account = TradingAccount::new(Money::ZERO);
+        let mut oms = OrderManagementSystem::new(&mut strat, money_account);
+        oms.queue_model = queue_model;
 
-    execution_flow(&mut oms, &mut ob, ob_path, orders_path);
+        reports.push(execution_flow(
+            &mut oms,
+            &mut ob,
+            ob_path,
+            orders_path,
+            CleaningPolicy::default(),
+        ));
+    }
+    for report in reports {
+        println!("{report}\n");
+    }
 }
@@ -0,0 +1,11 @@
+use orderbook::data::{generate_csv, GeneratorConfig};
+
+fn main() {
+    let (snap_count, trade_count) = generate_csv(
+        GeneratorConfig::default(),
+        "data/synthetic_ob.csv",
+        "data/synthetic_orders.csv",
+    )
+    .expect("Generation failed");
+    println!("Generated {snap_count} snapshots and {trade_count} trades");
+}
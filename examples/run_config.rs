@@ -0,0 +1,9 @@
+use orderbook::backtest::RunConfig;
+
+fn main() {
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "configs/fix_spread.yaml".to_string());
+    let config = RunConfig::load(&path).unwrap();
+    config.run().unwrap();
+}
@@ -1,5 +1,7 @@
 use orderbook::{
     backtest::{signal_flow, SignalStrategy},
+    data::{CleaningPolicy, SignalSource},
+    engine::Money,
     engine::OrderBook,
     engine::TradingAccount,
     management::OrderManagementSystem,
@@ -8,7 +10,11 @@ use orderbook::{
 fn main() {
     let ob_path = "/opt/Zenpy/jupyter/data/voskhod/RUST_OB/ob/ob_MMM5.2025-03-28.csv";
     let orders_path = "/opt/Zenpy/jupyter/data/voskhod/RUST_OB/orders/orders_MMM5.2025-03-28.csv";
-    let signals_path = "/opt/Zenpy/jupyter/data/voskhod/RUST_OB/signals/CVD_signal.2025-03-28.csv";
+    let signals = [SignalSource::new(
+        "CVD",
+        "/opt/Zenpy/jupyter/data/voskhod/RUST_OB/signals/CVD_signal.2025-03-28.csv",
+        150_000,
+    )];
     let mut ob = OrderBook::default();
     let mut strat = SignalStrategy {
         buy_open_criterion: 0.0001,
@@ -24,8 +30,18 @@ fn main() {
         ..Default::default()
     };
 
-    let money_account = TradingAccount::new(0.0);
+    let money_account = TradingAccount::new(Money::ZERO);
     let mut oms = OrderManagementSystem::new(&mut strat, money_account);
 
-    let _ = signal_flow(&mut oms, &mut ob, ob_path, orders_path, signals_path);
+    match signal_flow(
+        &mut oms,
+        &mut ob,
+        ob_path,
+        orders_path,
+        &signals,
+        CleaningPolicy::default(),
+    ) {
+        | Ok(metrics) => println!("{metrics}"),
+        | Err(err) => eprintln!("{err:?}"),
+    }
 }
@@ -1,6 +1,6 @@
 use orderbook::{
     backtest::{strategy_flow, FixSpreadStrategy},
-    engine::{OrderBook, TradingAccount},
+    engine::{Money, OrderBook, TradingAccount},
     management::OrderManagementSystem,
 };
 
@@ -9,7 +9,7 @@ fn main() {
     let orders_path = "/opt/Zenpy/jupyter/data/voskhod/RUST_OB/orders/orders_ALRS.2024-01-29.csv";
     let mut ob = OrderBook::default();
     let mut strat = FixSpreadStrategy::new(orderbook::utils::tick::ALRS);
-    let initial_balance = 0.0;
+    let initial_balance = Money::ZERO;
     strat.buy_criterion = -0.0002;
     strat.sell_criterion = 0.0002;
     strat.buy_position_limit = 100;
@@ -20,5 +20,6 @@ fn main() {
     let money_account = TradingAccount::new(initial_balance);
     let mut oms = OrderManagementSystem::new(&mut strat, money_account);
 
-    strategy_flow(&mut oms, &mut ob, ob_path, orders_path);
+    let metrics = strategy_flow(&mut oms, &mut ob, ob_path, orders_path);
+    println!("{metrics}");
 }
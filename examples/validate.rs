@@ -0,0 +1,20 @@
+use orderbook::{
+    data::{clean_file, CleaningPolicy},
+    engine::{Snap, Trade},
+};
+
+fn main() {
+    let ticker_id = orderbook::utils::tick::ALRS.ticker_id;
+    let policy = CleaningPolicy::default();
+    let ob_report = clean_file::<Snap>("data/ob.csv", "data/ob.clean.bin", ticker_id, policy)
+        .expect("Snapshot validation failed");
+    println!("{ob_report}\n");
+    let trade_report = clean_file::<Trade>(
+        "data/orders.csv",
+        "data/orders.clean.bin",
+        ticker_id,
+        policy,
+    )
+    .expect("Trade validation failed");
+    println!("{trade_report}");
+}
@@ -0,0 +1,561 @@
+//! Builders that check strategy parameters once instead of every quote.
+//! Each starts from the defaults of the strategy constructor.
+
+use crate::{
+    backtest::{
+        AvellanedaStoikovStrategy, FixPriceStrategy, FixSpreadStrategy, HedgeStrategy,
+        InventorySkew, SignalSizing, SignalStrategy,
+    },
+    engine::{
+        indicators::{ArrivalIntensity, MidVolatility},
+        Ticker,
+    },
+};
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Error)]
+pub enum BuildError {
+    #[error("qty is zero")]
+    ZeroQty,
+    #[error("buy limit {buy} is negative or sell limit {sell} is positive")]
+    PositionLimits { buy: i32, sell: i32 },
+    #[error("{name} range {range:?} is empty")]
+    EmptyRange {
+        name: &'static str,
+        range: (f32, f32),
+    },
+    #[error("buy criterion {buy} is not below sell criterion {sell}")]
+    CrossedCriterions { buy: f32, sell: f32 },
+    #[error("{name} is {value}, expected {expected}")]
+    OutOfRange {
+        name: &'static str,
+        value: f32,
+        expected: &'static str,
+    },
+}
+
+const fn check_qty(qty: u32) -> Result<(), BuildError> {
+    if qty == 0 {
+        return Err(BuildError::ZeroQty);
+    }
+    Ok(())
+}
+
+const fn check_limits(buy: i32, sell: i32) -> Result<(), BuildError> {
+    if buy < 0 || sell > 0 {
+        return Err(BuildError::PositionLimits { buy, sell });
+    }
+    Ok(())
+}
+
+// NaN bounds make the range empty too
+fn check_range(name: &'static str, range: (f32, f32)) -> Result<(), BuildError> {
+    if range.0 <= range.1 {
+        Ok(())
+    } else {
+        Err(BuildError::EmptyRange { name, range })
+    }
+}
+
+fn check_criterions(buy: f32, sell: f32) -> Result<(), BuildError> {
+    if buy <= sell {
+        Ok(())
+    } else {
+        Err(BuildError::CrossedCriterions { buy, sell })
+    }
+}
+
+const fn check_value(
+    name: &'static str,
+    value: f32,
+    valid: bool,
+    expected: &'static str,
+) -> Result<(), BuildError> {
+    if valid {
+        Ok(())
+    } else {
+        Err(BuildError::OutOfRange {
+            name,
+            value,
+            expected,
+        })
+    }
+}
+
+pub struct FixSpreadBuilder {
+    strategy: FixSpreadStrategy,
+}
+
+impl FixSpreadStrategy {
+    pub fn builder(ticker: Ticker) -> FixSpreadBuilder {
+        FixSpreadBuilder {
+            strategy: Self::new(ticker),
+        }
+    }
+}
+
+impl FixSpreadBuilder {
+    #[must_use]
+    pub const fn qty(mut self, qty: u32) -> Self {
+        self.strategy.qty = qty;
+        self
+    }
+
+    /// Offsets of the bid and ask from the reference price
+    #[must_use]
+    pub const fn criterions(mut self, buy: f32, sell: f32) -> Self {
+        self.strategy.buy_criterion = buy;
+        self.strategy.sell_criterion = sell;
+        self
+    }
+
+    /// The sell limit is negative
+    #[must_use]
+    pub const fn position_limits(mut self, buy: i32, sell: i32) -> Self {
+        self.strategy.buy_position_limit = buy;
+        self.strategy.sell_position_limit = sell;
+        self
+    }
+
+    #[must_use]
+    pub const fn maker_range(mut self, range: (f32, f32)) -> Self {
+        self.strategy.maker_range = range;
+        self
+    }
+
+    #[must_use]
+    pub const fn taker_range(mut self, range: (f32, f32)) -> Self {
+        self.strategy.taker_range = range;
+        self
+    }
+
+    #[must_use]
+    pub const fn flatten_before_close(mut self, window: Option<u64>) -> Self {
+        self.strategy.flatten_before_close = window;
+        self
+    }
+
+    #[must_use]
+    pub const fn ladder(mut self, depth: u32, step: u32) -> Self {
+        self.strategy.ladder_depth = depth;
+        self.strategy.ladder_step = step;
+        self
+    }
+
+    #[must_use]
+    pub const fn skew(mut self, skew: InventorySkew) -> Self {
+        self.strategy.skew = skew;
+        self
+    }
+
+    #[must_use]
+    pub const fn size_taper(mut self, size_taper: f32) -> Self {
+        self.strategy.size_taper = size_taper;
+        self
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the qty is zero, the criterions are not set or cross,
+    /// the limits have the wrong sign, a range is empty or the skew is out of range
+    pub fn build(self) -> Result<FixSpreadStrategy, BuildError> {
+        let strategy = self.strategy;
+        check_qty(strategy.qty)?;
+        check_criterions(strategy.buy_criterion, strategy.sell_criterion)?;
+        check_limits(strategy.buy_position_limit, strategy.sell_position_limit)?;
+        check_range("maker", strategy.maker_range)?;
+        check_range("taker", strategy.taker_range)?;
+        check_value(
+            "size taper",
+            strategy.size_taper,
+            (0.0..=1.0).contains(&strategy.size_taper),
+            "within [0, 1]",
+        )?;
+        match strategy.skew {
+            | InventorySkew::None => {}
+            | InventorySkew::Linear { max_shift } => {
+                check_value("max shift", max_shift, max_shift >= 0.0, "not negative")?;
+            }
+            | InventorySkew::Exponential { max_shift, rate } => {
+                check_value("max shift", max_shift, max_shift >= 0.0, "not negative")?;
+                check_value("skew rate", rate, rate > 0.0, "positive")?;
+            }
+        }
+        if strategy.ladder_depth > 0 && strategy.ladder_step == 0 {
+            return Err(BuildError::OutOfRange {
+                name: "ladder step",
+                value: 0.0,
+                expected: "positive",
+            });
+        }
+        Ok(strategy)
+    }
+}
+
+pub struct SignalStrategyBuilder {
+    strategy: SignalStrategy,
+}
+
+impl SignalStrategy {
+    pub fn builder(ticker: Ticker) -> SignalStrategyBuilder {
+        SignalStrategyBuilder {
+            strategy: Self {
+                ticker,
+                maker_range: (-f32::INFINITY, f32::INFINITY),
+                taker_range: (0.0, f32::INFINITY),
+                ..Self::default()
+            },
+        }
+    }
+}
+
+impl SignalStrategyBuilder {
+    #[must_use]
+    pub const fn qty(mut self, qty: u32) -> Self {
+        self.strategy.qty = qty;
+        self
+    }
+
+    #[must_use]
+    pub const fn open_criterions(mut self, buy: f32, sell: f32) -> Self {
+        self.strategy.buy_open_criterion = buy;
+        self.strategy.sell_open_criterion = sell;
+        self
+    }
+
+    #[must_use]
+    pub const fn close_criterions(mut self, buy: f32, sell: f32) -> Self {
+        self.strategy.buy_close_criterion = buy;
+        self.strategy.sell_close_criterion = sell;
+        self
+    }
+
+    /// The sell limit is negative
+    #[must_use]
+    pub const fn position_limits(mut self, buy: i32, sell: i32) -> Self {
+        self.strategy.buy_position_limit = buy;
+        self.strategy.sell_position_limit = sell;
+        self
+    }
+
+    #[must_use]
+    pub const fn maker_range(mut self, range: (f32, f32)) -> Self {
+        self.strategy.maker_range = range;
+        self
+    }
+
+    #[must_use]
+    pub const fn taker_range(mut self, range: (f32, f32)) -> Self {
+        self.strategy.taker_range = range;
+        self
+    }
+
+    #[must_use]
+    pub const fn sizing(mut self, sizing: SignalSizing) -> Self {
+        self.strategy.sizing = sizing;
+        self
+    }
+
+    #[must_use]
+    pub const fn flatten_before_close(mut self, window: Option<u64>) -> Self {
+        self.strategy.flatten_before_close = window;
+        self
+    }
+
+    /// Nanoseconds after an open before the position is worked back
+    /// and the next signal is taken
+    #[must_use]
+    pub const fn cooldown(mut self, nanos: u64) -> Self {
+        self.strategy.cooldown = nanos;
+        self
+    }
+
+    #[must_use]
+    pub const fn cross_after(mut self, nanos: Option<u64>) -> Self {
+        self.strategy.cross_after = nanos;
+        self
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the qty is zero, the open criterions cross,
+    /// the limits have the wrong sign, a range is empty or the sizing can not scale
+    pub fn build(self) -> Result<SignalStrategy, BuildError> {
+        let strategy = self.strategy;
+        check_qty(strategy.qty)?;
+        check_criterions(strategy.buy_open_criterion, strategy.sell_open_criterion)?;
+        check_limits(strategy.buy_position_limit, strategy.sell_position_limit)?;
+        check_range("maker", strategy.maker_range)?;
+        check_range("taker", strategy.taker_range)?;
+        if let SignalSizing::Proportional { full_value } = strategy.sizing {
+            check_value("full value", full_value, full_value > 0.0, "positive")?;
+        }
+        Ok(strategy)
+    }
+}
+
+pub struct HedgeStrategyBuilder {
+    strategy: HedgeStrategy,
+}
+
+impl HedgeStrategy {
+    pub fn builder(ticker: Ticker, hedge_ratio: f32) -> HedgeStrategyBuilder {
+        HedgeStrategyBuilder {
+            strategy: Self::new(ticker, hedge_ratio),
+        }
+    }
+}
+
+impl HedgeStrategyBuilder {
+    #[must_use]
+    pub const fn qty(mut self, qty: u32) -> Self {
+        self.strategy.qty = qty;
+        self
+    }
+
+    #[must_use]
+    pub const fn escalate_after(mut self, escalate_after: u64) -> Self {
+        self.strategy.escalate_after = escalate_after;
+        self
+    }
+
+    #[must_use]
+    pub const fn maker_range(mut self, range: (f32, f32)) -> Self {
+        self.strategy.maker_range = range;
+        self
+    }
+
+    #[must_use]
+    pub const fn taker_range(mut self, range: (f32, f32)) -> Self {
+        self.strategy.taker_range = range;
+        self
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the qty or the hedge ratio is zero,
+    /// the ratio is not finite or a range is empty
+    pub fn build(self) -> Result<HedgeStrategy, BuildError> {
+        let strategy = self.strategy;
+        check_qty(strategy.qty)?;
+        let ratio = strategy.hedge_ratio;
+        check_value(
+            "hedge ratio",
+            ratio,
+            ratio.is_finite() && ratio != 0.0,
+            "finite and not zero",
+        )?;
+        check_range("maker", strategy.maker_range)?;
+        check_range("taker", strategy.taker_range)?;
+        Ok(strategy)
+    }
+}
+
+pub struct FixPriceBuilder {
+    strategy: FixPriceStrategy,
+}
+
+impl FixPriceStrategy {
+    pub fn builder(ticker: Ticker) -> FixPriceBuilder {
+        FixPriceBuilder {
+            strategy: Self {
+                ticker,
+                ..Self::default()
+            },
+        }
+    }
+}
+
+impl FixPriceBuilder {
+    #[must_use]
+    pub const fn qty(mut self, qty: u32) -> Self {
+        self.strategy.qty = qty;
+        self
+    }
+
+    /// Ticks away from the best price the orders are placed at
+    #[must_use]
+    pub const fn tick_criterions(mut self, buy: Option<i32>, sell: Option<i32>) -> Self {
+        self.strategy.buy_tick_criterion = buy;
+        self.strategy.sell_tick_criterion = sell;
+        self
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the qty is zero
+    pub fn build(self) -> Result<FixPriceStrategy, BuildError> {
+        check_qty(self.strategy.qty)?;
+        Ok(self.strategy)
+    }
+}
+
+pub struct AvellanedaStoikovBuilder {
+    strategy: AvellanedaStoikovStrategy,
+}
+
+impl AvellanedaStoikovStrategy {
+    pub fn builder(ticker: Ticker) -> AvellanedaStoikovBuilder {
+        AvellanedaStoikovBuilder {
+            strategy: Self::new(ticker),
+        }
+    }
+}
+
+impl AvellanedaStoikovBuilder {
+    #[must_use]
+    pub const fn qty(mut self, qty: u32) -> Self {
+        self.strategy.qty = qty;
+        self
+    }
+
+    #[must_use]
+    pub const fn risk_aversion(mut self, risk_aversion: f32) -> Self {
+        self.strategy.risk_aversion = risk_aversion;
+        self
+    }
+
+    /// Nanoseconds the model looks ahead, none runs to the session close
+    #[must_use]
+    pub const fn horizon(mut self, horizon: Option<u64>) -> Self {
+        self.strategy.horizon = horizon;
+        self
+    }
+
+    /// The sell limit is negative
+    #[must_use]
+    pub const fn position_limits(mut self, buy: i32, sell: i32) -> Self {
+        self.strategy.buy_position_limit = buy;
+        self.strategy.sell_position_limit = sell;
+        self
+    }
+
+    #[must_use]
+    pub const fn maker_range(mut self, range: (f32, f32)) -> Self {
+        self.strategy.maker_range = range;
+        self
+    }
+
+    #[must_use]
+    pub const fn taker_range(mut self, range: (f32, f32)) -> Self {
+        self.strategy.taker_range = range;
+        self
+    }
+
+    #[must_use]
+    pub const fn flatten_before_close(mut self, window: Option<u64>) -> Self {
+        self.strategy.flatten_before_close = window;
+        self
+    }
+
+    /// Samples both estimates need before the first quote
+    #[must_use]
+    pub const fn warmup(mut self, warmup: u32) -> Self {
+        self.strategy.warmup = warmup;
+        self
+    }
+
+    /// Weight of the newest sample in both estimates
+    #[must_use]
+    pub const fn calibration_alpha(mut self, alpha: f32) -> Self {
+        self.strategy.volatility = MidVolatility::new(alpha);
+        self.strategy.intensity = ArrivalIntensity::new(alpha);
+        self
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the qty is zero, the limits have the wrong sign,
+    /// a range is empty, the risk aversion is not positive
+    /// or the calibration weight is out of range
+    pub fn build(self) -> Result<AvellanedaStoikovStrategy, BuildError> {
+        let strategy = self.strategy;
+        check_qty(strategy.qty)?;
+        check_limits(strategy.buy_position_limit, strategy.sell_position_limit)?;
+        check_range("maker", strategy.maker_range)?;
+        check_range("taker", strategy.taker_range)?;
+        check_value(
+            "risk aversion",
+            strategy.risk_aversion,
+            strategy.risk_aversion > 0.0,
+            "positive",
+        )?;
+        let alpha = strategy.volatility.alpha;
+        check_value(
+            "calibration alpha",
+            alpha,
+            alpha > 0.0 && alpha <= 1.0,
+            "within (0, 1]",
+        )?;
+        Ok(strategy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn fix_spread() -> FixSpreadBuilder {
+        FixSpreadStrategy::builder(Ticker::default())
+            .qty(10)
+            .criterions(-0.001, 0.001)
+            .position_limits(100, -100)
+    }
+
+    #[rstest]
+    #[case(fix_spread().qty(0), BuildError::ZeroQty)]
+    #[case(
+        fix_spread().position_limits(-100, 100),
+        BuildError::PositionLimits { buy: -100, sell: 100 }
+    )]
+    #[case(
+        fix_spread().maker_range((0.01, -0.01)),
+        BuildError::EmptyRange { name: "maker", range: (0.01, -0.01) }
+    )]
+    #[case(
+        FixSpreadStrategy::builder(Ticker::default()).qty(10),
+        BuildError::CrossedCriterions { buy: f32::INFINITY, sell: -f32::INFINITY }
+    )]
+    #[case(
+        fix_spread().skew(InventorySkew::Exponential { max_shift: 0.001, rate: 0.0 }),
+        BuildError::OutOfRange { name: "skew rate", value: 0.0, expected: "positive" }
+    )]
+    fn invalid_fix_spread(#[case] builder: FixSpreadBuilder, #[case] expected: BuildError) {
+        assert_eq!(builder.build().err(), Some(expected));
+    }
+
+    #[test]
+    fn valid_fix_spread() {
+        let strategy = fix_spread().ladder(2, 3).build().unwrap();
+        assert_eq!(strategy.qty, 10);
+        assert_eq!(
+            (strategy.buy_position_limit, strategy.sell_position_limit),
+            (100, -100)
+        );
+        assert_eq!((strategy.ladder_depth, strategy.ladder_step), (2, 3));
+        assert!(HedgeStrategy::builder(Ticker::default(), f32::NAN)
+            .build()
+            .is_err());
+    }
+
+    #[rstest]
+    #[case(0.0, 0.1, "risk aversion")]
+    #[case(0.1, 0.0, "calibration alpha")]
+    #[case(0.1, 1.5, "calibration alpha")]
+    fn invalid_avellaneda_stoikov(
+        #[case] risk_aversion: f32,
+        #[case] alpha: f32,
+        #[case] name: &'static str,
+    ) {
+        let error = AvellanedaStoikovStrategy::builder(Ticker::default())
+            .qty(10)
+            .position_limits(100, -100)
+            .risk_aversion(risk_aversion)
+            .calibration_alpha(alpha)
+            .build()
+            .err();
+        assert!(matches!(error, Some(BuildError::OutOfRange { name: found, .. }) if found == name));
+    }
+}
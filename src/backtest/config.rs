@@ -0,0 +1,283 @@
+//! Declarative run config. A YAML file names the data, the ticker,
+//! the strategy with its parameters and the account, and deserializes
+//! into a backtest that runs under `Engine`.
+
+use crate::{
+    backtest::{
+        AvellanedaStoikovBuilder, AvellanedaStoikovStrategy, BuildError, Engine, FixSpreadBuilder,
+        FixSpreadStrategy, InventorySkew, StrategyMetrics,
+    },
+    data::SignalSource,
+    engine::{
+        FeeRates, FeeSchedule, Money, OrderBook, SessionCalendar, Ticker, TradingAccount,
+        ALWAYS_OPEN, MOEX_EQUITIES, MOEX_FORTS,
+    },
+    error::MyError,
+    management::OrderManagementSystem,
+};
+use log4rs::config::Deserializers;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("YAML error: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+    #[error("Invalid strategy: {0}")]
+    Build(#[from] BuildError),
+    #[error("Logging error: {0}")]
+    Logging(String),
+    #[error("Run error: {0}")]
+    Run(#[from] MyError),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RunConfig {
+    pub data: DataConfig,
+    pub ticker: TickerConfig,
+    pub strategy: StrategyConfig,
+    #[serde(default)]
+    pub account: AccountConfig,
+    // log4rs config, nothing is logged without it
+    pub logging: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DataConfig {
+    pub ob_path: String,
+    pub orders_path: String,
+    #[serde(default)]
+    pub signals: Vec<SignalSource>,
+    // Nanoseconds between `on_timer` calls
+    pub timer: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CalendarName {
+    #[default]
+    AlwaysOpen,
+    MoexForts,
+    MoexEquities,
+}
+
+impl CalendarName {
+    pub const fn calendar(self) -> SessionCalendar {
+        match self {
+            | Self::AlwaysOpen => ALWAYS_OPEN,
+            | Self::MoexForts => MOEX_FORTS,
+            | Self::MoexEquities => MOEX_EQUITIES,
+        }
+    }
+}
+
+/// Base rates of `FeeSchedule`, turnover tiers are not configurable
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeeConfig {
+    pub maker_rate: f32,
+    pub taker_rate: f32,
+    pub maker_per_contract: f32,
+    pub taker_per_contract: f32,
+    pub clearing_per_contract: f32,
+    pub min_fee: f32,
+}
+
+impl FeeConfig {
+    pub const fn schedule(&self) -> FeeSchedule {
+        FeeSchedule {
+            base: FeeRates {
+                maker_rate: self.maker_rate,
+                taker_rate: self.taker_rate,
+                maker_per_contract: self.maker_per_contract,
+                taker_per_contract: self.taker_per_contract,
+            },
+            tiers: &[],
+            clearing_per_contract: self.clearing_per_contract,
+            min_fee: self.min_fee,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TickerConfig {
+    #[serde(default)]
+    pub ticker_id: u64,
+    pub tick_size: f32,
+    pub step_price: f32,
+    #[serde(default)]
+    pub fees: FeeConfig,
+    #[serde(default)]
+    pub calendar: CalendarName,
+    // Per contract, price units
+    #[serde(default)]
+    pub initial_margin: f64,
+}
+
+impl TickerConfig {
+    pub fn ticker(&self) -> Ticker {
+        Ticker {
+            ticker_id: self.ticker_id,
+            tick_size: self.tick_size,
+            step_price: self.step_price,
+            fees: self.fees.schedule(),
+            calendar: self.calendar.calendar(),
+            initial_margin: Money::from_f64(self.initial_margin),
+        }
+    }
+}
+
+/// Strategy picked by `kind`, the rest are its parameters
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StrategyConfig {
+    FixSpread(FixSpreadConfig),
+    AvellanedaStoikov(AvellanedaStoikovConfig),
+}
+
+/// Parameters of `FixSpreadBuilder`, missing optional ones keep its defaults
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FixSpreadConfig {
+    pub qty: u32,
+    // (buy, sell) offsets from the reference price
+    pub criterions: (f32, f32),
+    // (buy, sell) with the sell one negative
+    pub position_limits: (i32, i32),
+    pub maker_range: Option<(f32, f32)>,
+    pub taker_range: Option<(f32, f32)>,
+    pub flatten_before_close: Option<u64>,
+    // (depth, step)
+    pub ladder: Option<(u32, u32)>,
+    #[serde(default)]
+    pub skew: InventorySkew,
+    #[serde(default)]
+    pub size_taper: f32,
+}
+
+impl FixSpreadConfig {
+    pub fn builder(&self, ticker: Ticker) -> FixSpreadBuilder {
+        let mut builder = FixSpreadStrategy::builder(ticker)
+            .qty(self.qty)
+            .criterions(self.criterions.0, self.criterions.1)
+            .position_limits(self.position_limits.0, self.position_limits.1)
+            .flatten_before_close(self.flatten_before_close)
+            .skew(self.skew)
+            .size_taper(self.size_taper);
+        if let Some(range) = self.maker_range {
+            builder = builder.maker_range(range);
+        }
+        if let Some(range) = self.taker_range {
+            builder = builder.taker_range(range);
+        }
+        if let Some((depth, step)) = self.ladder {
+            builder = builder.ladder(depth, step);
+        }
+        builder
+    }
+}
+
+/// Parameters of `AvellanedaStoikovBuilder`, missing optional ones keep its defaults
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AvellanedaStoikovConfig {
+    pub qty: u32,
+    pub risk_aversion: f32,
+    // Nanoseconds, none runs to the session close
+    pub horizon: Option<u64>,
+    // (buy, sell) with the sell one negative
+    pub position_limits: (i32, i32),
+    pub maker_range: Option<(f32, f32)>,
+    pub taker_range: Option<(f32, f32)>,
+    pub flatten_before_close: Option<u64>,
+    pub warmup: Option<u32>,
+    pub calibration_alpha: Option<f32>,
+}
+
+impl AvellanedaStoikovConfig {
+    pub fn builder(&self, ticker: Ticker) -> AvellanedaStoikovBuilder {
+        let mut builder = AvellanedaStoikovStrategy::builder(ticker)
+            .qty(self.qty)
+            .risk_aversion(self.risk_aversion)
+            .horizon(self.horizon)
+            .position_limits(self.position_limits.0, self.position_limits.1)
+            .flatten_before_close(self.flatten_before_close);
+        if let Some(range) = self.maker_range {
+            builder = builder.maker_range(range);
+        }
+        if let Some(range) = self.taker_range {
+            builder = builder.taker_range(range);
+        }
+        if let Some(warmup) = self.warmup {
+            builder = builder.warmup(warmup);
+        }
+        if let Some(alpha) = self.calibration_alpha {
+            builder = builder.calibration_alpha(alpha);
+        }
+        builder
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AccountConfig {
+    // Ticks
+    pub initial_balance: f64,
+}
+
+impl RunConfig {
+    /// # Errors
+    ///
+    /// Will return `Err` if `yaml` does not describe a run
+    pub fn from_yaml(yaml: &str) -> Result<Self, ConfigError> {
+        Ok(serde_yaml::from_str(yaml)?)
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the file can not be read or does not describe a run
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        Self::from_yaml(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn engine(&self) -> Engine {
+        Engine {
+            signals: self.data.signals.clone(),
+            timer: self.data.timer,
+            ..Engine::new(&self.data.ob_path, &self.data.orders_path)
+        }
+    }
+
+    /// Builds the strategy, sets up logging and replays the data
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the strategy is invalid, logging can not be set up
+    /// or a data file can not be opened
+    pub fn run(&self) -> Result<StrategyMetrics, ConfigError> {
+        let ticker = self.ticker.ticker();
+        let account = TradingAccount::new(Money::from_f64(self.account.initial_balance));
+        if let Some(path) = &self.logging {
+            log4rs::init_file(path, Deserializers::default())
+                .map_err(|err| ConfigError::Logging(err.to_string()))?;
+        }
+        match &self.strategy {
+            | StrategyConfig::FixSpread(config) => {
+                let mut strategy = config.builder(ticker).build()?;
+                let mut oms = OrderManagementSystem::new(&mut strategy, account);
+                Ok(self.engine().run(&mut oms, &mut OrderBook::new())?)
+            }
+            | StrategyConfig::AvellanedaStoikov(config) => {
+                let mut strategy = config.builder(ticker).build()?;
+                let mut oms = OrderManagementSystem::new(&mut strategy, account);
+                Ok(self.engine().run(&mut oms, &mut OrderBook::new())?)
+            }
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use crate::{
+    backtest::{ledger_mark, strategy_metrics, Context, SessionTracker, Strategy, StrategyMetrics},
+    data::{
+        open_market_data, CleaningPolicy, EventKind, MarketEvent, SignalSource, StreamEnd,
+        Timestamped,
+    },
+    dbgp,
+    engine::{place_body, InFlight, OrderBook, Signal},
+    error::MyError,
+    management::OrderManagementSystem,
+};
+use log::warn;
+
+/// Reaction to a market data event, on its way to the book.
+/// Carries the book without own orders as the trader saw it when deciding.
+#[derive(Debug)]
+enum Reaction {
+    Snapshot {
+        decision_epoch: u64,
+        seen: OrderBook,
+    },
+    Signal {
+        decision_epoch: u64,
+        signal: Signal,
+        seen: OrderBook,
+    },
+}
+
+/// Replays market data into any `Strategy` through its callbacks
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Engine {
+    pub ob_path: String,
+    pub orders_path: String,
+    // Merged into the replay, each delayed by its own `SignalSource::delay`
+    pub signals: Vec<SignalSource>,
+    // Nanoseconds between `on_timer` calls, none for no timer
+    pub timer: Option<u64>,
+    // Applied to every file before and during the replay
+    pub cleaning: CleaningPolicy,
+}
+
+impl Engine {
+    pub fn new(ob_path: impl Into<String>, orders_path: impl Into<String>) -> Self {
+        Self {
+            ob_path: ob_path.into(),
+            orders_path: orders_path.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Validates the data under `self.cleaning` and replays it into `oms.strategy`.
+    /// Reactions to snapshots and signals reach the book after `oms.latency`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if a data file can not be opened
+    ///
+    /// # Panics
+    ///
+    /// Will panic if there are more than `u16::MAX` signal sources
+    pub fn run<S: Strategy>(
+        &self,
+        oms: &mut OrderManagementSystem<S>,
+        ob: &mut OrderBook,
+    ) -> Result<StrategyMetrics, MyError> {
+        let mut events = open_market_data(&self.ob_path, &self.orders_path, self.cleaning)?;
+        for (idx, source) in self.signals.iter().enumerate() {
+            let idx = u16::try_from(idx).expect("Too many signal sources");
+            events = events.with_stream(source.open(idx, self.cleaning)?, StreamEnd::Continue);
+        }
+        let ticker = oms.strategy.ticker();
+        let mut sessions = SessionTracker::new(ticker.calendar, ticker.unit_cash());
+        // Skip all trades and signals that occured before the first snapshot
+        let mut events = events
+            .map_while(Result::ok)
+            .skip_while(|event| event.kind() != EventKind::Snap);
+        let Some(MarketEvent::Snap(first_snap)) = events.next() else {
+            return Ok(strategy_metrics(
+                oms,
+                ob,
+                sessions.finish(ledger_mark(oms, ob)),
+            ));
+        };
+        let start = first_snap.exch_epoch;
+        dbgp!("[ EPCH ] snap {:?}", start);
+        *ob = ob.process(first_snap, oms, place_body(false));
+        S::on_start(&mut Context {
+            oms,
+            ob,
+            epoch: start,
+        });
+
+        let mut in_flight = InFlight::default();
+        let mut next_timer = self.timer.map(|interval| start + interval);
+        // Replay until either stream stops, then deliver what is still in flight
+        for event in events.map(Some).chain([None]) {
+            let now = event.as_ref().map_or(u64::MAX, Timestamped::exch_epoch);
+            loop {
+                // Timers stop with the replay, a reaction due at the same epoch goes first
+                let due = in_flight.next_epoch().filter(|&epoch| epoch <= now);
+                if let Some(epoch) = next_timer.filter(|&epoch| {
+                    epoch <= now && event.is_some() && due.is_none_or(|due| epoch < due)
+                }) {
+                    oms.account.clock = epoch;
+                    S::on_timer(&mut Context { oms, ob, epoch });
+                    next_timer = self.timer.map(|interval| epoch + interval);
+                    continue;
+                }
+                let Some((delivery_epoch, reaction)) = in_flight.pop_due(now) else {
+                    break;
+                };
+                oms.account.clock = delivery_epoch;
+                dbgp!("[ EPCH ] action {:?} {:?}", delivery_epoch, reaction);
+                match reaction {
+                    | Reaction::Snapshot {
+                        decision_epoch,
+                        seen,
+                    } => S::on_snapshot(
+                        &mut Context {
+                            oms,
+                            ob,
+                            epoch: decision_epoch,
+                        },
+                        &seen,
+                    ),
+                    | Reaction::Signal {
+                        decision_epoch,
+                        signal,
+                        seen,
+                    } => S::on_signal(
+                        &mut Context {
+                            oms,
+                            ob,
+                            epoch: decision_epoch,
+                        },
+                        &signal,
+                        &seen,
+                    ),
+                }
+            }
+            let Some(event) = event else {
+                break;
+            };
+            match event {
+                | MarketEvent::Trade(trade) => {
+                    dbgp!("[ EPCH ] trade {:?}", trade.exch_epoch);
+                    let (exec_report, mismatch) = oms.apply_trade(ob, &trade);
+                    if let Some(mismatch) = mismatch {
+                        warn!(target: "replay", "{:?}", mismatch);
+                    }
+                    let mut ctx = Context {
+                        oms,
+                        ob,
+                        epoch: trade.exch_epoch,
+                    };
+                    if exec_report
+                        .filled_orders
+                        .iter()
+                        .any(|&(id, _, _)| ctx.oms.orders.is_tracked(id))
+                    {
+                        ctx.oms.update(&exec_report);
+                        S::on_fill(&mut ctx, &exec_report);
+                    }
+                    S::on_trade(&mut ctx, &trade);
+                }
+                | MarketEvent::Snap(snap) => {
+                    let epoch = snap.exch_epoch;
+                    dbgp!("[ EPCH ] snap {:?}", epoch);
+                    let _ = oms.settle_clearings(ob, epoch);
+                    *ob = ob.process(snap, oms, place_body(false));
+                    oms.release_held(ob);
+                    sessions.observe(epoch, ledger_mark(oms, ob));
+                    // Trader sees the snapshot late and the reaction reaches the book later still
+                    let decision_epoch = epoch + oms.latency.feed_delay();
+                    let delivery_epoch = decision_epoch + oms.latency.order_delay();
+                    let seen = ob.get_raw(oms);
+                    in_flight.push(
+                        delivery_epoch,
+                        Reaction::Snapshot {
+                            decision_epoch,
+                            seen,
+                        },
+                    );
+                }
+                | MarketEvent::Signal(signal) => {
+                    dbgp!("[ EPCH ] signal {:?}", signal.exch_epoch);
+                    // Signal epochs already include the delay of their source
+                    let decision_epoch = signal.exch_epoch;
+                    let delivery_epoch = decision_epoch + oms.latency.order_delay();
+                    let seen = ob.get_raw(oms);
+                    in_flight.push(
+                        delivery_epoch,
+                        Reaction::Signal {
+                            decision_epoch,
+                            signal,
+                            seen,
+                        },
+                    );
+                }
+            }
+        }
+        let epoch = oms.account.clock;
+        S::on_end(&mut Context { oms, ob, epoch });
+        dbgp!("Done!");
+        Ok(strategy_metrics(
+            oms,
+            ob,
+            sessions.finish(ledger_mark(oms, ob)),
+        ))
+    }
+}
@@ -0,0 +1,236 @@
+use crate::data::{open_market_data, CleaningPolicy, EventKind, MarketEvent, Timestamped};
+use crate::dbgp;
+use crate::engine::{indicators::Midprice, place_body, InFlight, OrderBook};
+use crate::management::{HedgeStats, OrderManagementSystem};
+use log::warn;
+use std::fmt;
+
+use super::{
+    ledger_mark, strategy_metrics, FixSpreadStrategy, HedgeStrategy, SessionTracker,
+    StrategyMetrics,
+};
+
+#[derive(Debug, PartialEq)]
+pub struct HedgeMetrics {
+    pub source: StrategyMetrics,
+    pub hedge: StrategyMetrics,
+    pub stats: HedgeStats,
+    // Contracts the hedge still had to trade at the end
+    pub gap: i32,
+}
+
+impl fmt::Display for HedgeMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[ SOURCE ]\n{}\n[ HEDGE  ]\n{}\n{}\nGap         = {}",
+            self.source, self.hedge, self.stats, self.gap
+        )
+    }
+}
+
+/// Book an event belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Leg {
+    Source,
+    Hedge,
+}
+
+/// Trader's reaction to a snapshot or a fill, on its way to the engine
+#[derive(Debug)]
+enum TraderMove {
+    // Source book without own orders as seen when deciding
+    Requote {
+        decision_epoch: u64,
+        seen: OrderBook,
+    },
+    Hedge {
+        decision_epoch: u64,
+    },
+}
+
+fn replay(
+    ob_path: &str,
+    orders_path: &str,
+    cleaning: CleaningPolicy,
+) -> impl Iterator<Item = MarketEvent> {
+    open_market_data(ob_path, orders_path, cleaning)
+        .unwrap()
+        .map_while(Result::ok)
+        // Skip all trades that occured before the first snapshot
+        .skip_while(|event| event.kind() != EventKind::Snap)
+}
+
+// A change of the source position moves the hedge target,
+// the hedge reacts after its own latencies
+fn follow_source(
+    source_position: i32,
+    followed: &mut i32,
+    hedge: &mut OrderManagementSystem<HedgeStrategy>,
+    hedge_ob: &OrderBook,
+    in_flight: &mut InFlight<TraderMove>,
+    epoch: u64,
+) {
+    if source_position == *followed {
+        return;
+    }
+    *followed = source_position;
+    hedge.follow(
+        source_position,
+        epoch,
+        Midprice::evaluate(&hedge_ob.get_raw(hedge)),
+    );
+    let decision_epoch = epoch + hedge.latency.feed_delay();
+    let delivery_epoch = decision_epoch + hedge.latency.order_delay();
+    in_flight.push(delivery_epoch, TraderMove::Hedge { decision_epoch });
+}
+
+/// Quotes the source book as `strategy_flow` does and hedges every change
+/// of its position in the hedge book.
+///
+/// Both replays run on one clock, either of them running dry ends the flow.
+/// Every file is validated under `cleaning`.
+///
+/// # Panics
+///
+/// Will panic if file read fails
+pub fn hedge_flow(
+    oms: &mut OrderManagementSystem<FixSpreadStrategy>,
+    ob: &mut OrderBook,
+    (ob_path, orders_path): (&str, &str),
+    hedge: &mut OrderManagementSystem<HedgeStrategy>,
+    hedge_ob: &mut OrderBook,
+    (hedge_ob_path, hedge_orders_path): (&str, &str),
+    cleaning: CleaningPolicy,
+) -> HedgeMetrics {
+    let calendar = oms.strategy.ticker.calendar;
+    let hedge_calendar = hedge.strategy.ticker.calendar;
+    let mut sessions = SessionTracker::new(calendar, oms.strategy.ticker.unit_cash());
+    let mut hedge_sessions = SessionTracker::new(hedge_calendar, hedge.strategy.ticker.unit_cash());
+    let mut source_events = replay(ob_path, orders_path, cleaning).peekable();
+    let mut hedge_events = replay(hedge_ob_path, hedge_orders_path, cleaning).peekable();
+    // Ties go to the source, its fills are hedged after them anyway
+    let events = std::iter::from_fn(|| {
+        let leg = match (source_events.peek(), hedge_events.peek()) {
+            | (Some(a), Some(b)) if a.exch_epoch() <= b.exch_epoch() => Leg::Source,
+            | (Some(_), Some(_)) => Leg::Hedge,
+            | _ => return None,
+        };
+        let event = match leg {
+            | Leg::Source => source_events.next(),
+            | Leg::Hedge => hedge_events.next(),
+        };
+        event.map(|event| (leg, event))
+    });
+
+    let mut in_flight = InFlight::default();
+    let mut followed = oms.position();
+    // Replay until either book runs dry, then deliver what is still in flight
+    for event in events.map(Some).chain([None]) {
+        let now = event
+            .as_ref()
+            .map_or(u64::MAX, |(_, event)| event.exch_epoch());
+        while let Some((delivery_epoch, action)) = in_flight.pop_due(now) {
+            dbgp!("[ EPCH ] action {:?} {:?}", delivery_epoch, action);
+            match action {
+                | TraderMove::Requote {
+                    decision_epoch,
+                    seen,
+                } => {
+                    oms.account.clock = delivery_epoch;
+                    oms.requote(ob, &seen, decision_epoch);
+                }
+                | TraderMove::Hedge { decision_epoch } => {
+                    hedge.account.clock = delivery_epoch;
+                    hedge.rehedge(hedge_ob, decision_epoch);
+                }
+            }
+            // Aggressive quotes and flattening trade the source right away
+            follow_source(
+                oms.position(),
+                &mut followed,
+                hedge,
+                hedge_ob,
+                &mut in_flight,
+                delivery_epoch,
+            );
+        }
+        let Some((leg, event)) = event else {
+            break;
+        };
+        match (leg, event) {
+            | (Leg::Source, MarketEvent::Trade(trade)) => {
+                dbgp!("[ EPCH ] source trade {:?}", trade.exch_epoch);
+                let (exec_report, mismatch) = oms.apply_trade(ob, &trade);
+                if let Some(mismatch) = mismatch {
+                    warn!(target: "replay", "{:?}", mismatch);
+                }
+                oms.update(&exec_report);
+                oms.release_held(ob);
+                follow_source(
+                    oms.position(),
+                    &mut followed,
+                    hedge,
+                    hedge_ob,
+                    &mut in_flight,
+                    trade.exch_epoch,
+                );
+            }
+            | (Leg::Source, MarketEvent::Snap(snap)) => {
+                let epoch = snap.exch_epoch;
+                dbgp!("[ EPCH ] source snap {:?}", epoch);
+                let _ = oms.settle_clearings(ob, epoch);
+                *ob = ob.process(snap, oms, place_body(false));
+                oms.release_held(ob);
+                sessions.observe(epoch, ledger_mark(oms, ob));
+                // Trader sees the snapshot late and the reaction reaches the book later still
+                let decision_epoch = epoch + oms.latency.feed_delay();
+                let delivery_epoch = decision_epoch + oms.latency.order_delay();
+                let seen = ob.get_raw(oms);
+                in_flight.push(
+                    delivery_epoch,
+                    TraderMove::Requote {
+                        decision_epoch,
+                        seen,
+                    },
+                );
+            }
+            | (Leg::Hedge, MarketEvent::Trade(trade)) => {
+                dbgp!("[ EPCH ] hedge trade {:?}", trade.exch_epoch);
+                let (exec_report, mismatch) = hedge.apply_trade(hedge_ob, &trade);
+                if let Some(mismatch) = mismatch {
+                    warn!(target: "replay", "{:?}", mismatch);
+                }
+                hedge.update(&exec_report);
+                hedge.release_held(hedge_ob);
+            }
+            | (Leg::Hedge, MarketEvent::Snap(snap)) => {
+                let epoch = snap.exch_epoch;
+                dbgp!("[ EPCH ] hedge snap {:?}", epoch);
+                let _ = hedge.settle_clearings(hedge_ob, epoch);
+                *hedge_ob = hedge_ob.process(snap, hedge, place_body(false));
+                hedge.release_held(hedge_ob);
+                hedge_sessions.observe(epoch, ledger_mark(hedge, hedge_ob));
+                // Reprices the open gap and escalates it once it is old enough
+                if hedge.gap() != 0 {
+                    let decision_epoch = epoch + hedge.latency.feed_delay();
+                    let delivery_epoch = decision_epoch + hedge.latency.order_delay();
+                    in_flight.push(delivery_epoch, TraderMove::Hedge { decision_epoch });
+                }
+            }
+            | (_, MarketEvent::Signal(_)) => {}
+        }
+    }
+    hedge.book_hedge_fills();
+    dbgp!("Done!");
+    HedgeMetrics {
+        source: strategy_metrics(oms, ob, sessions.finish(ledger_mark(oms, ob))),
+        hedge: strategy_metrics(
+            hedge,
+            hedge_ob,
+            hedge_sessions.finish(ledger_mark(hedge, hedge_ob)),
+        ),
+        stats: hedge.strategy.stats,
+        gap: hedge.gap(),
+    }
+}
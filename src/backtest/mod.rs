@@ -1,7 +1,17 @@
+mod builder;
+mod config;
+mod event_engine;
+mod hedge_flow;
+mod session_metrics;
 mod strat_w_signal;
 mod strategy;
 mod strategy_flow;
 
+pub use builder::*;
+pub use config::*;
+pub use event_engine::*;
+pub use hedge_flow::*;
+pub use session_metrics::*;
 pub use strat_w_signal::*;
 pub use strategy::*;
 pub use strategy_flow::*;
@@ -0,0 +1,90 @@
+use crate::engine::{Money, SessionCalendar, SessionKey};
+use readable::num::{Float, Unsigned};
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SessionMetrics {
+    // Local days since unix epoch
+    pub day: u64,
+    pub session: &'static str,
+    pub pnl_abs: f32,
+    pub pnl_bps: f32,
+    pub volume: f32,
+    pub trade_count: u32,
+}
+
+impl fmt::Display for SessionMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{:>6} {:<8}] PnL abs = {:.1}, PnL bps = {:.3}, Volume = {}, Trade Count = {}",
+            self.day,
+            self.session,
+            self.pnl_abs,
+            self.pnl_bps,
+            Float::from(self.volume),
+            Unsigned::from(self.trade_count)
+        )
+    }
+}
+
+/// Account state at a session boundary
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Mark {
+    // Marked to market balance
+    pub pnl: Money,
+    // Traded notional, price units
+    pub volume: u64,
+    pub trade_count: u32,
+}
+
+/// Splits a replay into sessions of the ticker calendar and
+/// accumulates metrics for each of them
+pub struct SessionTracker {
+    calendar: SessionCalendar,
+    unit_cash: f32,
+    current: Option<(SessionKey, Mark)>,
+    sessions: Vec<SessionMetrics>,
+}
+
+impl SessionTracker {
+    pub const fn new(calendar: SessionCalendar, unit_cash: f32) -> Self {
+        Self {
+            calendar,
+            unit_cash,
+            current: None,
+            sessions: Vec::new(),
+        }
+    }
+
+    /// Closes the running session once the epoch leaves it
+    /// and opens the next one
+    pub fn observe(&mut self, epoch: u64, mark: Mark) {
+        let key = self.calendar.session(epoch).map(|(key, _)| key);
+        if self.current.map(|(current, _)| current) == key {
+            return;
+        }
+        self.close(mark);
+        self.current = key.map(|key| (key, mark));
+    }
+
+    pub fn finish(mut self, mark: Mark) -> Vec<SessionMetrics> {
+        self.close(mark);
+        self.sessions
+    }
+
+    fn close(&mut self, mark: Mark) {
+        if let Some((key, start)) = self.current.take() {
+            let pnl = mark.pnl - start.pnl;
+            let volume = mark.volume - start.volume;
+            self.sessions.push(SessionMetrics {
+                day: key.day,
+                session: self.calendar.sessions[key.session].name,
+                pnl_abs: pnl.cash(self.unit_cash),
+                pnl_bps: pnl.bps(volume),
+                volume: volume as f32 * self.unit_cash,
+                trade_count: mark.trade_count - start.trade_count,
+            });
+        }
+    }
+}
@@ -7,25 +7,243 @@
 // use std::collections::HashSet;
 
 use core::f32;
+use log::info;
+use serde::Deserialize;
 
-use crate::engine::Ticker;
+use crate::dbgp;
+use crate::engine::indicators::{ArrivalIntensity, EmaMidprice, MidVolatility, Midprice};
+use crate::engine::{
+    ExecutionReport, OrderBook, SessionAction, Side, Signal, Ticker, Trade, TradingAccount,
+};
+use crate::experiments::FillStats;
+use crate::management::{HedgeStats, OrderManagementSystem};
 
-pub trait Strategy {}
+/// What a callback sees of the replay and acts through
+pub struct Context<'c, 'a, S: Strategy> {
+    pub oms: &'c mut OrderManagementSystem<'a, S>,
+    pub ob: &'c mut OrderBook,
+    // When the trader decided, the epoch of the event for fills and timers
+    pub epoch: u64,
+}
+
+impl<S: Strategy> Context<'_, '_, S> {
+    pub const fn strategy(&mut self) -> &mut S {
+        self.oms.strategy
+    }
+
+    pub const fn account(&self) -> &TradingAccount {
+        &self.oms.account
+    }
+}
+
+/// Callbacks `Engine` drives a strategy with, all of them do nothing by default.
+///
+/// Snapshots and signals are reacted to once the reaction would reach
+/// the book, with `seen` as the book without own orders the trader saw
+/// when deciding. Trades, fills and timers are handled as they happen.
+pub trait Strategy: Sized {
+    // Instrument the strategy trades
+    fn ticker(&self) -> &Ticker;
+    // After the first snapshot built the book
+    fn on_start(_ctx: &mut Context<'_, '_, Self>) {}
+    fn on_snapshot(_ctx: &mut Context<'_, '_, Self>, _seen: &OrderBook) {}
+    // Every print of the replay, after own fills it caused
+    fn on_trade(_ctx: &mut Context<'_, '_, Self>, _trade: &Trade) {}
+    fn on_signal(_ctx: &mut Context<'_, '_, Self>, _signal: &Signal, _seen: &OrderBook) {}
+    // A print that filled own resting orders, already booked to the account
+    fn on_fill(_ctx: &mut Context<'_, '_, Self>, _exec_report: &ExecutionReport) {}
+    fn on_timer(_ctx: &mut Context<'_, '_, Self>) {}
+    // Once the replay ran dry, before the metrics are taken
+    fn on_end(_ctx: &mut Context<'_, '_, Self>) {}
+}
+
+impl Strategy for FixSpreadStrategy {
+    fn ticker(&self) -> &Ticker {
+        &self.ticker
+    }
+    fn on_snapshot(ctx: &mut Context<'_, '_, Self>, seen: &OrderBook) {
+        ctx.oms.requote(ctx.ob, seen, ctx.epoch);
+    }
+    fn on_trade(ctx: &mut Context<'_, '_, Self>, _trade: &Trade) {
+        ctx.oms.release_held(ctx.ob);
+    }
+}
+
+impl Strategy for AvellanedaStoikovStrategy {
+    fn ticker(&self) -> &Ticker {
+        &self.ticker
+    }
+    fn on_snapshot(ctx: &mut Context<'_, '_, Self>, seen: &OrderBook) {
+        ctx.oms.requote(ctx.ob, seen, ctx.epoch);
+    }
+    fn on_trade(ctx: &mut Context<'_, '_, Self>, trade: &Trade) {
+        ctx.strategy().observe_trade(trade);
+        ctx.oms.release_held(ctx.ob);
+    }
+}
+
+impl Strategy for FixPriceStrategy {
+    fn ticker(&self) -> &Ticker {
+        &self.ticker
+    }
+    fn on_snapshot(ctx: &mut Context<'_, '_, Self>, seen: &OrderBook) {
+        ctx.oms.requote(ctx.ob, seen, ctx.epoch);
+    }
+    // A side filled in full releases the locked prices
+    fn on_fill(ctx: &mut Context<'_, '_, Self>, _exec_report: &ExecutionReport) {
+        let oms = &mut *ctx.oms;
+        let filled = (oms.active_buy_order.is_none() && oms.strategy.buy_price.is_some())
+            || (oms.active_sell_order.is_none() && oms.strategy.sell_price.is_some());
+        if filled {
+            oms.strategy.fills.record(true);
+            oms.lock_release();
+        }
+    }
+}
+
+impl Strategy for SignalStrategy {
+    fn ticker(&self) -> &Ticker {
+        &self.ticker
+    }
+    // Works the position back once the cooldown is over, crosses it out
+    // later still or once the signal expired
+    fn on_snapshot(ctx: &mut Context<'_, '_, Self>, seen: &OrderBook) {
+        let calendar = ctx.strategy().ticker.calendar;
+        let epoch = ctx.epoch;
+        match calendar.action(epoch, ctx.strategy().flatten_before_close) {
+            | SessionAction::Flatten => {
+                let _ = ctx.oms.flatten(ctx.ob, epoch);
+            }
+            | SessionAction::Quote => {
+                let strategy = ctx.strategy();
+                let since_open = strategy.since_open(epoch);
+                if strategy.cross_due(epoch) {
+                    dbgp!("Hedging as Taker");
+                    ctx.oms
+                        .close_position(ctx.ob, Midprice::evaluate(seen), epoch, true);
+                } else if since_open.is_some_and(|nanos| nanos >= strategy.cooldown) {
+                    dbgp!("Hedging as Maker, time passed={:?}", since_open);
+                    let m = strategy.close_mid.evaluate(seen);
+                    ctx.oms.close_position(ctx.ob, m, epoch, false);
+                }
+            }
+            | SessionAction::CancelAll => ctx.oms.cancel_all_orders(ctx.ob),
+        }
+        info!(target: "pnl", "{};{:?}", epoch, ctx.oms.get_pnl(Midprice::evaluate(ctx.ob), false));
+        info!(target: "pos", "{};{:?}", epoch, ctx.oms.position());
+    }
+    fn on_trade(ctx: &mut Context<'_, '_, Self>, trade: &Trade) {
+        ctx.oms.release_held(ctx.ob);
+        info!(target: "pnl", "{};{:?}", trade.exch_epoch, ctx.oms.get_pnl(Midprice::evaluate(ctx.ob), false));
+        info!(target: "pos", "{};{:?}", trade.exch_epoch, ctx.oms.position());
+    }
+    // Takes the signal once the cooldown after the last open is over
+    fn on_signal(ctx: &mut Context<'_, '_, Self>, signal: &Signal, seen: &OrderBook) {
+        let epoch = ctx.epoch;
+        let strategy = ctx.strategy();
+        if signal
+            .instrument_id
+            .is_some_and(|id| id != strategy.ticker.ticker_id)
+        {
+            return;
+        }
+        let qty = strategy.signal_qty(signal);
+        let action = strategy
+            .ticker
+            .calendar
+            .action(epoch, strategy.flatten_before_close);
+        let cooled = strategy
+            .since_open(epoch)
+            .is_none_or(|nanos| nanos >= strategy.cooldown);
+        if action != SessionAction::Quote || qty == 0 || !cooled {
+            return;
+        }
+        dbgp!("[ SGNL ] {:?}", signal);
+        strategy.opened_at = Some(epoch);
+        strategy.expires_at = signal.horizon.map(|horizon| epoch + horizon);
+        let m = Midprice::evaluate(seen);
+        if ctx.oms.open_position(ctx.ob, signal.side, qty, m, epoch) {
+            dbgp!("Cooldown started!");
+            let clock = ctx.oms.account.clock;
+            ctx.strategy().opened_at = Some(clock);
+        }
+    }
+}
 
-impl Strategy for FixSpreadStrategy {}
-impl Strategy for FixPriceStrategy {}
-impl Strategy for SignalStrategy {}
+impl Strategy for HedgeStrategy {
+    fn ticker(&self) -> &Ticker {
+        &self.ticker
+    }
+    fn on_snapshot(ctx: &mut Context<'_, '_, Self>, _seen: &OrderBook) {
+        ctx.oms.rehedge(ctx.ob, ctx.epoch);
+    }
+    fn on_trade(ctx: &mut Context<'_, '_, Self>, _trade: &Trade) {
+        ctx.oms.release_held(ctx.ob);
+    }
+    fn on_fill(ctx: &mut Context<'_, '_, Self>, _exec_report: &ExecutionReport) {
+        ctx.oms.book_hedge_fills();
+    }
+    fn on_end(ctx: &mut Context<'_, '_, Self>) {
+        ctx.oms.book_hedge_fills();
+    }
+}
 
 pub struct FixSpreadStrategy {
     pub ticker: Ticker,
     pub qty: u32,
     pub buy_criterion: f32,
     pub sell_criterion: f32,
-    pub master_position: i32,
     pub buy_position_limit: i32,
     pub sell_position_limit: i32,
     pub maker_range: (f32, f32),
     pub taker_range: (f32, f32),
+    // Close the position this many nanoseconds before the session ends
+    pub flatten_before_close: Option<u64>,
+    // Extra rungs quoted behind each quote, `ladder_step` ticks apart
+    pub ladder_depth: u32,
+    pub ladder_step: u32,
+    // Shift of both quotes against the inventory
+    pub skew: InventorySkew,
+    // Quote adding to the inventory loses this fraction of `qty` at the limit
+    pub size_taper: f32,
+}
+
+/// Shift of both quotes by the position as a fraction of its limit.
+///
+/// Relative to the reference price as the criterions, reaches `max_shift`
+/// at the limit. Long positions move the quotes down and short ones up.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum InventorySkew {
+    #[default]
+    None,
+    Linear {
+        max_shift: f32,
+    },
+    // Flat near zero inventory and steep near the limit for large `rate`, linear at 0
+    Exponential {
+        max_shift: f32,
+        rate: f32,
+    },
+}
+
+impl InventorySkew {
+    /// Shift at `inventory` within [-1, 1]
+    pub fn shift(self, inventory: f32) -> f32 {
+        match self {
+            | Self::None => 0.0,
+            // The exponential curve tends to the line as `rate` goes to 0
+            | Self::Linear { max_shift }
+            | Self::Exponential {
+                max_shift,
+                rate: 0.0,
+            } => -max_shift * inventory,
+            | Self::Exponential { max_shift, rate } => {
+                let curve = (rate * inventory.abs()).exp_m1() / rate.exp_m1();
+                -max_shift * curve.copysign(inventory)
+            }
+        }
+    }
 }
 
 impl FixSpreadStrategy {
@@ -36,10 +254,47 @@ impl FixSpreadStrategy {
             sell_criterion: -f32::INFINITY,
             maker_range: (-f32::INFINITY, f32::INFINITY),
             taker_range: (0.0, f32::INFINITY),
-            master_position: 0,
             buy_position_limit: 0,
             sell_position_limit: 0,
             qty: 0,
+            flatten_before_close: None,
+            ladder_depth: 0,
+            ladder_step: 1,
+            skew: InventorySkew::None,
+            size_taper: 0.0,
+        }
+    }
+
+    /// `position` as a fraction of the limit on its side, within [-1, 1]
+    pub fn inventory(&self, position: i32) -> f32 {
+        let limit = match position {
+            | position if position > 0 => self.buy_position_limit,
+            | _ => -self.sell_position_limit,
+        };
+        match limit {
+            | limit if limit <= 0 => 0.0,
+            | limit => (position as f32 / limit as f32).clamp(-1.0, 1.0),
+        }
+    }
+
+    /// Buy and sell criterions shifted by the inventory skew at `position`
+    pub fn skewed_criterions(&self, position: i32) -> (f32, f32) {
+        let shift = self.skew.shift(self.inventory(position));
+        (self.buy_criterion + shift, self.sell_criterion + shift)
+    }
+
+    /// `qty` tapered on the side that adds to the inventory at `position`
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn quote_qty(&self, side: Side, position: i32) -> u32 {
+        let inventory = self.inventory(position);
+        let adding = match side {
+            | Side::Bid => inventory > 0.0,
+            | Side::Ask => inventory < 0.0,
+        };
+        if adding {
+            (self.qty as f32 * self.size_taper.mul_add(-inventory.abs(), 1.0)).round() as u32
+        } else {
+            self.qty
         }
     }
 }
@@ -52,14 +307,15 @@ impl Default for FixSpreadStrategy {
 
 #[derive(Default)]
 pub struct FixPriceStrategy {
+    pub ticker: Ticker,
     pub qty: u32,
     pub buy_price: Option<u32>,
     pub sell_price: Option<u32>,
     pub buy_tick_criterion: Option<i32>,
     pub sell_tick_criterion: Option<i32>,
+    pub fills: FillStats,
 }
 
-#[derive(Default)]
 pub struct SignalStrategy {
     pub ticker: Ticker,
     pub qty: u32,
@@ -67,9 +323,242 @@ pub struct SignalStrategy {
     pub sell_open_criterion: f32,
     pub buy_close_criterion: f32,
     pub sell_close_criterion: f32,
-    pub master_position: i32,
     pub buy_position_limit: i32,
     pub sell_position_limit: i32,
     pub maker_range: (f32, f32),
     pub taker_range: (f32, f32),
+    pub sizing: SignalSizing,
+    // Close the position this many nanoseconds before the session ends
+    pub flatten_before_close: Option<u64>,
+    // Nanoseconds after an open before the next signal is taken,
+    // the position is worked back with the close criterions from then on
+    pub cooldown: u64,
+    // Nanoseconds after an open the position is crossed out, none never
+    pub cross_after: Option<u64>,
+    // Mid the close criterions are applied to
+    pub close_mid: EmaMidprice,
+    // Decision epoch of the last open, a fill on arrival restarts the cooldown
+    pub opened_at: Option<u64>,
+    // End of the horizon of the signal behind the last open
+    pub expires_at: Option<u64>,
+}
+
+impl Default for SignalStrategy {
+    fn default() -> Self {
+        Self {
+            ticker: Ticker::default(),
+            qty: 0,
+            buy_open_criterion: 0.0,
+            sell_open_criterion: 0.0,
+            buy_close_criterion: 0.0,
+            sell_close_criterion: 0.0,
+            buy_position_limit: 0,
+            sell_position_limit: 0,
+            maker_range: (0.0, 0.0),
+            taker_range: (0.0, 0.0),
+            sizing: SignalSizing::Fixed,
+            flatten_before_close: None,
+            cooldown: 10_000_000_000,
+            cross_after: None,
+            close_mid: EmaMidprice::new(0.95),
+            opened_at: None,
+            expires_at: None,
+        }
+    }
+}
+
+/// How `SignalStrategy` turns signal strength into order size
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SignalSizing {
+    // Always quote `qty`
+    #[default]
+    Fixed,
+    // Scale `qty` by |value| / `full_value`, capped at `qty`
+    Proportional {
+        full_value: f32,
+    },
+}
+
+impl SignalStrategy {
+    /// Nanoseconds since the last open at `epoch`, none before the first one
+    pub fn since_open(&self, epoch: u64) -> Option<u64> {
+        self.opened_at.map(|at| epoch.saturating_sub(at))
+    }
+
+    /// Whether the position is crossed out at `epoch`, `cross_after` the
+    /// last open or once the horizon of the signal behind it passed
+    pub fn cross_due(&self, epoch: u64) -> bool {
+        let late = self
+            .since_open(epoch)
+            .zip(self.cross_after)
+            .is_some_and(|(nanos, after)| nanos >= after);
+        late || self.expires_at.is_some_and(|at| epoch >= at)
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn signal_qty(&self, signal: &Signal) -> u32 {
+        match self.sizing {
+            | SignalSizing::Fixed => self.qty,
+            | SignalSizing::Proportional { full_value } => {
+                let strength = (signal.value.abs() / full_value).min(1.0);
+                (self.qty as f32 * strength).round() as u32
+            }
+        }
+    }
+}
+
+/// Keeps the position in its ticker at `hedge_ratio` contracts against
+/// every contract held in another instrument, in the opposite direction
+pub struct HedgeStrategy {
+    pub ticker: Ticker,
+    // Hedge contracts per source contract, negative for inversely related ones
+    pub hedge_ratio: f32,
+    // Largest single hedge order
+    pub qty: u32,
+    // Position the hedge works towards
+    pub target: i32,
+    // A passive hedge crosses the spread once the gap is this many nanoseconds old
+    pub escalate_after: u64,
+    pub maker_range: (f32, f32),
+    pub taker_range: (f32, f32),
+    pub stats: HedgeStats,
+    // Epoch the current gap opened at and the mid it is measured against
+    pub(crate) gap_since: Option<u64>,
+    pub(crate) arrival: Option<f32>,
+    pub(crate) escalated: bool,
+    // Account fills already in `stats`
+    pub(crate) booked: usize,
+}
+
+impl HedgeStrategy {
+    pub fn new(ticker: Ticker, hedge_ratio: f32) -> Self {
+        Self {
+            ticker,
+            hedge_ratio,
+            qty: u32::MAX,
+            target: 0,
+            escalate_after: 0,
+            maker_range: (-f32::INFINITY, f32::INFINITY),
+            taker_range: (0.0, f32::INFINITY),
+            stats: HedgeStats::default(),
+            gap_since: None,
+            arrival: None,
+            escalated: false,
+            booked: 0,
+        }
+    }
+}
+
+/// Market maker of Avellaneda and Stoikov.
+///
+/// Quotes around a reservation price that leans against the inventory, with
+/// a spread from the risk aversion, the volatility of the mid and the
+/// arrival intensity of prints.
+///
+/// Volatility is calibrated on the mids of the snapshots and the
+/// intensity on the prints against them, nothing is quoted before both
+/// have seen `warmup` samples.
+pub struct AvellanedaStoikovStrategy {
+    pub ticker: Ticker,
+    pub qty: u32,
+    // Gamma, per price unit and `qty` held
+    pub risk_aversion: f32,
+    // Nanoseconds to the end of the horizon, none runs to the session close
+    pub horizon: Option<u64>,
+    pub buy_position_limit: i32,
+    pub sell_position_limit: i32,
+    pub maker_range: (f32, f32),
+    pub taker_range: (f32, f32),
+    // Close the position this many nanoseconds before the session ends
+    pub flatten_before_close: Option<u64>,
+    pub warmup: u32,
+    pub volatility: MidVolatility,
+    pub intensity: ArrivalIntensity,
+    // Mid of the last snapshot and the offsets quoted around it
+    pub(crate) last_mid: Option<f32>,
+    pub(crate) offsets: (f32, f32),
+}
+
+impl AvellanedaStoikovStrategy {
+    pub fn new(ticker: Ticker) -> Self {
+        Self {
+            ticker,
+            qty: 0,
+            risk_aversion: 0.1,
+            horizon: Some(10_000_000_000),
+            buy_position_limit: 0,
+            sell_position_limit: 0,
+            maker_range: (-f32::INFINITY, f32::INFINITY),
+            taker_range: (0.0, f32::INFINITY),
+            flatten_before_close: None,
+            warmup: 20,
+            volatility: MidVolatility::new(0.05),
+            intensity: ArrivalIntensity::new(0.05),
+            last_mid: None,
+            offsets: (0.0, 0.0),
+        }
+    }
+
+    /// `position` in units of `qty`
+    #[allow(clippy::cast_precision_loss)]
+    pub fn inventory(&self, position: i32) -> f32 {
+        match self.qty {
+            | 0 => 0.0,
+            | qty => position as f32 / qty as f32,
+        }
+    }
+
+    /// Seconds left to the end of the horizon at `epoch`
+    #[allow(clippy::cast_precision_loss)]
+    pub fn time_left(&self, epoch: u64) -> Option<f32> {
+        let nanos = self
+            .horizon
+            .or_else(|| self.ticker.calendar.until_close(epoch))?;
+        Some(nanos as f32 / 1e9)
+    }
+
+    /// `s - q * gamma * sigma^2 * tau` holding `position`
+    pub fn reservation_price(&self, mid: f32, variance: f32, time_left: f32, position: i32) -> f32 {
+        (-self.inventory(position) * self.risk_aversion * variance).mul_add(time_left, mid)
+    }
+
+    /// `gamma * sigma^2 * tau + 2 / gamma * ln(1 + gamma / k)`
+    pub fn optimal_spread(&self, variance: f32, time_left: f32, decay: f32) -> f32 {
+        let gamma = self.risk_aversion;
+        (gamma * variance).mul_add(time_left, 2.0 / gamma * (gamma / decay).ln_1p())
+    }
+
+    /// Feeds the mid of a snapshot decided at `epoch` to the volatility
+    pub fn observe_mid(&mut self, epoch: u64, mid: f32) {
+        let _ = self.volatility.observe(epoch, mid);
+        self.last_mid = Some(mid);
+    }
+
+    /// Feeds a print to the intensity against the last mid
+    pub fn observe_trade(&mut self, trade: &Trade) {
+        if let Some(mid) = self.last_mid {
+            self.intensity.observe(trade.exch_epoch, trade.price, mid);
+        }
+    }
+
+    /// Bid and ask prices around `mid` at `epoch` holding `position`,
+    /// none while warming up
+    pub fn quotes(&self, mid: f32, epoch: u64, position: i32) -> Option<(f32, f32)> {
+        if self.volatility.samples < self.warmup || self.intensity.samples < self.warmup {
+            return None;
+        }
+        let variance = self.volatility.variance()?;
+        // A print inside half a tick says nothing about the decay
+        let decay = self.intensity.decay(self.ticker.tick_size / 2.0)?;
+        let time_left = self.time_left(epoch)?;
+        let reservation = self.reservation_price(mid, variance, time_left, position);
+        let half_spread = self.optimal_spread(variance, time_left, decay) / 2.0;
+        Some((reservation - half_spread, reservation + half_spread))
+    }
+}
+
+impl Default for AvellanedaStoikovStrategy {
+    fn default() -> Self {
+        Self::new(Ticker::default())
+    }
 }
@@ -1,10 +1,9 @@
-use crate::dbgp;
-use crate::engine::{indicators::Midprice, place_body, Order, OrderBook, Snap};
-use crate::management::OrderManagementSystem;
+use crate::engine::{indicators::Midprice, Money, OrderBook};
+use crate::management::{OrderManagementSystem, Throttle};
 use readable::num::{Float, Unsigned};
 use std::fmt;
 
-use super::FixSpreadStrategy;
+use super::{Engine, FixSpreadStrategy, Mark, SessionMetrics, Strategy};
 
 #[derive(Debug, PartialEq)]
 pub struct StrategyMetrics {
@@ -12,21 +11,86 @@ pub struct StrategyMetrics {
     pub pnl_bps: f32,
     pub volume: f32,
     pub trade_count: u32,
+    // Fees paid net of rebates, already taken out of `pnl_abs`
+    pub fees: f32,
+    pub messages: MessageMetrics,
+    pub sessions: Vec<SessionMetrics>,
+}
+
+/// Load the run put on the venue
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct MessageMetrics {
+    // New orders, amendments and cancels sent
+    pub messages: u64,
+    // Amendments held back or dropped by the throttle
+    pub throttled: u64,
+    pub order_to_trade: f32,
+    pub messages_per_sec: f32,
+}
+
+impl MessageMetrics {
+    pub fn new(throttle: &Throttle, trade_count: u32) -> Self {
+        Self {
+            messages: throttle.messages,
+            throttled: throttle.throttled,
+            order_to_trade: throttle.order_to_trade(trade_count),
+            messages_per_sec: throttle.messages_per_sec(),
+        }
+    }
 }
 
 impl fmt::Display for StrategyMetrics {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "PnL abs     = {:.1}\nPnl bps     = {:.3}\nVolume      = {}\nTrade Count = {}",
+            "PnL abs     = {:.1}\nPnl bps     = {:.3}\nVolume      = {}\nTrade Count = {}\nFees        = {:.1}\nMessages    = {} ({} throttled)\nOTR         = {:.2}\nMsg/sec     = {:.2}",
             self.pnl_abs,
             self.pnl_bps,
             Float::from(self.volume),
-            Unsigned::from(self.trade_count)
-        )
+            Unsigned::from(self.trade_count),
+            self.fees,
+            Unsigned::from(self.messages.messages),
+            Unsigned::from(self.messages.throttled),
+            self.messages.order_to_trade,
+            self.messages.messages_per_sec
+        )?;
+        for session in &self.sessions {
+            write!(f, "\n{session}")?;
+        }
+        Ok(())
     }
 }
 
+/// Marks the position of the ticker as booked in the account ledger
+pub fn ledger_mark<S: Strategy>(oms: &OrderManagementSystem<S>, ob: &OrderBook) -> Mark {
+    Mark {
+        pnl: Midprice::evaluate(ob).map_or(Money::ZERO, |m| oms.account.pnl(oms.position(), m)),
+        volume: oms.account.cumulative_volume,
+        trade_count: oms.account.trade_count,
+    }
+}
+
+/// Metrics of the run so far, the position is taken from the account ledger
+pub fn strategy_metrics<S: Strategy>(
+    oms: &OrderManagementSystem<S>,
+    ob: &OrderBook,
+    sessions: Vec<SessionMetrics>,
+) -> StrategyMetrics {
+    let ticker = oms.strategy.ticker();
+    let pnl = ledger_mark(oms, ob).pnl;
+    StrategyMetrics {
+        pnl_abs: pnl.cash(ticker.unit_cash()),
+        pnl_bps: pnl.bps(oms.account.cumulative_volume),
+        volume: oms.account.cumulative_volume as f32 * ticker.unit_cash(),
+        trade_count: oms.account.trade_count,
+        fees: oms.account.fees.cash(ticker.unit_cash()),
+        messages: MessageMetrics::new(&oms.throttle, oms.account.trade_count),
+        sessions,
+    }
+}
+
+/// Replays the files into `FixSpreadStrategy` through `Engine`
+///
 /// # Panics
 ///
 /// Will panic if file read fails
@@ -36,77 +100,5 @@ pub fn strategy_flow(
     ob_path: &str,
     orders_path: &str,
 ) -> StrategyMetrics {
-    let mut snap_reader = csv::Reader::from_path(ob_path).unwrap();
-    let mut trade_reader = csv::Reader::from_path(orders_path).unwrap();
-    let mut srdr = snap_reader.deserialize::<Snap>();
-    let mut trdr = trade_reader.deserialize::<Order>();
-    let mut epoch = 0;
-    let mut trader_buy_id;
-    let mut trader_sell_id;
-    let mut next_order = Order::default();
-    dbgp!("Crafting Orderbook");
-    // Load first snapshot
-    if let Some(Ok(first_snap)) = srdr.next() {
-        epoch = first_snap.exch_epoch;
-        dbgp!("[ EPCH ] snap {:?}", epoch);
-        *ob = ob.process(first_snap, oms, place_body(false));
-    }
-
-    // Skip all trades that occured before the first snapshot
-    while next_order.id < epoch {
-        if let Some(Ok(order)) = trdr.next() {
-            next_order = order;
-        }
-    }
-
-    'a: while let Some(Ok(snap)) = srdr.next() {
-        epoch = snap.exch_epoch;
-        loop {
-            if next_order.id <= epoch {
-                // Apply order
-                dbgp!("[ EPCH ] order {:?}", next_order.id);
-                let exec_report = ob.add_limit_order(next_order);
-                dbgp!("{:#?}", exec_report);
-                oms.update(&exec_report);
-                dbgp!("{}", ob);
-                // Load next order
-                if let Some(Ok(order)) = trdr.next() {
-                    next_order = order;
-                } else {
-                    // Replay until last order
-                    break 'a;
-                }
-            // If next snap before order
-            } else if epoch < next_order.id {
-                // Load next snap
-                dbgp!("[ EPCH ] snap {:?}", epoch);
-                *ob = ob.process(snap, oms, place_body(false));
-                dbgp!("{}", ob);
-                // Trader's move
-                let m = Midprice::evaluate(&ob.get_raw(oms));
-                trader_buy_id = Some(epoch + 3);
-                trader_sell_id = Some(epoch + 7);
-                oms.send_orders(ob, m, trader_buy_id, trader_sell_id);
-                dbgp!("{}", ob);
-                break;
-            }
-        }
-    }
-    let _ = ob.get_bbo();
-    let pnl = Midprice::evaluate(ob)
-        .unwrap()
-        .mul_add(oms.strategy.master_position as f32, oms.account.balance);
-    let pnl_bps = match oms.account.cumulative_volume {
-        | 0 => 0.0,
-        | _ => (pnl / (oms.account.cumulative_volume as f32)) * 10000.0,
-    };
-    dbgp!("Done!");
-    let metrics = StrategyMetrics {
-        pnl_abs: pnl * oms.strategy.ticker.step_price,
-        pnl_bps,
-        volume: oms.account.cumulative_volume as f32 * oms.strategy.ticker.step_price,
-        trade_count: oms.account.trade_count,
-    };
-    println!("{metrics}");
-    metrics
+    Engine::new(ob_path, orders_path).run(oms, ob).unwrap()
 }
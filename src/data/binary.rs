@@ -0,0 +1,556 @@
+//! Fixed-width little-endian market data format.
+//!
+//! A file is a 16 byte header followed by records of a single kind:
+//!
+//! | offset | size | field                         |
+//! |--------|------|-------------------------------|
+//! | 0      | 4    | magic `RSOB`                  |
+//! | 4      | 2    | version                       |
+//! | 6      | 1    | record kind (snap/trade/sig)  |
+//! | 7      | 1    | depth (levels per side)       |
+//! | 8      | 8    | ticker id                     |
+//!
+//! Records are read straight from the file buffer, nothing is parsed
+//! until a field is accessed.
+//!
+//! Version 2 widened signal records to carry source, value, horizon
+//! and instrument id. Version 1 files are still readable.
+
+use crate::{
+    engine::{LimitOrder, Side, Signal, Snap, Trade},
+    error::MyError,
+};
+use std::{
+    fs,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+pub const MAGIC: [u8; 4] = *b"RSOB";
+pub const VERSION: u16 = 2;
+pub const HEADER_LEN: usize = 16;
+pub const TRADE_RECORD_LEN: usize = 32;
+pub const SIGNAL_RECORD_LEN: usize = 32;
+pub const SIGNAL_RECORD_LEN_V1: usize = 16;
+
+// Signal record flags
+const HAS_HORIZON: u8 = 1;
+const HAS_INSTRUMENT: u8 = 1 << 1;
+
+#[repr(u8)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum RecordKind {
+    Snap = 0,
+    Trade = 1,
+    Signal = 2,
+}
+
+impl TryFrom<u8> for RecordKind {
+    type Error = MyError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            | 0 => Ok(Self::Snap),
+            | 1 => Ok(Self::Trade),
+            | 2 => Ok(Self::Signal),
+            | other => Err(MyError::FormatError(format!("unknown record kind {other}"))),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct Header {
+    pub version: u16,
+    pub kind: RecordKind,
+    pub depth: u8,
+    pub ticker_id: u64,
+}
+
+impl Header {
+    pub const fn new(kind: RecordKind, depth: u8, ticker_id: u64) -> Self {
+        Self {
+            version: VERSION,
+            kind,
+            depth,
+            ticker_id,
+        }
+    }
+
+    pub const fn record_len(&self) -> usize {
+        match self.kind {
+            | RecordKind::Snap => 8 + 16 * self.depth as usize,
+            | RecordKind::Trade => TRADE_RECORD_LEN,
+            | RecordKind::Signal if self.version < 2 => SIGNAL_RECORD_LEN_V1,
+            | RecordKind::Signal => SIGNAL_RECORD_LEN,
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; HEADER_LEN] {
+        let mut buf = [0; HEADER_LEN];
+        buf[0..4].copy_from_slice(&MAGIC);
+        buf[4..6].copy_from_slice(&self.version.to_le_bytes());
+        buf[6] = self.kind as u8;
+        buf[7] = self.depth;
+        buf[8..16].copy_from_slice(&self.ticker_id.to_le_bytes());
+        buf
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if `bytes` does not start with a valid header
+    pub fn parse(bytes: &[u8]) -> Result<Self, MyError> {
+        if bytes.len() < HEADER_LEN || bytes[0..4] != MAGIC {
+            return Err(MyError::FormatError("missing header".to_owned()));
+        }
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if version > VERSION {
+            return Err(MyError::FormatError(format!(
+                "unsupported version {version}"
+            )));
+        }
+        let header = Self {
+            version,
+            kind: RecordKind::try_from(bytes[6])?,
+            depth: bytes[7],
+            ticker_id: read_u64(bytes, 8),
+        };
+        if header.kind == RecordKind::Snap && header.depth == 0 {
+            return Err(MyError::FormatError("zero snapshot depth".to_owned()));
+        }
+        Ok(header)
+    }
+}
+
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes.len() >= MAGIC.len() && bytes[0..4] == MAGIC
+}
+
+fn read_u64(bytes: &[u8], at: usize) -> u64 {
+    let mut buf = [0; 8];
+    buf.copy_from_slice(&bytes[at..at + 8]);
+    u64::from_le_bytes(buf)
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    let mut buf = [0; 4];
+    buf.copy_from_slice(&bytes[at..at + 4]);
+    u32::from_le_bytes(buf)
+}
+
+fn read_u16(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+/// Borrowed view of a snapshot record
+#[derive(Debug, Clone, Copy)]
+pub struct SnapRecord<'a> {
+    bytes: &'a [u8],
+    depth: usize,
+}
+
+impl SnapRecord<'_> {
+    pub fn exch_epoch(&self) -> u64 {
+        read_u64(self.bytes, 0)
+    }
+
+    /// Levels are stored bids first, best to worst, then asks
+    pub fn level(&self, idx: usize) -> LimitOrder {
+        let at = 8 + 8 * idx;
+        LimitOrder {
+            side: if idx < self.depth {
+                Side::Bid
+            } else {
+                Side::Ask
+            },
+            price: read_u32(self.bytes, at),
+            qty: read_u32(self.bytes, at + 4),
+        }
+    }
+
+    /// Zero padded levels of a shallow snapshot are skipped
+    pub fn to_snap(&self) -> Snap {
+        Snap {
+            exch_epoch: self.exch_epoch(),
+            vec: (0..2 * self.depth)
+                .map(|idx| self.level(idx))
+                .filter(|level| level.price != 0 || level.qty != 0)
+                .collect(),
+        }
+    }
+}
+
+/// Borrowed view of a trade record
+#[derive(Debug, Clone, Copy)]
+pub struct TradeRecord<'a> {
+    bytes: &'a [u8],
+}
+
+impl TradeRecord<'_> {
+    pub fn exch_epoch(&self) -> u64 {
+        read_u64(self.bytes, 0)
+    }
+
+    pub fn to_trade(&self) -> Trade {
+        Trade {
+            exch_epoch: self.exch_epoch(),
+            price: read_u32(self.bytes, 8),
+            qty: read_u32(self.bytes, 12),
+            side: if self.bytes[16] == 0 {
+                Side::Bid
+            } else {
+                Side::Ask
+            },
+            trade_id: (self.bytes[17] != 0).then(|| read_u64(self.bytes, 24)),
+        }
+    }
+}
+
+/// Borrowed view of a signal record
+#[derive(Debug, Clone, Copy)]
+pub struct SignalRecord<'a> {
+    bytes: &'a [u8],
+    version: u16,
+}
+
+impl SignalRecord<'_> {
+    pub fn exch_epoch(&self) -> u64 {
+        read_u64(self.bytes, 0)
+    }
+
+    pub fn to_signal(&self) -> Signal {
+        let side = if self.bytes[8] == 0 {
+            Side::Bid
+        } else {
+            Side::Ask
+        };
+        if self.version < 2 {
+            return Signal {
+                exch_epoch: self.exch_epoch(),
+                side,
+                value: if side == Side::Bid { 1.0 } else { -1.0 },
+                ..Default::default()
+            };
+        }
+        let flags = self.bytes[9];
+        Signal {
+            exch_epoch: self.exch_epoch(),
+            side,
+            source: read_u16(self.bytes, 10),
+            value: f32::from_bits(read_u32(self.bytes, 12)),
+            horizon: (flags & HAS_HORIZON != 0).then(|| read_u64(self.bytes, 16)),
+            instrument_id: (flags & HAS_INSTRUMENT != 0).then(|| read_u64(self.bytes, 24)),
+        }
+    }
+}
+
+/// Whole binary file held in memory
+pub struct BinaryFile {
+    pub header: Header,
+    bytes: Vec<u8>,
+}
+
+impl BinaryFile {
+    /// # Errors
+    ///
+    /// Will return `Err` if file read fails or the header is malformed
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, MyError> {
+        Self::from_bytes(fs::read(path)?)
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the header is malformed or the body
+    /// is not a whole number of records
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, MyError> {
+        let header = Header::parse(&bytes)?;
+        if !(bytes.len() - HEADER_LEN).is_multiple_of(header.record_len()) {
+            return Err(MyError::FormatError("truncated record".to_owned()));
+        }
+        Ok(Self { header, bytes })
+    }
+
+    pub const fn len(&self) -> usize {
+        (self.bytes.len() - HEADER_LEN) / self.header.record_len()
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn records(&self, kind: RecordKind) -> Result<std::slice::ChunksExact<'_, u8>, MyError> {
+        if self.header.kind == kind {
+            Ok(self.bytes[HEADER_LEN..].chunks_exact(self.header.record_len()))
+        } else {
+            Err(MyError::FormatError(format!(
+                "expected {kind:?} records, found {:?}",
+                self.header.kind
+            )))
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if file does not hold snapshots
+    pub fn snaps(&self) -> Result<impl Iterator<Item = SnapRecord<'_>>, MyError> {
+        let depth = self.header.depth as usize;
+        Ok(self
+            .records(RecordKind::Snap)?
+            .map(move |bytes| SnapRecord { bytes, depth }))
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if file does not hold trades
+    pub fn trades(&self) -> Result<impl Iterator<Item = TradeRecord<'_>>, MyError> {
+        Ok(self
+            .records(RecordKind::Trade)?
+            .map(|bytes| TradeRecord { bytes }))
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if file does not hold signals
+    pub fn signals(&self) -> Result<impl Iterator<Item = SignalRecord<'_>>, MyError> {
+        let version = self.header.version;
+        Ok(self
+            .records(RecordKind::Signal)?
+            .map(move |bytes| SignalRecord { bytes, version }))
+    }
+
+    /// Owning iterator over decoded records
+    pub const fn into_records<T: BinaryRecord>(self) -> BinaryRecords<T> {
+        BinaryRecords {
+            file: self,
+            pos: HEADER_LEN,
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+pub trait BinaryRecord: Sized {
+    const KIND: RecordKind;
+    fn decode(bytes: &[u8], header: &Header) -> Self;
+    fn encode(&self, buf: &mut Vec<u8>, header: &Header);
+
+    /// Levels per side needed to store the record
+    fn depth(&self) -> usize {
+        0
+    }
+}
+
+impl BinaryRecord for Snap {
+    const KIND: RecordKind = RecordKind::Snap;
+
+    fn depth(&self) -> usize {
+        let bids = self.vec.iter().filter(|l| l.side == Side::Bid).count();
+        bids.max(self.vec.len() - bids)
+    }
+
+    fn decode(bytes: &[u8], header: &Header) -> Self {
+        SnapRecord {
+            bytes,
+            depth: header.depth as usize,
+        }
+        .to_snap()
+    }
+
+    fn encode(&self, buf: &mut Vec<u8>, header: &Header) {
+        let depth = header.depth as usize;
+        buf.extend_from_slice(&self.exch_epoch.to_le_bytes());
+        let bids = self.vec.iter().filter(|l| l.side == Side::Bid);
+        let asks = self.vec.iter().filter(|l| l.side == Side::Ask);
+        for side in [bids.collect::<Vec<_>>(), asks.collect::<Vec<_>>()] {
+            for idx in 0..depth {
+                let (price, qty) = side.get(idx).map_or((0, 0), |l| (l.price, l.qty));
+                buf.extend_from_slice(&price.to_le_bytes());
+                buf.extend_from_slice(&qty.to_le_bytes());
+            }
+        }
+    }
+}
+
+impl BinaryRecord for Trade {
+    const KIND: RecordKind = RecordKind::Trade;
+
+    fn decode(bytes: &[u8], _header: &Header) -> Self {
+        TradeRecord { bytes }.to_trade()
+    }
+
+    fn encode(&self, buf: &mut Vec<u8>, _header: &Header) {
+        buf.extend_from_slice(&self.exch_epoch.to_le_bytes());
+        buf.extend_from_slice(&self.price.to_le_bytes());
+        buf.extend_from_slice(&self.qty.to_le_bytes());
+        buf.push(self.side as u8);
+        buf.push(u8::from(self.trade_id.is_some()));
+        buf.extend_from_slice(&[0; 6]);
+        buf.extend_from_slice(&self.trade_id.unwrap_or_default().to_le_bytes());
+    }
+}
+
+impl BinaryRecord for Signal {
+    const KIND: RecordKind = RecordKind::Signal;
+
+    fn decode(bytes: &[u8], header: &Header) -> Self {
+        SignalRecord {
+            bytes,
+            version: header.version,
+        }
+        .to_signal()
+    }
+
+    fn encode(&self, buf: &mut Vec<u8>, _header: &Header) {
+        let mut flags = 0;
+        if self.horizon.is_some() {
+            flags |= HAS_HORIZON;
+        }
+        if self.instrument_id.is_some() {
+            flags |= HAS_INSTRUMENT;
+        }
+        buf.extend_from_slice(&self.exch_epoch.to_le_bytes());
+        buf.push(self.side as u8);
+        buf.push(flags);
+        buf.extend_from_slice(&self.source.to_le_bytes());
+        buf.extend_from_slice(&self.value.to_bits().to_le_bytes());
+        buf.extend_from_slice(&self.horizon.unwrap_or_default().to_le_bytes());
+        buf.extend_from_slice(&self.instrument_id.unwrap_or_default().to_le_bytes());
+    }
+}
+
+pub struct BinaryRecords<T> {
+    file: BinaryFile,
+    pos: usize,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: BinaryRecord> Iterator for BinaryRecords<T> {
+    type Item = Result<T, MyError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.file.header.kind != T::KIND {
+            self.pos = self.file.bytes.len();
+            return Some(Err(MyError::FormatError(format!(
+                "expected {:?} records, found {:?}",
+                T::KIND,
+                self.file.header.kind
+            ))));
+        }
+        let len = self.file.header.record_len();
+        let bytes = self.file.bytes.get(self.pos..self.pos + len)?;
+        self.pos += len;
+        Some(Ok(T::decode(bytes, &self.file.header)))
+    }
+}
+
+pub struct BinaryWriter<W: Write> {
+    header: Header,
+    inner: W,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> BinaryWriter<W> {
+    /// # Errors
+    ///
+    /// Will return `Err` if header write fails
+    pub fn new(mut inner: W, header: Header) -> Result<Self, MyError> {
+        inner.write_all(&header.to_bytes())?;
+        Ok(Self {
+            header,
+            inner,
+            buf: Vec::with_capacity(header.record_len()),
+        })
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if write fails, `record` kind does not match the header
+    /// or `record` is deeper than the header
+    pub fn write<T: BinaryRecord>(&mut self, record: &T) -> Result<(), MyError> {
+        if T::KIND != self.header.kind {
+            return Err(MyError::FormatError(format!(
+                "cannot write {:?} into {:?} file",
+                T::KIND,
+                self.header.kind
+            )));
+        }
+        if record.depth() > self.header.depth as usize {
+            return Err(MyError::FormatError(format!(
+                "record of depth {} in file of depth {}",
+                record.depth(),
+                self.header.depth
+            )));
+        }
+        self.buf.clear();
+        record.encode(&mut self.buf, &self.header);
+        self.inner.write_all(&self.buf)?;
+        Ok(())
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if flush fails
+    pub fn finish(mut self) -> Result<W, MyError> {
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+/// Converts a csv file in the `data/` layout into the binary format.
+/// Snapshot depth is the deepest row, shallower rows are zero padded.
+///
+/// # Errors
+///
+/// Will return `Err` if either file fails, a csv row does not parse,
+/// a snapshot file has no rows or a row deeper than `u8::MAX` levels
+pub fn csv_to_binary(
+    csv_path: impl AsRef<Path>,
+    bin_path: impl AsRef<Path>,
+    kind: RecordKind,
+    ticker_id: u64,
+) -> Result<usize, MyError> {
+    let csv_path = csv_path.as_ref();
+    let mut reader = csv::Reader::from_path(csv_path)?;
+    match kind {
+        | RecordKind::Snap => {
+            // Depth is only known once every row has been seen
+            let mut depth = None;
+            for row in reader.deserialize::<Snap>() {
+                depth = depth.max(Some(row?.depth()));
+            }
+            let depth = depth.ok_or_else(|| {
+                MyError::FormatError(format!("no snapshots in {}", csv_path.display()))
+            })?;
+            let depth = u8::try_from(depth).map_err(|_| {
+                MyError::FormatError(format!("snapshot depth {depth} over {}", u8::MAX))
+            })?;
+            let out = BufWriter::new(fs::File::create(bin_path)?);
+            let mut writer = BinaryWriter::new(out, Header::new(kind, depth, ticker_id))?;
+            convert(
+                csv::Reader::from_path(csv_path)?.deserialize::<Snap>(),
+                &mut writer,
+            )
+        }
+        | RecordKind::Trade => {
+            let out = BufWriter::new(fs::File::create(bin_path)?);
+            let mut writer = BinaryWriter::new(out, Header::new(kind, 0, ticker_id))?;
+            convert(reader.deserialize::<Trade>(), &mut writer)
+        }
+        | RecordKind::Signal => {
+            let out = BufWriter::new(fs::File::create(bin_path)?);
+            let mut writer = BinaryWriter::new(out, Header::new(kind, 0, ticker_id))?;
+            convert(reader.deserialize::<Signal>(), &mut writer)
+        }
+    }
+}
+
+fn convert<T: BinaryRecord, W: Write>(
+    rows: impl Iterator<Item = Result<T, csv::Error>>,
+    writer: &mut BinaryWriter<W>,
+) -> Result<usize, MyError> {
+    let mut count = 0;
+    for row in rows {
+        writer.write(&row?)?;
+        count += 1;
+    }
+    writer.inner.flush()?;
+    Ok(count)
+}
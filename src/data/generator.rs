@@ -0,0 +1,365 @@
+//! Seeded synthetic market data. Order flow is simulated against the
+//! matching engine and written in the csv layout of `data/`, so every
+//! flow can be replayed without the production dumps.
+//!
+//! Limit orders are placed around a latent fair price that follows a
+//! random walk, market orders take liquidity and produce the trade prints,
+//! random resting orders get cancelled. Each process has its own clock.
+
+use crate::{
+    engine::{Order, OrderBook, Side, Snap, Trade},
+    error::MyError,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::path::Path;
+
+/// Levels per side in the csv layout
+pub const CSV_DEPTH: usize = 10;
+
+#[allow(clippy::cast_possible_truncation)]
+const DEPTH_TICKS: u32 = CSV_DEPTH as u32;
+const NANOS_IN_SECOND: f64 = 1_000_000_000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arrivals {
+    // Constant intensity, events per second
+    Poisson { rate: f64 },
+    // Self exciting: every event adds `alpha` to the intensity,
+    // which decays back to `base` at speed `beta`. Stable for alpha < beta
+    Hawkes { base: f64, alpha: f64, beta: f64 },
+}
+
+/// Arrival clock of a single process
+#[derive(Debug, Clone, Copy)]
+struct Clock {
+    arrivals: Arrivals,
+    // Seconds since the start of generation
+    next: f64,
+    // Hawkes excitation on top of the base intensity
+    excitation: f64,
+}
+
+fn exponential(rng: &mut StdRng, rate: f64) -> f64 {
+    -(1.0 - rng.random::<f64>()).ln() / rate
+}
+
+impl Clock {
+    fn new(arrivals: Arrivals, rng: &mut StdRng) -> Self {
+        let mut clock = Self {
+            arrivals,
+            next: 0.0,
+            excitation: 0.0,
+        };
+        clock.advance(rng);
+        clock
+    }
+
+    /// Draws the next arrival after `self.next`
+    fn advance(&mut self, rng: &mut StdRng) {
+        match self.arrivals {
+            | Arrivals::Poisson { rate } => self.next += exponential(rng, rate),
+            // Ogata thinning, intensity only decays between events
+            | Arrivals::Hawkes { base, alpha, beta } => loop {
+                let bound = base + self.excitation;
+                let wait = exponential(rng, bound);
+                self.next += wait;
+                self.excitation *= (-beta * wait).exp();
+                if rng.random::<f64>() * bound <= base + self.excitation {
+                    self.excitation += alpha;
+                    break;
+                }
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratorConfig {
+    pub seed: u64,
+    pub start_epoch: u64,
+    // Nanoseconds of market data to produce
+    pub duration: u64,
+    pub snap_interval: u64,
+    pub initial_price: u32,
+    pub tick_size: u32,
+    pub limit_flow: Arrivals,
+    pub market_flow: Arrivals,
+    // Cancels per second
+    pub cancel_rate: f64,
+    // Fair price moves by one tick at this rate per second
+    pub price_step_rate: f64,
+    pub limit_qty: (u32, u32),
+    pub market_qty: (u32, u32),
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        Self {
+            seed: 42,
+            // 2023-11-01 10:05 MSK
+            start_epoch: 1_698_822_300_000_000_000,
+            duration: 600_000_000_000,
+            snap_interval: 100_000_000,
+            initial_price: 26_850,
+            tick_size: 1,
+            limit_flow: Arrivals::Poisson { rate: 50.0 },
+            market_flow: Arrivals::Hawkes {
+                base: 1.0,
+                alpha: 0.5,
+                beta: 1.0,
+            },
+            cancel_rate: 30.0,
+            price_step_rate: 0.5,
+            limit_qty: (1, 50),
+            market_qty: (1, 100),
+        }
+    }
+}
+
+pub struct Generator {
+    config: GeneratorConfig,
+    rng: StdRng,
+    ob: OrderBook,
+    fair_price: u32,
+    next_id: u64,
+    live_ids: Vec<u64>,
+}
+
+impl Generator {
+    pub fn new(config: GeneratorConfig) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(config.seed),
+            fair_price: config.initial_price,
+            config,
+            ob: OrderBook::new(),
+            next_id: 0,
+            live_ids: Vec::new(),
+        }
+    }
+
+    /// Simulates the whole `duration`, snapshots are taken every `snap_interval`
+    pub fn run(mut self) -> (Vec<Snap>, Vec<Trade>) {
+        let mut snaps = Vec::new();
+        let mut trades = Vec::new();
+        let cancel_flow = Arrivals::Poisson {
+            rate: self.config.cancel_rate,
+        };
+        let walk = Arrivals::Poisson {
+            rate: self.config.price_step_rate,
+        };
+        // Limit, market, cancel and random walk clocks
+        let mut clocks = [
+            self.config.limit_flow,
+            self.config.market_flow,
+            cancel_flow,
+            walk,
+        ]
+        .map(|arrivals| Clock::new(arrivals, &mut self.rng));
+        let mut next_snap = self.config.snap_interval;
+        self.refill();
+        while next_snap <= self.config.duration {
+            let idx = (0..clocks.len())
+                .min_by(|&a, &b| clocks[a].next.total_cmp(&clocks[b].next))
+                .unwrap_or_default();
+            let now = clocks[idx].next;
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let offset = (now * NANOS_IN_SECOND) as u64;
+            // Snapshots go out before later events
+            if next_snap <= offset {
+                self.refill();
+                snaps.push(
+                    self.ob
+                        .to_snap(self.config.start_epoch + next_snap, CSV_DEPTH),
+                );
+                next_snap += self.config.snap_interval;
+                continue;
+            }
+            let epoch = self.config.start_epoch + offset;
+            match idx {
+                | 0 => self.limit_order(epoch, &mut trades),
+                | 1 => self.market_order(epoch, &mut trades),
+                | 2 => self.cancel_order(),
+                | _ => self.step_price(),
+            }
+            clocks[idx].advance(&mut self.rng);
+        }
+        (snaps, trades)
+    }
+
+    const fn new_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn qty(&mut self, range: (u32, u32)) -> u32 {
+        self.rng.random_range(range.0..=range.1)
+    }
+
+    fn record(trades: &mut Vec<Trade>, epoch: u64, side: Side, fills: &[(u64, u32, u32)]) {
+        trades.extend(fills.iter().map(|&(_, qty, price)| Trade {
+            exch_epoch: epoch,
+            price,
+            qty,
+            side,
+            trade_id: None,
+        }));
+    }
+
+    /// Passive order within `CSV_DEPTH` ticks of the fair price.
+    /// Orders placed through a stale touch execute right away.
+    fn limit_order(&mut self, epoch: u64, trades: &mut Vec<Trade>) {
+        let side: Side = self.rng.random();
+        let distance = self.rng.random_range(1..=DEPTH_TICKS) * self.config.tick_size;
+        let price = match side {
+            | Side::Bid => self.fair_price.saturating_sub(distance).max(1),
+            | Side::Ask => self.fair_price + distance,
+        };
+        let order = Order {
+            id: self.new_id(),
+            side,
+            price,
+            qty: self.qty(self.config.limit_qty),
+        };
+        let exec_report = self.ob.add_limit_order(order);
+        Self::record(trades, epoch, side, &exec_report.filled_orders);
+        if exec_report.remaining_qty > 0 {
+            self.live_ids.push(order.id);
+        }
+    }
+
+    /// Aggressive order, large ones sweep several levels.
+    /// Whatever is left unfilled is cancelled, nothing is left resting.
+    fn market_order(&mut self, epoch: u64, trades: &mut Vec<Trade>) {
+        let side: Side = self.rng.random();
+        let sweep = DEPTH_TICKS * self.config.tick_size;
+        let limit = match side {
+            | Side::Bid => self.ob.best_offer_price.map(|price| price + sweep),
+            | Side::Ask => self
+                .ob
+                .best_bid_price
+                .map(|price| price.saturating_sub(sweep).max(1)),
+        };
+        if let Some(price) = limit {
+            let order = Order {
+                id: self.new_id(),
+                side,
+                price,
+                qty: self.qty(self.config.market_qty),
+            };
+            let exec_report = self.ob.add_limit_order(order);
+            Self::record(trades, epoch, side, &exec_report.filled_orders);
+            if exec_report.remaining_qty > 0 {
+                let _ = self.ob.cancel_order(order.id);
+            }
+        }
+    }
+
+    fn cancel_order(&mut self) {
+        if !self.live_ids.is_empty() {
+            let idx = self.rng.random_range(0..self.live_ids.len());
+            // Filled orders are already gone from the book
+            let _ = self.ob.cancel_order(self.live_ids.swap_remove(idx));
+        }
+    }
+
+    fn step_price(&mut self) {
+        let tick = self.config.tick_size;
+        self.fair_price = match self.rng.random::<Side>() {
+            | Side::Bid => self.fair_price + tick,
+            | Side::Ask => self.fair_price.saturating_sub(tick).max(tick),
+        };
+    }
+
+    /// Liquidity providers keep both sides at least `CSV_DEPTH` levels deep
+    fn refill(&mut self) {
+        let tick = self.config.tick_size;
+        for side in [Side::Bid, Side::Ask] {
+            let levels = self.ob.to_snap(0, CSV_DEPTH).vec;
+            let count = levels.iter().filter(|level| level.side == side).count();
+            let mut price = levels
+                .iter()
+                .rev()
+                .find(|level| level.side == side)
+                .map_or(self.fair_price, |level| level.price);
+            for _ in count..CSV_DEPTH {
+                price = match side {
+                    | Side::Bid => price.saturating_sub(tick).max(1),
+                    | Side::Ask => price + tick,
+                };
+                let order = Order {
+                    id: self.new_id(),
+                    side,
+                    price,
+                    qty: self.qty(self.config.limit_qty),
+                };
+                let _ = self.ob.add_limit_order(order);
+                self.live_ids.push(order.id);
+            }
+        }
+    }
+}
+
+/// # Errors
+///
+/// Will return `Err` if file write fails
+pub fn write_snaps_csv(path: impl AsRef<Path>, snaps: &[Snap]) -> Result<(), MyError> {
+    let mut writer = csv::Writer::from_path(path)?;
+    let mut header = vec!["exch_epoch".to_owned()];
+    for field in ["price", "qty"] {
+        for side in ["bid", "ask"] {
+            header.extend((1..=CSV_DEPTH).map(|level| format!("{side}_{level}_{field}")));
+        }
+    }
+    writer.write_record(&header)?;
+    for snap in snaps {
+        let bids = snap.vec.iter().filter(|level| level.side == Side::Bid);
+        let asks = snap.vec.iter().filter(|level| level.side == Side::Ask);
+        let levels = bids.chain(asks).collect::<Vec<_>>();
+        let mut record = vec![snap.exch_epoch.to_string()];
+        record.extend(levels.iter().map(|level| level.price.to_string()));
+        record.extend(levels.iter().map(|level| level.qty.to_string()));
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// # Errors
+///
+/// Will return `Err` if file write fails
+pub fn write_trades_csv(path: impl AsRef<Path>, trades: &[Trade]) -> Result<(), MyError> {
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(["exch_epoch", "side", "price", "qty"])?;
+    for trade in trades {
+        // Aggressor side: 0 is a sell print, 1 is a buy print
+        let side = match trade.side {
+            | Side::Ask => "0",
+            | Side::Bid => "1",
+        };
+        writer.write_record([
+            trade.exch_epoch.to_string().as_str(),
+            side,
+            trade.price.to_string().as_str(),
+            trade.qty.to_string().as_str(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Generates a session and writes it in the `data/` csv layout.
+/// Returns the number of snapshots and trades written.
+///
+/// # Errors
+///
+/// Will return `Err` if file write fails
+pub fn generate_csv(
+    config: GeneratorConfig,
+    ob_path: impl AsRef<Path>,
+    orders_path: impl AsRef<Path>,
+) -> Result<(usize, usize), MyError> {
+    let (snaps, trades) = Generator::new(config).run();
+    write_snaps_csv(ob_path, &snaps)?;
+    write_trades_csv(orders_path, &trades)?;
+    Ok((snaps.len(), trades.len()))
+}
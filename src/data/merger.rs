@@ -0,0 +1,228 @@
+//! k-way merge of timestamped streams into a single time ordered
+//! `MarketEvent` stream.
+
+use crate::{
+    engine::{Signal, Snap, Trade},
+    error::MyError,
+};
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+pub trait Timestamped {
+    fn exch_epoch(&self) -> u64;
+}
+
+impl Timestamped for Snap {
+    fn exch_epoch(&self) -> u64 {
+        self.exch_epoch
+    }
+}
+
+impl Timestamped for Trade {
+    fn exch_epoch(&self) -> u64 {
+        self.exch_epoch
+    }
+}
+
+impl Timestamped for Signal {
+    fn exch_epoch(&self) -> u64 {
+        self.exch_epoch
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum EventKind {
+    Snap,
+    Trade,
+    Signal,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MarketEvent {
+    Snap(Snap),
+    Trade(Trade),
+    Signal(Signal),
+}
+
+impl MarketEvent {
+    pub const fn kind(&self) -> EventKind {
+        match self {
+            | Self::Snap(_) => EventKind::Snap,
+            | Self::Trade(_) => EventKind::Trade,
+            | Self::Signal(_) => EventKind::Signal,
+        }
+    }
+}
+
+impl Timestamped for MarketEvent {
+    fn exch_epoch(&self) -> u64 {
+        match self {
+            | Self::Snap(snap) => snap.exch_epoch,
+            | Self::Trade(trade) => trade.exch_epoch,
+            | Self::Signal(signal) => signal.exch_epoch,
+        }
+    }
+}
+
+impl From<Snap> for MarketEvent {
+    fn from(snap: Snap) -> Self {
+        Self::Snap(snap)
+    }
+}
+
+impl From<Trade> for MarketEvent {
+    fn from(trade: Trade) -> Self {
+        Self::Trade(trade)
+    }
+}
+
+impl From<Signal> for MarketEvent {
+    fn from(signal: Signal) -> Self {
+        Self::Signal(signal)
+    }
+}
+
+/// Order of events sharing an epoch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TieBreak {
+    // Kinds listed first are yielded first
+    Kind([EventKind; 3]),
+    // Streams added first are yielded first
+    StreamOrder,
+}
+
+impl Default for TieBreak {
+    /// Trades print before the snapshot that already reflects them,
+    /// signals are acted upon before the book moves.
+    fn default() -> Self {
+        Self::Kind([EventKind::Trade, EventKind::Signal, EventKind::Snap])
+    }
+}
+
+impl TieBreak {
+    fn rank(self, event: &MarketEvent, stream: usize) -> usize {
+        match self {
+            | Self::Kind(order) => order
+                .iter()
+                .position(|kind| *kind == event.kind())
+                .unwrap_or(order.len()),
+            | Self::StreamOrder => stream,
+        }
+    }
+}
+
+/// What the merger does once a stream runs dry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamEnd {
+    // End the merged stream
+    Stop,
+    // Keep replaying the other streams
+    Continue,
+}
+
+type BoxedStream = Box<dyn Iterator<Item = Result<MarketEvent, MyError>>>;
+
+struct Source {
+    stream: BoxedStream,
+    end: StreamEnd,
+    head: Option<MarketEvent>,
+}
+
+pub struct EventMerger {
+    tie_break: TieBreak,
+    sources: Vec<Source>,
+    // (epoch, tie break rank, stream index)
+    heap: BinaryHeap<Reverse<(u64, usize, usize)>>,
+    started: bool,
+    stopped: bool,
+    pending_err: Option<MyError>,
+}
+
+impl Default for EventMerger {
+    fn default() -> Self {
+        Self::new(TieBreak::default())
+    }
+}
+
+impl EventMerger {
+    pub const fn new(tie_break: TieBreak) -> Self {
+        Self {
+            tie_break,
+            sources: Vec::new(),
+            heap: BinaryHeap::new(),
+            started: false,
+            stopped: false,
+            pending_err: None,
+        }
+    }
+
+    /// Streams must be sorted by epoch on their own.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if called after the merger started yielding events
+    #[must_use]
+    pub fn with_stream<T, I>(mut self, stream: I, end: StreamEnd) -> Self
+    where
+        T: Into<MarketEvent>,
+        I: Iterator<Item = Result<T, MyError>> + 'static,
+    {
+        assert!(!self.started, "streams must be added before replay");
+        self.sources.push(Source {
+            stream: Box::new(stream.map(|item| item.map(Into::into))),
+            end,
+            head: None,
+        });
+        self
+    }
+
+    /// Loads the next head of stream `idx`.
+    /// Errors are passed through and end the stream.
+    fn pull(&mut self, idx: usize) -> Result<(), MyError> {
+        let source = &mut self.sources[idx];
+        match source.stream.next() {
+            | Some(Ok(event)) => {
+                let rank = self.tie_break.rank(&event, idx);
+                self.heap.push(Reverse((event.exch_epoch(), rank, idx)));
+                source.head = Some(event);
+                Ok(())
+            }
+            | Some(Err(err)) => {
+                self.stopped |= source.end == StreamEnd::Stop;
+                Err(err)
+            }
+            | None => {
+                self.stopped |= source.end == StreamEnd::Stop;
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Iterator for EventMerger {
+    type Item = Result<MarketEvent, MyError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.started {
+            self.started = true;
+            for idx in 0..self.sources.len() {
+                if let Err(err) = self.pull(idx) {
+                    self.pending_err.get_or_insert(err);
+                }
+            }
+        }
+        if let Some(err) = self.pending_err.take() {
+            return Some(Err(err));
+        }
+        if self.stopped {
+            return None;
+        }
+        let Reverse((_, _, idx)) = self.heap.pop()?;
+        let event = self.sources[idx].head.take()?;
+        // Look ahead so that exhausting a `Stop` stream ends the replay
+        // right after its last event
+        if let Err(err) = self.pull(idx) {
+            self.pending_err = Some(err);
+        }
+        Some(Ok(event))
+    }
+}
@@ -0,0 +1,11 @@
+mod binary;
+mod generator;
+mod merger;
+mod stream;
+mod validation;
+
+pub use binary::*;
+pub use generator::*;
+pub use merger::*;
+pub use stream::*;
+pub use validation::*;
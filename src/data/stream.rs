@@ -0,0 +1,102 @@
+//! Format agnostic readers. Flows open market data through these so that
+//! both the csv layout and the binary format can be replayed.
+
+use crate::{
+    data::{is_binary, open_validated, BinaryFile, BinaryRecord, CleaningPolicy},
+    engine::{Signal, Snap, Trade},
+    error::MyError,
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+pub type EventStream<T> = Box<dyn Iterator<Item = Result<T, MyError>>>;
+
+/// # Errors
+///
+/// Will return `Err` if file read fails or binary header is malformed
+pub fn open_stream<T>(path: impl AsRef<Path>) -> Result<EventStream<T>, MyError>
+where
+    T: BinaryRecord + DeserializeOwned + 'static,
+{
+    let path = path.as_ref();
+    let mut magic = [0; 4];
+    let read = File::open(path)?.read(&mut magic)?;
+    if is_binary(&magic[..read]) {
+        Ok(Box::new(BinaryFile::open(path)?.into_records::<T>()))
+    } else {
+        Ok(Box::new(
+            csv::Reader::from_path(path)?
+                .into_deserialize::<T>()
+                .map(|row| row.map_err(MyError::from)),
+        ))
+    }
+}
+
+/// # Errors
+///
+/// Will return `Err` if file read fails
+pub fn snap_stream(path: impl AsRef<Path>) -> Result<EventStream<Snap>, MyError> {
+    open_stream(path)
+}
+
+/// # Errors
+///
+/// Will return `Err` if file read fails
+pub fn trade_stream(path: impl AsRef<Path>) -> Result<EventStream<Trade>, MyError> {
+    open_stream(path)
+}
+
+/// # Errors
+///
+/// Will return `Err` if file read fails
+pub fn signal_stream(path: impl AsRef<Path>) -> Result<EventStream<Signal>, MyError> {
+    open_stream(path)
+}
+
+/// Signal file replayed alongside market data
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SignalSource {
+    pub name: String,
+    pub path: PathBuf,
+    // Nanoseconds between signal generation and its arrival to the strategy
+    #[serde(default)]
+    pub delay: u64,
+}
+
+impl SignalSource {
+    pub fn new(name: impl Into<String>, path: impl Into<PathBuf>, delay: u64) -> Self {
+        Self {
+            name: name.into(),
+            path: path.into(),
+            delay,
+        }
+    }
+
+    /// Signals are validated under `policy`, delayed and tagged with `source`,
+    /// the index of this source within the run. The report is logged.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if file read fails
+    pub fn open(
+        &self,
+        source: u16,
+        policy: CleaningPolicy,
+    ) -> Result<EventStream<Signal>, MyError> {
+        let delay = self.delay;
+        let (signals, report) = open_validated::<Signal>(&self.path, policy)?;
+        report.log();
+        Ok(Box::new(signals.map(move |signal| {
+            signal.map(|mut signal| {
+                signal.exch_epoch += delay;
+                signal.source = source;
+                signal
+            })
+        })))
+    }
+}
@@ -0,0 +1,432 @@
+//! Market data checks run before a replay. Every record is classified,
+//! then kept, repaired or dropped according to a `CleaningPolicy`.
+
+use crate::{
+    data::{open_stream, BinaryRecord, BinaryWriter, EventMerger, EventStream, Header, StreamEnd},
+    engine::{Side, Signal, Snap, Trade},
+    error::MyError,
+};
+use log::{info, warn};
+use serde::de::DeserializeOwned;
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Clone, Copy)]
+pub enum Anomaly {
+    // Row could not be parsed or holds a non finite value
+    Malformed,
+    // Level with zero price or qty
+    ZeroQty,
+    // Best bid at or above best ask
+    CrossedBook,
+    // Same epoch as the previous record
+    DuplicateEpoch,
+    // Epoch before the previous record
+    OutOfOrder,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Default)]
+pub enum Action {
+    Keep,
+    #[default]
+    Repair,
+    Drop,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CleaningPolicy {
+    pub zero_qty: Action,
+    pub crossed_book: Action,
+    pub duplicate_epoch: Action,
+    pub out_of_order: Action,
+}
+
+impl CleaningPolicy {
+    /// Classifies records without touching them
+    pub const fn report_only() -> Self {
+        Self {
+            zero_qty: Action::Keep,
+            crossed_book: Action::Keep,
+            duplicate_epoch: Action::Keep,
+            out_of_order: Action::Keep,
+        }
+    }
+
+    pub const fn drop_all() -> Self {
+        Self {
+            zero_qty: Action::Drop,
+            crossed_book: Action::Drop,
+            duplicate_epoch: Action::Drop,
+            out_of_order: Action::Drop,
+        }
+    }
+
+    const fn action(self, anomaly: Anomaly) -> Action {
+        match anomaly {
+            | Anomaly::Malformed => Action::Drop,
+            | Anomaly::ZeroQty => self.zero_qty,
+            | Anomaly::CrossedBook => self.crossed_book,
+            | Anomaly::DuplicateEpoch => self.duplicate_epoch,
+            | Anomaly::OutOfOrder => self.out_of_order,
+        }
+    }
+}
+
+/// Record that can be checked by `Validator`
+pub trait Validate {
+    // Whether every record needs an epoch of its own,
+    // false lets e.g. several prints share one ns
+    const UNIQUE_EPOCH: bool;
+
+    fn exch_epoch(&self) -> u64;
+
+    fn set_exch_epoch(&mut self, exch_epoch: u64);
+
+    /// Anomalies in the record content itself
+    fn inspect(&self) -> Vec<Anomaly>;
+
+    /// Returns `false` if the record can not be repaired
+    fn repair(&mut self, anomaly: Anomaly) -> bool;
+}
+
+impl Validate for Snap {
+    const UNIQUE_EPOCH: bool = true;
+
+    fn exch_epoch(&self) -> u64 {
+        self.exch_epoch
+    }
+
+    fn set_exch_epoch(&mut self, exch_epoch: u64) {
+        self.exch_epoch = exch_epoch;
+    }
+
+    fn inspect(&self) -> Vec<Anomaly> {
+        let mut anomalies = Vec::new();
+        if self.vec.iter().any(|l| l.qty == 0 || l.price == 0) {
+            anomalies.push(Anomaly::ZeroQty);
+        }
+        if let (Some(bid), Some(ask)) = best_prices(self) {
+            if bid >= ask {
+                anomalies.push(Anomaly::CrossedBook);
+            }
+        }
+        anomalies
+    }
+
+    fn repair(&mut self, anomaly: Anomaly) -> bool {
+        match anomaly {
+            | Anomaly::ZeroQty => {
+                self.vec.retain(|l| l.qty > 0 && l.price > 0);
+                true
+            }
+            | Anomaly::CrossedBook => {
+                // Drop every level that crosses the opposite touch
+                let (Some(bid), Some(ask)) = best_prices(self) else {
+                    return true;
+                };
+                self.vec.retain(|l| match l.side {
+                    | Side::Bid => l.price < ask,
+                    | Side::Ask => l.price > bid,
+                });
+                !self.vec.is_empty()
+            }
+            | _ => false,
+        }
+    }
+}
+
+fn best_prices(snap: &Snap) -> (Option<u32>, Option<u32>) {
+    let live = snap.vec.iter().filter(|l| l.qty > 0 && l.price > 0);
+    let bid = live
+        .clone()
+        .filter(|l| l.side == Side::Bid)
+        .map(|l| l.price)
+        .max();
+    let ask = live.filter(|l| l.side == Side::Ask).map(|l| l.price).min();
+    (bid, ask)
+}
+
+impl Validate for Trade {
+    const UNIQUE_EPOCH: bool = false;
+
+    fn exch_epoch(&self) -> u64 {
+        self.exch_epoch
+    }
+
+    fn set_exch_epoch(&mut self, exch_epoch: u64) {
+        self.exch_epoch = exch_epoch;
+    }
+
+    fn inspect(&self) -> Vec<Anomaly> {
+        if self.qty == 0 || self.price == 0 {
+            vec![Anomaly::ZeroQty]
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn repair(&mut self, _anomaly: Anomaly) -> bool {
+        // An empty print carries no information
+        false
+    }
+}
+
+impl Validate for Signal {
+    const UNIQUE_EPOCH: bool = true;
+
+    fn exch_epoch(&self) -> u64 {
+        self.exch_epoch
+    }
+
+    fn set_exch_epoch(&mut self, exch_epoch: u64) {
+        self.exch_epoch = exch_epoch;
+    }
+
+    fn inspect(&self) -> Vec<Anomaly> {
+        if self.value.is_finite() {
+            Vec::new()
+        } else {
+            vec![Anomaly::Malformed]
+        }
+    }
+
+    fn repair(&mut self, _anomaly: Anomaly) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub source: Option<PathBuf>,
+    pub total: usize,
+    pub kept: usize,
+    pub repaired: usize,
+    pub dropped: usize,
+    pub anomalies: BTreeMap<Anomaly, usize>,
+    pub first_epoch: Option<u64>,
+    pub last_epoch: Option<u64>,
+}
+
+impl ValidationReport {
+    pub fn count(&self, anomaly: Anomaly) -> usize {
+        self.anomalies.get(&anomaly).copied().unwrap_or_default()
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.anomalies.is_empty()
+    }
+
+    /// Logs the report, as a warning if anything was found
+    pub fn log(&self) {
+        if self.is_clean() {
+            info!(target: "validation", "\n{self}");
+        } else {
+            warn!(target: "validation", "\n{self}");
+        }
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(source) = &self.source {
+            writeln!(f, "File        = {}", source.display())?;
+        }
+        write!(
+            f,
+            "Records     = {}\nKept        = {}\nRepaired    = {}\nDropped     = {}\nEpochs      = {:?}..{:?}",
+            self.total, self.kept, self.repaired, self.dropped, self.first_epoch, self.last_epoch
+        )?;
+        for (anomaly, count) in &self.anomalies {
+            write!(f, "\n{:<12}= {count}", format!("{anomaly:?}"))?;
+        }
+        Ok(())
+    }
+}
+
+pub struct Validator {
+    pub policy: CleaningPolicy,
+    pub report: ValidationReport,
+    last_epoch: Option<u64>,
+}
+
+impl Validator {
+    pub fn new(policy: CleaningPolicy) -> Self {
+        Self {
+            policy,
+            report: ValidationReport::default(),
+            last_epoch: None,
+        }
+    }
+
+    fn advance(&mut self, epoch: u64) {
+        self.last_epoch = Some(self.last_epoch.map_or(epoch, |last| last.max(epoch)));
+    }
+
+    fn note(&mut self, anomaly: Anomaly) -> Action {
+        *self.report.anomalies.entry(anomaly).or_default() += 1;
+        self.policy.action(anomaly)
+    }
+
+    /// Classifies one record and applies the policy.
+    /// Returns `None` if the record has to be dropped.
+    pub fn check<T: Validate>(&mut self, record: Result<T, MyError>) -> Option<T> {
+        self.report.total += 1;
+        let Ok(mut record) = record else {
+            self.note(Anomaly::Malformed);
+            self.report.dropped += 1;
+            return None;
+        };
+        let mut anomalies = record.inspect();
+        match self.last_epoch {
+            | Some(last) if record.exch_epoch() < last => anomalies.push(Anomaly::OutOfOrder),
+            | Some(last) if T::UNIQUE_EPOCH && record.exch_epoch() == last => {
+                anomalies.push(Anomaly::DuplicateEpoch);
+            }
+            | _ => {}
+        }
+        let mut repaired = false;
+        for anomaly in anomalies {
+            let ok = match self.note(anomaly) {
+                | Action::Keep => true,
+                | Action::Drop => false,
+                | Action::Repair => {
+                    repaired = true;
+                    match (anomaly, self.last_epoch) {
+                        // Shift into place right after the previous record
+                        | (Anomaly::OutOfOrder | Anomaly::DuplicateEpoch, Some(last)) => {
+                            record.set_exch_epoch(last + u64::from(T::UNIQUE_EPOCH));
+                            true
+                        }
+                        | _ => record.repair(anomaly),
+                    }
+                }
+            };
+            if !ok {
+                // Only accepted records move the epoch later ones are checked against
+                self.report.dropped += 1;
+                return None;
+            }
+        }
+        if repaired {
+            self.report.repaired += 1;
+        }
+        self.report.kept += 1;
+        let epoch = record.exch_epoch();
+        self.advance(epoch);
+        self.report.first_epoch.get_or_insert(epoch);
+        self.report.last_epoch = Some(epoch);
+        Some(record)
+    }
+
+    pub fn clean<T: Validate>(
+        &mut self,
+        records: impl IntoIterator<Item = Result<T, MyError>>,
+    ) -> Vec<T> {
+        records
+            .into_iter()
+            .filter_map(|record| self.check(record))
+            .collect()
+    }
+}
+
+/// Reads a whole file in either format and validates it.
+///
+/// # Errors
+///
+/// Will return `Err` if file can not be opened
+pub fn validate_file<T>(
+    path: impl AsRef<Path>,
+    policy: CleaningPolicy,
+) -> Result<(Vec<T>, ValidationReport), MyError>
+where
+    T: Validate + BinaryRecord + DeserializeOwned + 'static,
+{
+    let mut validator = Validator::new(policy);
+    let records = validator.clean(open_stream::<T>(&path)?);
+    validator.report.source = Some(path.as_ref().to_path_buf());
+    Ok((records, validator.report))
+}
+
+/// Checks the whole of `path` under `policy` for the report, then opens it
+/// again with the same policy applied to every record as it is read.
+/// The file is read twice, its records are not kept in memory.
+///
+/// # Errors
+///
+/// Will return `Err` if file can not be opened
+pub fn open_validated<T>(
+    path: impl AsRef<Path>,
+    policy: CleaningPolicy,
+) -> Result<(EventStream<T>, ValidationReport), MyError>
+where
+    T: Validate + BinaryRecord + DeserializeOwned + 'static,
+{
+    let mut validator = Validator::new(policy);
+    for record in open_stream::<T>(&path)? {
+        let _ = validator.check(record);
+    }
+    let mut report = validator.report;
+    report.source = Some(path.as_ref().to_path_buf());
+    let mut validator = Validator::new(policy);
+    let records =
+        open_stream::<T>(&path)?.filter_map(move |record| validator.check(record).map(Ok));
+    Ok((Box::new(records), report))
+}
+
+/// Snapshots and prints of a replay, both stopping it once they run dry.
+/// Each file is validated under `policy` and its report logged before the replay.
+///
+/// # Errors
+///
+/// Will return `Err` if either file can not be opened
+pub fn open_market_data(
+    ob_path: impl AsRef<Path>,
+    orders_path: impl AsRef<Path>,
+    policy: CleaningPolicy,
+) -> Result<EventMerger, MyError> {
+    let (snaps, snap_report) = open_validated::<Snap>(ob_path, policy)?;
+    let (trades, trade_report) = open_validated::<Trade>(orders_path, policy)?;
+    snap_report.log();
+    trade_report.log();
+    Ok(EventMerger::default()
+        .with_stream(snaps, StreamEnd::Stop)
+        .with_stream(trades, StreamEnd::Stop))
+}
+
+/// Validates `in_path` and writes the surviving records to `out_path`
+/// in the binary format, ready to be replayed by any flow.
+///
+/// # Errors
+///
+/// Will return `Err` if either file fails or a snapshot is deeper
+/// than `u8::MAX` levels
+pub fn clean_file<T>(
+    in_path: impl AsRef<Path>,
+    out_path: impl AsRef<Path>,
+    ticker_id: u64,
+    policy: CleaningPolicy,
+) -> Result<ValidationReport, MyError>
+where
+    T: Validate + BinaryRecord + DeserializeOwned + 'static,
+{
+    let (records, report) = validate_file::<T>(in_path, policy)?;
+    let depth = records
+        .iter()
+        .map(BinaryRecord::depth)
+        .max()
+        .unwrap_or_default();
+    let depth = u8::try_from(depth)
+        .map_err(|_| MyError::FormatError(format!("snapshot depth {depth} over {}", u8::MAX)))?;
+    let out = BufWriter::new(File::create(out_path)?);
+    let mut writer = BinaryWriter::new(out, Header::new(T::KIND, depth, ticker_id))?;
+    for record in &records {
+        writer.write(record)?;
+    }
+    writer.finish()?;
+    Ok(report)
+}
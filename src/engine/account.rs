@@ -1,16 +1,133 @@
+use crate::engine::{LedgerEntry, Liquidity, LotMatching, Money, PositionLedger, Side, Ticker};
+
+/// A single own fill with the fee paid for it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FillRecord {
+    pub side: Side,
+    pub qty: u32,
+    pub price: u32,
+    pub liquidity: Liquidity,
+    pub fee: Money,
+}
+
 #[derive(Default)]
 pub struct TradingAccount {
-    pub balance: f32,
-    pub cumulative_volume: u32,
+    pub balance: Money,
+    // Balance the account was opened with, pnl is counted from it
+    pub initial_balance: Money,
+    // Traded notional, price units
+    pub cumulative_volume: u64,
     pub trade_count: u32,
+    // Fees paid net of rebates, included in `balance`
+    pub fees: Money,
+    pub fills: Vec<FillRecord>,
+    // Epoch of the event being processed, stamps ledger entries
+    pub clock: u64,
+    pub ledger: PositionLedger,
+    // Initial balance with realized pnl, fees and variation margin booked
+    pub collateral: Money,
+    // Clearings of the calendar settled so far
+    pub clearings: Option<u64>,
 }
 
 impl TradingAccount {
-    pub const fn new(initial_balance: f32) -> Self {
+    pub const fn new(initial_balance: Money) -> Self {
         Self {
             balance: initial_balance,
+            initial_balance,
             cumulative_volume: 0,
             trade_count: 0,
+            fees: Money::ZERO,
+            fills: Vec::new(),
+            clock: 0,
+            ledger: PositionLedger::new(LotMatching::Fifo),
+            collateral: initial_balance,
+            clearings: None,
+        }
+    }
+
+    /// Balance with `position` marked to market at `mark`
+    pub fn marked(&self, position: i32, mark: f32) -> Money {
+        self.balance + Money::from_f64(f64::from(mark) * f64::from(position))
+    }
+
+    /// Pnl since the account was opened with `position` marked at `mark`
+    pub fn pnl(&self, position: i32, mark: f32) -> Money {
+        self.marked(position, mark) - self.initial_balance
+    }
+
+    /// Books a fill at `clock`, the fee tier is picked by the volume traded before it.
+    /// Trade count is left to the caller, one order may fill many times.
+    pub fn record_fill(
+        &mut self,
+        ticker: &Ticker,
+        side: Side,
+        qty: u32,
+        price: u32,
+        liquidity: Liquidity,
+    ) -> Money {
+        let fee = ticker
+            .fees
+            .fee(liquidity, qty, price, self.cumulative_volume);
+        let value = Money::notional(qty, price);
+        match side {
+            | Side::Bid => self.balance -= value + fee,
+            | Side::Ask => self.balance += value - fee,
+        }
+        self.cumulative_volume += u64::from(qty) * u64::from(price);
+        self.fees += fee;
+        let realized = self.ledger.record(LedgerEntry {
+            epoch: self.clock,
+            ticker_id: ticker.ticker_id,
+            side,
+            qty,
+            price,
+            fee,
+        });
+        self.collateral += realized - fee;
+        self.fills.push(FillRecord {
+            side,
+            qty,
+            price,
+            liquidity,
+            fee,
+        });
+        fee
+    }
+
+    /// Books the variation margin of the position at the clearing `price` at `clock`
+    pub fn settle(&mut self, ticker_id: u64, price: u32) -> Money {
+        let variation = self.ledger.settle(ticker_id, price, self.clock);
+        self.collateral += variation;
+        variation
+    }
+
+    /// Collateral with the position in `ticker_id` marked to market at `mark`
+    pub fn equity(&self, ticker_id: u64, mark: f32) -> Money {
+        self.collateral
+            + self
+                .ledger
+                .position(ticker_id)
+                .map_or(Money::ZERO, |position| position.unrealized(mark))
+    }
+
+    /// Initial margin held against the open position
+    pub fn blocked_margin(&self, ticker: &Ticker) -> Money {
+        ticker.initial_margin * i64::from(self.ledger.qty(ticker.ticker_id).unsigned_abs())
+    }
+
+    /// Equity left once the initial margin is blocked
+    pub fn free_margin(&self, ticker: &Ticker, mark: f32) -> Money {
+        self.equity(ticker.ticker_id, mark) - self.blocked_margin(ticker)
+    }
+
+    /// Share of equity blocked as margin, infinite once equity is gone
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn margin_utilisation(&self, ticker: &Ticker, mark: f32) -> f32 {
+        let equity = self.equity(ticker.ticker_id, mark);
+        if equity <= Money::ZERO {
+            return f32::INFINITY;
         }
+        (self.blocked_margin(ticker).units() / equity.units()) as f32
     }
 }
@@ -1,21 +1,42 @@
-use crate::engine::{Order, Side, Snap};
+use crate::engine::{Side, Snap};
 use serde::{
     de::{Deserializer, Error, MapAccess, Visitor},
     Deserialize, Serialize,
 };
 use std::fmt;
 
-#[derive(Debug, Clone, Copy, Serialize, Default)]
+#[derive(Debug, Clone, Copy, Serialize, Default, PartialEq, Eq)]
 pub struct LimitOrder {
     pub side: Side,
     pub price: u32,
     pub qty: u32,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Default)]
+/// Historical trade print. Unlike `Order` it never rests in the book:
+/// replay treats it as an immediate execution by the aggressor `side`.
+#[derive(Debug, Clone, Copy, Serialize, Default, PartialEq, Eq)]
+pub struct Trade {
+    pub exch_epoch: u64,
+    pub price: u32,
+    pub qty: u32,
+    // Aggressor side
+    pub side: Side,
+    pub trade_id: Option<u64>,
+}
+
+/// Alpha signal. `value` carries direction and strength,
+/// `side` is kept for strategies that only care about direction.
+#[derive(Debug, Clone, Copy, Serialize, Default, PartialEq)]
 pub struct Signal {
     pub exch_epoch: u64,
     pub side: Side,
+    // Index of the `SignalSource` the signal was read from
+    pub source: u16,
+    pub value: f32,
+    // Nanoseconds the signal stays relevant for
+    pub horizon: Option<u64>,
+    // None applies to the traded instrument
+    pub instrument_id: Option<u64>,
 }
 
 impl<'de> Deserialize<'de> for Snap {
@@ -397,7 +418,7 @@ impl<'de> Deserialize<'de> for Snap {
                 vec.push(LimitOrder { side, price, qty });
 
                 Ok(Snap {
-                    exch_epoch: exch_epoch.unwrap(),
+                    exch_epoch: exch_epoch.ok_or_else(|| Error::missing_field("exch_epoch"))?,
                     vec,
                 })
             }
@@ -407,7 +428,7 @@ impl<'de> Deserialize<'de> for Snap {
     }
 }
 
-impl<'de> Deserialize<'de> for Order {
+impl<'de> Deserialize<'de> for Trade {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
@@ -415,10 +436,10 @@ impl<'de> Deserialize<'de> for Order {
         struct EventVisitor;
 
         impl<'de> Visitor<'de> for EventVisitor {
-            type Value = Order;
+            type Value = Trade;
 
             fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str("struct Event")
+                formatter.write_str("struct Trade")
             }
 
             fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
@@ -429,6 +450,7 @@ impl<'de> Deserialize<'de> for Order {
                 let mut side: Option<u8> = None;
                 let mut price: Option<u32> = None;
                 let mut qty: Option<u32> = None;
+                let mut trade_id: Option<u64> = None;
 
                 while let Some(key) = map.next_key()? {
                     match key {
@@ -456,24 +478,33 @@ impl<'de> Deserialize<'de> for Order {
                             }
                             qty = Some(map.next_value()?);
                         }
+                        | "trade_id" => {
+                            if trade_id.is_some() {
+                                return Err(Error::duplicate_field("trade_id"));
+                            }
+                            trade_id = map.next_value()?;
+                        }
                         | _ => {
                             // Ignore unknown fields
                             let _: serde::de::IgnoredAny = map.next_value()?;
                         }
                     }
                 }
-                let side = match side.unwrap() {
+                // Aggressor side: 0 is a sell print, 1 is a buy print
+                let side = match side.ok_or_else(|| Error::missing_field("side"))? {
                     | 0 => Side::Ask,
                     | 1 => Side::Bid,
-                    | _ => unreachable!(),
+                    | other => {
+                        return Err(Error::custom(format!("invalid trade side {other}")));
+                    }
                 };
 
-                Ok(Order {
-                    // HACK
-                    id: exch_epoch.unwrap(),
+                Ok(Trade {
+                    exch_epoch: exch_epoch.ok_or_else(|| Error::missing_field("exch_epoch"))?,
+                    price: price.ok_or_else(|| Error::missing_field("price"))?,
+                    qty: qty.ok_or_else(|| Error::missing_field("qty"))?,
                     side,
-                    price: price.unwrap(),
-                    qty: qty.unwrap(),
+                    trade_id,
                 })
             }
         }
@@ -502,6 +533,9 @@ impl<'de> Deserialize<'de> for Signal {
             {
                 let mut exch_epoch: Option<u64> = None;
                 let mut side: Option<i8> = None;
+                let mut value: Option<f32> = None;
+                let mut horizon: Option<u64> = None;
+                let mut instrument_id: Option<u64> = None;
 
                 while let Some(key) = map.next_key()? {
                     match key {
@@ -515,7 +549,25 @@ impl<'de> Deserialize<'de> for Signal {
                             if side.is_some() {
                                 return Err(Error::duplicate_field("side"));
                             }
-                            side = Some(map.next_value()?);
+                            side = map.next_value()?;
+                        }
+                        | "value" => {
+                            if value.is_some() {
+                                return Err(Error::duplicate_field("value"));
+                            }
+                            value = map.next_value()?;
+                        }
+                        | "horizon" => {
+                            if horizon.is_some() {
+                                return Err(Error::duplicate_field("horizon"));
+                            }
+                            horizon = map.next_value()?;
+                        }
+                        | "instrument_id" => {
+                            if instrument_id.is_some() {
+                                return Err(Error::duplicate_field("instrument_id"));
+                            }
+                            instrument_id = map.next_value()?;
                         }
                         | _ => {
                             // Ignore unknown fields
@@ -523,15 +575,30 @@ impl<'de> Deserialize<'de> for Signal {
                         }
                     }
                 }
-                let side = match side.unwrap() {
-                    | -1 => Side::Ask,
-                    | 1 => Side::Bid,
-                    | _ => unreachable!(),
+                // Direction comes from `side` and falls back to the sign of `value`,
+                // strength defaults to a unit signal
+                let side = match (side, value) {
+                    | (Some(-1), _) => Side::Ask,
+                    | (Some(1), _) => Side::Bid,
+                    | (Some(other), _) => {
+                        return Err(Error::custom(format!("invalid signal side {other}")));
+                    }
+                    | (None, Some(value)) if value < 0.0 => Side::Ask,
+                    | (None, Some(value)) if value > 0.0 => Side::Bid,
+                    | (None, _) => return Err(Error::missing_field("side")),
                 };
+                let value = value.unwrap_or(match side {
+                    | Side::Bid => 1.0,
+                    | Side::Ask => -1.0,
+                });
 
                 Ok(Signal {
-                    exch_epoch: exch_epoch.unwrap(),
+                    exch_epoch: exch_epoch.ok_or_else(|| Error::missing_field("exch_epoch"))?,
                     side,
+                    source: 0,
+                    value,
+                    horizon,
+                    instrument_id,
                 })
             }
         }
@@ -0,0 +1,151 @@
+//! Exchange and clearing fees. Rates are configured as floats,
+//! charged amounts are `Money` as `TradingAccount::balance`.
+
+use crate::engine::Money;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Liquidity {
+    Maker,
+    Taker,
+}
+
+/// Negative fees are rebates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeeRates {
+    // Fraction of the traded value
+    pub maker_rate: f32,
+    pub taker_rate: f32,
+    pub maker_per_contract: f32,
+    pub taker_per_contract: f32,
+}
+
+impl FeeRates {
+    pub const ZERO: Self = Self::proportional(0.0, 0.0);
+
+    pub const fn proportional(maker_rate: f32, taker_rate: f32) -> Self {
+        Self {
+            maker_rate,
+            taker_rate,
+            maker_per_contract: 0.0,
+            taker_per_contract: 0.0,
+        }
+    }
+}
+
+/// Rates that apply once cumulative turnover reaches `min_turnover`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeeTier {
+    pub min_turnover: u64,
+    pub rates: FeeRates,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeeSchedule {
+    // Rates below the first tier
+    pub base: FeeRates,
+    // Sorted by `min_turnover`
+    pub tiers: &'static [FeeTier],
+    // Charged on every contract on top of the exchange fee, price units
+    pub clearing_per_contract: f32,
+    // Smallest exchange fee of a fill in price units, rebates are paid in full
+    pub min_fee: f32,
+}
+
+impl Default for FeeSchedule {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+impl FeeSchedule {
+    pub const NONE: Self = Self::proportional(0.0, 0.0);
+
+    pub const fn proportional(maker_rate: f32, taker_rate: f32) -> Self {
+        Self {
+            base: FeeRates::proportional(maker_rate, taker_rate),
+            tiers: &[],
+            clearing_per_contract: 0.0,
+            min_fee: 0.0,
+        }
+    }
+
+    /// Rates of the highest tier reached by `turnover`
+    pub fn rates(&self, turnover: u64) -> FeeRates {
+        self.tiers
+            .iter()
+            .rev()
+            .find(|tier| turnover >= tier.min_turnover)
+            .map_or(self.base, |tier| tier.rates)
+    }
+
+    /// Fee of a single fill after `turnover` price units were already traded
+    pub fn fee(&self, liquidity: Liquidity, qty: u32, price: u32, turnover: u64) -> Money {
+        let rates = self.rates(turnover);
+        let (rate, per_contract) = match liquidity {
+            | Liquidity::Maker => (rates.maker_rate, rates.maker_per_contract),
+            | Liquidity::Taker => (rates.taker_rate, rates.taker_per_contract),
+        };
+        let value = Money::notional(qty, price).units();
+        let exchange = Money::from_f64(
+            value.mul_add(f64::from(rate), f64::from(per_contract) * f64::from(qty)),
+        );
+        let exchange = if exchange > Money::ZERO {
+            exchange.max(Money::from_f64(f64::from(self.min_fee)))
+        } else {
+            exchange
+        };
+        exchange + Money::from_f64(f64::from(self.clearing_per_contract) * f64::from(qty))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    const TIERED: FeeSchedule = FeeSchedule {
+        base: FeeRates {
+            maker_rate: -0.000_1,
+            taker_rate: 0.001,
+            maker_per_contract: 0.0,
+            taker_per_contract: 0.5,
+        },
+        tiers: &[FeeTier {
+            min_turnover: 10_000,
+            rates: FeeRates::proportional(-0.000_2, 0.000_5),
+        }],
+        clearing_per_contract: 0.1,
+        min_fee: 2.0,
+    };
+
+    #[rstest]
+    // 1000 * 0.001 + 10 * 0.5 + 10 * 0.1
+    #[case(Liquidity::Taker, 10, 100, 0, 7.0)]
+    // Rebate of 0.1 is paid in full, clearing is still charged
+    #[case(Liquidity::Maker, 10, 100, 0, 0.9)]
+    // Minimum fee
+    #[case(Liquidity::Taker, 1, 100, 20_000, 2.1)]
+    // Upper tier: 10000 * 0.0005 + 10 * 0.1
+    #[case(Liquidity::Taker, 10, 1_000, 10_000, 6.0)]
+    #[case(Liquidity::Maker, 10, 1_000, 10_000, -1.0)]
+    fn fee(
+        #[case] liquidity: Liquidity,
+        #[case] qty: u32,
+        #[case] price: u32,
+        #[case] turnover: u64,
+        #[case] expected: f64,
+    ) {
+        let fee = TIERED.fee(liquidity, qty, price, turnover);
+        assert_eq!(fee, Money::from_f64(expected));
+    }
+
+    #[test]
+    fn no_fees() {
+        assert_eq!(
+            FeeSchedule::NONE.fee(Liquidity::Taker, 10, 100, 0),
+            Money::ZERO
+        );
+        assert_eq!(FeeSchedule::default().rates(u64::MAX), FeeRates::ZERO);
+    }
+}
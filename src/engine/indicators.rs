@@ -56,3 +56,113 @@ impl EmaMidprice {
 pub fn midprice(bid: u32, ask: u32) -> f32 {
     (bid + ask) as f32 / 2.0
 }
+
+/// Variance of the mid per second, from exponentially weighted squared
+/// moves and the time they took. Weighting both keeps a burst of snapshots
+/// from inflating the estimate.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MidVolatility {
+    pub alpha: f32,
+    // Moves seen so far, the first mid only starts the series
+    pub samples: u32,
+    last: Option<(u64, f32)>,
+    squared_moves: f32,
+    seconds: f32,
+}
+
+impl MidVolatility {
+    pub const fn new(alpha: f32) -> Self {
+        Self {
+            alpha,
+            samples: 0,
+            last: None,
+            squared_moves: 0.0,
+            seconds: 0.0,
+        }
+    }
+
+    /// Feeds the mid at `epoch`, a mid at the same or an earlier epoch is ignored
+    #[allow(clippy::cast_precision_loss)]
+    pub fn observe(&mut self, epoch: u64, mid: f32) -> Option<f32> {
+        match self.last {
+            | None => self.last = Some((epoch, mid)),
+            | Some((last_epoch, last_mid)) if epoch > last_epoch => {
+                let squared_move = (mid - last_mid).powi(2);
+                let seconds = (epoch - last_epoch) as f32 / 1e9;
+                if self.samples == 0 {
+                    self.squared_moves = squared_move;
+                    self.seconds = seconds;
+                } else {
+                    self.squared_moves = self
+                        .alpha
+                        .mul_add(squared_move, (1.0 - self.alpha) * self.squared_moves);
+                    self.seconds = self
+                        .alpha
+                        .mul_add(seconds, (1.0 - self.alpha) * self.seconds);
+                }
+                self.samples += 1;
+                self.last = Some((epoch, mid));
+            }
+            | Some(_) => {}
+        }
+        self.variance()
+    }
+
+    // Price units squared per second
+    pub fn variance(&self) -> Option<f32> {
+        (self.samples > 0).then(|| self.squared_moves / self.seconds)
+    }
+}
+
+/// Order arrival intensity `A * exp(-k * depth)` of prints at `depth` from the mid.
+///
+/// Depths are then exponential with mean `1 / k`, so `k` comes from their
+/// exponentially weighted mean and `A` from the print rate.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ArrivalIntensity {
+    pub alpha: f32,
+    // Prints seen so far
+    pub samples: u32,
+    mean_depth: f32,
+    first_epoch: Option<u64>,
+    last_epoch: u64,
+}
+
+impl ArrivalIntensity {
+    pub const fn new(alpha: f32) -> Self {
+        Self {
+            alpha,
+            samples: 0,
+            mean_depth: 0.0,
+            first_epoch: None,
+            last_epoch: 0,
+        }
+    }
+
+    /// Feeds a print at `price` against the mid it traded at
+    pub fn observe(&mut self, epoch: u64, price: u32, mid: f32) {
+        #[allow(clippy::cast_precision_loss)]
+        let depth = (price as f32 - mid).abs();
+        self.mean_depth = if self.samples == 0 {
+            depth
+        } else {
+            self.alpha
+                .mul_add(depth, (1.0 - self.alpha) * self.mean_depth)
+        };
+        self.samples += 1;
+        self.first_epoch.get_or_insert(epoch);
+        self.last_epoch = self.last_epoch.max(epoch);
+    }
+
+    /// Decay of the intensity with depth, the mean depth is floored at `min_depth`
+    pub fn decay(&self, min_depth: f32) -> Option<f32> {
+        (self.samples > 0).then(|| 1.0 / self.mean_depth.max(min_depth))
+    }
+
+    /// Prints per second between the first and the last one
+    #[allow(clippy::cast_precision_loss)]
+    pub fn rate(&self) -> Option<f32> {
+        let seconds = (self.last_epoch - self.first_epoch?) as f32 / 1e9;
+        (seconds > 0.0).then(|| (self.samples - 1) as f32 / seconds)
+    }
+}
@@ -0,0 +1,155 @@
+//! Delays between the strategy and the exchange. Market data reaches
+//! the strategy `feed` nanoseconds after the exchange epoch, orders and
+//! cancels reach the matching engine `order` nanoseconds after the decision.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Latency {
+    Constant(u64),
+    // Uniform draw from recorded round trips
+    Empirical(Vec<u64>),
+    // `base` plus uniform noise up to `jitter`
+    Jitter { base: u64, jitter: u64 },
+}
+
+impl Default for Latency {
+    fn default() -> Self {
+        Self::Constant(0)
+    }
+}
+
+impl Latency {
+    fn sample(&self, rng: &mut StdRng) -> u64 {
+        match self {
+            | Self::Constant(latency) => *latency,
+            | Self::Empirical(samples) if samples.is_empty() => 0,
+            | Self::Empirical(samples) => samples[rng.random_range(0..samples.len())],
+            | Self::Jitter { base, jitter } => base + rng.random_range(0..=*jitter),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LatencyModel {
+    // Strategy to engine: orders and cancels
+    pub order: Latency,
+    // Engine to strategy: market data
+    pub feed: Latency,
+    rng: StdRng,
+}
+
+impl Default for LatencyModel {
+    fn default() -> Self {
+        Self::new(Latency::default(), Latency::default(), 0)
+    }
+}
+
+impl LatencyModel {
+    pub fn new(order: Latency, feed: Latency, seed: u64) -> Self {
+        Self {
+            order,
+            feed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn order_delay(&mut self) -> u64 {
+        self.order.sample(&mut self.rng)
+    }
+
+    pub fn feed_delay(&mut self) -> u64 {
+        self.feed.sample(&mut self.rng)
+    }
+}
+
+/// Strategy actions on their way to the engine.
+/// Actions are delivered in epoch order, same epoch ones in send order.
+#[derive(Debug)]
+pub struct InFlight<A> {
+    queue: BTreeMap<(u64, u64), A>,
+    seq: u64,
+}
+
+impl<A> Default for InFlight<A> {
+    fn default() -> Self {
+        Self {
+            queue: BTreeMap::new(),
+            seq: 0,
+        }
+    }
+}
+
+impl<A> InFlight<A> {
+    pub fn push(&mut self, delivery_epoch: u64, action: A) {
+        self.queue.insert((delivery_epoch, self.seq), action);
+        self.seq += 1;
+    }
+
+    /// Next action due at or before `epoch` with its delivery epoch
+    pub fn pop_due(&mut self, epoch: u64) -> Option<(u64, A)> {
+        if self.next_epoch()? <= epoch {
+            self.queue
+                .pop_first()
+                .map(|((delivery_epoch, _), action)| (delivery_epoch, action))
+        } else {
+            None
+        }
+    }
+
+    pub fn next_epoch(&self) -> Option<u64> {
+        self.queue.keys().next().map(|&(epoch, _)| epoch)
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn delivery_order() {
+        let mut in_flight = InFlight::default();
+        in_flight.push(20, "late");
+        in_flight.push(10, "first");
+        in_flight.push(10, "second");
+        assert_eq!(in_flight.pop_due(5), None);
+        assert_eq!(in_flight.pop_due(15), Some((10, "first")));
+        assert_eq!(in_flight.pop_due(15), Some((10, "second")));
+        assert_eq!(in_flight.pop_due(15), None);
+        assert_eq!(in_flight.pop_due(u64::MAX), Some((20, "late")));
+        assert!(in_flight.is_empty());
+    }
+
+    #[test]
+    fn seeded_samples() {
+        let model = || {
+            LatencyModel::new(
+                Latency::Jitter {
+                    base: 100,
+                    jitter: 50,
+                },
+                Latency::Empirical(vec![7, 9]),
+                1,
+            )
+        };
+        let (mut a, mut b) = (model(), model());
+        for _ in 0..20 {
+            let delay = a.order_delay();
+            assert!((100..=150).contains(&delay));
+            assert_eq!(delay, b.order_delay());
+            assert!([7, 9].contains(&a.feed_delay()));
+            let _ = b.feed_delay();
+        }
+        assert_eq!(LatencyModel::default().order_delay(), 0);
+    }
+}
@@ -0,0 +1,340 @@
+//! Positions built from own fills. Every fill is kept as a ledger entry,
+//! open quantity is kept as lots that closing fills are matched against.
+//! Prices are in price units of the book, pnl is `Money` as `TradingAccount::balance`.
+
+use crate::engine::{Money, Side};
+use std::collections::{BTreeMap, VecDeque};
+
+/// Order in which closing fills consume open lots
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LotMatching {
+    #[default]
+    Fifo,
+    Lifo,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lot {
+    pub qty: u32,
+    pub price: u32,
+    pub epoch: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LedgerEntry {
+    pub epoch: u64,
+    pub ticker_id: u64,
+    pub side: Side,
+    pub qty: u32,
+    pub price: u32,
+    pub fee: Money,
+}
+
+/// Variation margin booked at a clearing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Settlement {
+    pub epoch: u64,
+    pub ticker_id: u64,
+    // Clearing price, price units
+    pub price: u32,
+    pub variation: Money,
+}
+
+/// Open lots and booked results of a single instrument
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Position {
+    // Positive for long
+    pub qty: i32,
+    pub realized: Money,
+    pub fees: Money,
+    // All on the side of `qty`
+    lots: VecDeque<Lot>,
+}
+
+impl Position {
+    pub fn lots(&self) -> impl Iterator<Item = &Lot> {
+        self.lots.iter()
+    }
+
+    /// Volume weighted price of the open lots
+    pub fn avg_entry_price(&self) -> Option<f32> {
+        let (value, qty) = self.lots.iter().fold((0u64, 0u64), |(value, qty), lot| {
+            (
+                value + u64::from(lot.qty) * u64::from(lot.price),
+                qty + u64::from(lot.qty),
+            )
+        });
+        (qty != 0).then(|| value as f32 / qty as f32)
+    }
+
+    /// Pnl of the open lots if they were closed at `mark`
+    pub fn unrealized(&self, mark: f32) -> Money {
+        let sign = f64::from(self.qty.signum());
+        self.lots
+            .iter()
+            .map(|lot| {
+                Money::from_f64(
+                    (f64::from(mark) - f64::from(lot.price)) * f64::from(lot.qty) * sign,
+                )
+            })
+            .sum()
+    }
+
+    /// Realized and unrealized pnl net of fees
+    pub fn pnl(&self, mark: f32) -> Money {
+        self.realized + self.unrealized(mark) - self.fees
+    }
+
+    /// Moves the unrealized pnl at the clearing `price` into `realized`
+    /// and carries the open lots over at that price. Returns the moved amount.
+    pub fn settle(&mut self, price: u32) -> Money {
+        let variation = self.unrealized(price as f32);
+        for lot in &mut self.lots {
+            lot.price = price;
+        }
+        self.realized += variation;
+        variation
+    }
+
+    // Returns pnl realized by the fill
+    fn apply(&mut self, entry: &LedgerEntry, matching: LotMatching) -> Money {
+        let sign = match entry.side {
+            | Side::Bid => 1,
+            | Side::Ask => -1,
+        };
+        let mut qty = entry.qty;
+        let mut realized = 0;
+        // A fill against the position closes lots first
+        while qty > 0 && self.qty * sign < 0 {
+            let lot = match matching {
+                | LotMatching::Fifo => self.lots.front_mut(),
+                | LotMatching::Lifo => self.lots.back_mut(),
+            }
+            .expect("open position has lots");
+            let matched = qty.min(lot.qty);
+            // Selling a long earns price - entry, buying back a short the reverse
+            realized += (i64::from(lot.price) - i64::from(entry.price))
+                * i64::from(matched)
+                * i64::from(sign);
+            lot.qty -= matched;
+            if lot.qty == 0 {
+                match matching {
+                    | LotMatching::Fifo => self.lots.pop_front(),
+                    | LotMatching::Lifo => self.lots.pop_back(),
+                };
+            }
+            self.qty += matched as i32 * sign;
+            qty -= matched;
+        }
+        // What is left opens or extends the position
+        if qty > 0 {
+            self.lots.push_back(Lot {
+                qty,
+                price: entry.price,
+                epoch: entry.epoch,
+            });
+            self.qty += qty as i32 * sign;
+        }
+        let realized = Money::from_units(realized);
+        self.realized += realized;
+        self.fees += entry.fee;
+        realized
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PositionLedger {
+    pub matching: LotMatching,
+    positions: BTreeMap<u64, Position>,
+    entries: Vec<LedgerEntry>,
+    settlements: Vec<Settlement>,
+}
+
+impl PositionLedger {
+    pub const fn new(matching: LotMatching) -> Self {
+        Self {
+            matching,
+            positions: BTreeMap::new(),
+            entries: Vec::new(),
+            settlements: Vec::new(),
+        }
+    }
+
+    /// Books a fill and returns the pnl it realized
+    pub fn record(&mut self, entry: LedgerEntry) -> Money {
+        let realized = self
+            .positions
+            .entry(entry.ticker_id)
+            .or_default()
+            .apply(&entry, self.matching);
+        self.entries.push(entry);
+        realized
+    }
+
+    /// Settles the position at the clearing `price` and returns the variation margin,
+    /// nothing is recorded for instruments without an open position
+    pub fn settle(&mut self, ticker_id: u64, price: u32, epoch: u64) -> Money {
+        let Some(position) = self.positions.get_mut(&ticker_id).filter(|p| p.qty != 0) else {
+            return Money::ZERO;
+        };
+        let variation = position.settle(price);
+        self.settlements.push(Settlement {
+            epoch,
+            ticker_id,
+            price,
+            variation,
+        });
+        variation
+    }
+
+    pub fn position(&self, ticker_id: u64) -> Option<&Position> {
+        self.positions.get(&ticker_id)
+    }
+
+    /// Signed open quantity, zero for instruments never traded
+    pub fn qty(&self, ticker_id: u64) -> i32 {
+        self.position(ticker_id).map_or(0, |position| position.qty)
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = (&u64, &Position)> {
+        self.positions.iter()
+    }
+
+    pub fn entries(&self) -> &[LedgerEntry] {
+        &self.entries
+    }
+
+    pub fn settlements(&self) -> &[Settlement] {
+        &self.settlements
+    }
+
+    /// The ledger as it was after every fill and settlement up to `epoch` inclusive.
+    /// A settlement goes after the fills of its epoch.
+    #[must_use]
+    pub fn at(&self, epoch: u64) -> Self {
+        let mut ledger = Self::new(self.matching);
+        let mut settlements = self
+            .settlements
+            .iter()
+            .filter(|settlement| settlement.epoch <= epoch)
+            .peekable();
+        for entry in self.entries.iter().filter(|entry| entry.epoch <= epoch) {
+            while let Some(settlement) = settlements.next_if(|s| s.epoch < entry.epoch) {
+                ledger.settle(settlement.ticker_id, settlement.price, settlement.epoch);
+            }
+            ledger.record(*entry);
+        }
+        for settlement in settlements {
+            ledger.settle(settlement.ticker_id, settlement.price, settlement.epoch);
+        }
+        ledger
+    }
+
+    pub fn realized(&self) -> Money {
+        self.positions
+            .values()
+            .map(|position| position.realized)
+            .sum()
+    }
+
+    pub fn fees(&self) -> Money {
+        self.positions.values().map(|position| position.fees).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn entry(epoch: u64, side: Side, qty: u32, price: u32) -> LedgerEntry {
+        LedgerEntry {
+            epoch,
+            ticker_id: 1,
+            side,
+            qty,
+            price,
+            fee: Money::from_f64(0.5),
+        }
+    }
+
+    fn fills() -> Vec<LedgerEntry> {
+        vec![
+            entry(1, Side::Bid, 10, 100),
+            entry(2, Side::Bid, 10, 110),
+            entry(3, Side::Ask, 15, 120),
+        ]
+    }
+
+    #[rstest]
+    // 10 * (120 - 100) + 5 * (120 - 110), 5 left at 110
+    #[case(LotMatching::Fifo, 250, 110)]
+    // 10 * (120 - 110) + 5 * (120 - 100), 5 left at 100
+    #[case(LotMatching::Lifo, 200, 100)]
+    fn lot_matching(
+        #[case] matching: LotMatching,
+        #[case] realized: i64,
+        #[case] entry_price: u32,
+    ) {
+        let mut ledger = PositionLedger::new(matching);
+        for fill in fills() {
+            ledger.record(fill);
+        }
+        let position = ledger.position(1).unwrap();
+        assert_eq!(position.qty, 5);
+        assert_eq!(position.realized, Money::from_units(realized));
+        assert_eq!(position.avg_entry_price(), Some(entry_price as f32));
+        assert_eq!(position.fees, Money::from_f64(1.5));
+        assert_eq!(
+            position.unrealized(130.0),
+            Money::from_units(5 * (130 - i64::from(entry_price)))
+        );
+    }
+
+    #[test]
+    fn flip_and_history() {
+        let mut ledger = PositionLedger::default();
+        for fill in fills() {
+            ledger.record(fill);
+        }
+        // Sells through the long into a short of 5 at 90
+        let realized = ledger.record(entry(4, Side::Ask, 10, 90));
+        assert_eq!(realized, Money::from_units(-100));
+        let position = ledger.position(1).unwrap();
+        assert_eq!(position.qty, -5);
+        assert_eq!(position.avg_entry_price(), Some(90.0));
+        assert_eq!(position.unrealized(80.0), Money::from_units(50));
+
+        let before = ledger.at(2);
+        assert_eq!(before.qty(1), 20);
+        assert_eq!(before.entries().len(), 2);
+        assert_eq!(before.realized(), Money::ZERO);
+        assert_eq!(ledger.at(u64::MAX), ledger);
+        assert_eq!(ledger.qty(2), 0);
+    }
+
+    #[test]
+    fn settlement() {
+        let mut ledger = PositionLedger::default();
+        for fill in fills() {
+            ledger.record(fill);
+        }
+        // 5 left at 110 settle at 125
+        assert_eq!(ledger.settle(1, 125, 4), Money::from_units(75));
+        let position = ledger.position(1).unwrap();
+        assert_eq!(position.avg_entry_price(), Some(125.0));
+        assert_eq!(position.unrealized(125.0), Money::ZERO);
+        // Selling at 130 realizes only the move since the clearing
+        assert_eq!(
+            ledger.record(entry(5, Side::Ask, 5, 130)),
+            Money::from_units(25)
+        );
+        assert_eq!(ledger.realized(), Money::from_units(250 + 75 + 25));
+        assert_eq!(ledger.settle(1, 140, 6), Money::ZERO);
+        assert_eq!(ledger.settlements().len(), 1);
+
+        assert_eq!(ledger.at(4).realized(), Money::from_units(250 + 75));
+        assert_eq!(ledger.at(u64::MAX), ledger);
+    }
+}
@@ -1,7 +1,8 @@
 use crate::{
     backtest::{FixPriceStrategy, Strategy},
     dbgp,
-    engine::snap::{next_snap, Snap},
+    engine::snap::{next_snap, LevelQueue, Snap},
+    engine::Trade,
     experiments::Schedule,
     management::OrderManagementSystem,
 };
@@ -28,6 +29,8 @@ pub enum OrderStatus {
     Filled,
     PartiallyFilled,
     Cancelled,
+    // Stopped by the pre-trade checks of the OMS
+    Rejected,
 }
 
 #[derive(Debug, Default, PartialEq, Eq)]
@@ -66,6 +69,26 @@ impl ExecutionReport {
     }
 }
 
+/// Part of a `Trade` print the rebuilt book could not absorb
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct TradeMismatch {
+    pub trade: Trade,
+    pub matched_qty: u32,
+    pub unmatched_qty: u32,
+}
+
+/// How replayed prints fill own resting orders
+#[derive(Debug, Eq, PartialEq, Default, Clone, Copy)]
+pub enum FillModel {
+    // Prints sweep the rebuilt book from the touch,
+    // own orders fill only when the sweep reaches them
+    #[default]
+    BookSweep,
+    // Prints through our price fill own orders up to the printed qty,
+    // prints at our price fill what is left after the queue ahead
+    TradeThrough,
+}
+
 #[derive(Debug, Eq, PartialEq, Default, Clone, Copy)]
 pub struct Order {
     pub id: u64,
@@ -277,16 +300,8 @@ impl OrderBook {
         (ids, done_qty)
     }
 
-    pub fn add_limit_order(&mut self, order: Order) -> ExecutionReport {
+    fn match_incoming(&mut self, order: &Order, exec_report: &mut ExecutionReport) -> u32 {
         let mut remaining_order_qty = order.qty;
-        dbgp!(
-            "[ INFO ] Booked {:?} {}@{} id={}",
-            order.side,
-            remaining_order_qty,
-            order.price,
-            order.id,
-        );
-        let mut exec_report = ExecutionReport::new();
         match order.side {
             | Side::Bid => {
                 let askbook = &mut self.ask_book;
@@ -341,6 +356,19 @@ impl OrderBook {
                 }
             }
         }
+        remaining_order_qty
+    }
+
+    pub fn add_limit_order(&mut self, order: Order) -> ExecutionReport {
+        dbgp!(
+            "[ INFO ] Booked {:?} {}@{} id={}",
+            order.side,
+            order.qty,
+            order.price,
+            order.id,
+        );
+        let mut exec_report = ExecutionReport::new();
+        let remaining_order_qty = self.match_incoming(&order, &mut exec_report);
         let status = match remaining_order_qty {
             | qty if qty == order.qty => {
                 self.create_new_limit_order(
@@ -384,6 +412,150 @@ impl OrderBook {
         exec_report
     }
 
+    /// Replays a historical print as an immediate execution against the book.
+    /// The print never rests: whatever the book could not match is dropped
+    /// and reported back as a `TradeMismatch`.
+    pub fn apply_trade(&mut self, trade: &Trade) -> (ExecutionReport, Option<TradeMismatch>) {
+        dbgp!(
+            "[ INFO ] Trade {:?} {}@{} epoch={}",
+            trade.side,
+            trade.qty,
+            trade.price,
+            trade.exch_epoch,
+        );
+        // Prints without an exchange id are identified by their epoch
+        let order = Order {
+            id: trade.trade_id.unwrap_or(trade.exch_epoch),
+            side: trade.side,
+            price: trade.price,
+            qty: trade.qty,
+        };
+        let mut exec_report = ExecutionReport::new();
+        let remaining_qty = self.match_incoming(&order, &mut exec_report);
+        if remaining_qty < trade.qty {
+            self.update_bbo();
+        }
+        exec_report.own_id = order.id;
+        exec_report.own_side = trade.side;
+        exec_report.remaining_qty = remaining_qty;
+        exec_report.status = match remaining_qty {
+            | 0 => OrderStatus::Filled,
+            | qty if qty < trade.qty => OrderStatus::PartiallyFilled,
+            | _ => OrderStatus::Cancelled,
+        };
+        let mismatch = (remaining_qty > 0).then(|| {
+            dbgp!("[ WARN ]    Trade unmatched qty={}", remaining_qty);
+            TradeMismatch {
+                trade: *trade,
+                matched_qty: trade.qty - remaining_qty,
+                unmatched_qty: remaining_qty,
+            }
+        });
+        (exec_report, mismatch)
+    }
+
+    /// Replays a print under `fill_model`, see `FillModel`.
+    pub fn apply_trade_with(
+        &mut self,
+        trade: &Trade,
+        fill_model: FillModel,
+        own_ids: &[u64],
+    ) -> (ExecutionReport, Option<TradeMismatch>) {
+        match fill_model {
+            | FillModel::BookSweep => self.apply_trade(trade),
+            | FillModel::TradeThrough => self.apply_trade_through(trade, own_ids),
+        }
+    }
+
+    /// Levels better than the print price were traded out before it,
+    /// foreign orders there are removed. Own orders there are filled at their
+    /// price, best level first, but no more than the printed qty in total:
+    /// the print is the only evidence of volume, whatever is not covered
+    /// keeps resting. The rest of the print matches its own level,
+    /// front of the queue first.
+    fn apply_trade_through(
+        &mut self,
+        trade: &Trade,
+        own_ids: &[u64],
+    ) -> (ExecutionReport, Option<TradeMismatch>) {
+        dbgp!(
+            "[ INFO ] Trade through {:?} {}@{} epoch={}",
+            trade.side,
+            trade.qty,
+            trade.price,
+            trade.exch_epoch,
+        );
+        let mut exec_report = ExecutionReport::new();
+        let book = match trade.side {
+            | Side::Bid => &mut self.ask_book,
+            | Side::Ask => &mut self.bid_book,
+        };
+        let mut through = book
+            .price_map
+            .iter()
+            .filter(|&(&price, _)| match trade.side {
+                | Side::Bid => price < trade.price,
+                | Side::Ask => price > trade.price,
+            })
+            .map(|(&price, &level)| (price, level))
+            .collect::<Vec<_>>();
+        // Best level first
+        if trade.side == Side::Ask {
+            through.reverse();
+        }
+        let mut remaining_qty = trade.qty;
+        for (price, level) in through {
+            let queue = std::mem::take(&mut book.price_levels[level]);
+            for mut order in queue {
+                if !own_ids.contains(&order.id) {
+                    self.order_loc.remove(&order.id);
+                    continue;
+                }
+                let fill = order.qty.min(remaining_qty);
+                if fill > 0 {
+                    dbgp!("[ FILL ]    Through {}@{} id={}", fill, price, order.id);
+                    exec_report.filled_orders.push((order.id, fill, price));
+                    remaining_qty -= fill;
+                    order.qty -= fill;
+                }
+                if order.qty == 0 {
+                    self.order_loc.remove(&order.id);
+                } else {
+                    book.price_levels[level].push_back(order);
+                }
+            }
+            if book.price_levels[level].is_empty() {
+                book.price_map.remove(&price);
+            }
+        }
+        if let Some(&level) = book.price_map.get(&trade.price) {
+            let (id_vec, qty_vec) = Self::match_at_price_level(
+                &mut book.price_levels[level],
+                &mut remaining_qty,
+                &mut self.order_loc,
+            );
+            for (id, qty) in id_vec.into_iter().zip(qty_vec) {
+                dbgp!("[ INFO ]    Matched {}@{} id={}", qty, trade.price, id);
+                exec_report.filled_orders.push((id, qty, trade.price));
+            }
+        }
+        self.update_bbo();
+        exec_report.own_id = trade.trade_id.unwrap_or(trade.exch_epoch);
+        exec_report.own_side = trade.side;
+        exec_report.remaining_qty = remaining_qty;
+        exec_report.status = match remaining_qty {
+            | 0 => OrderStatus::Filled,
+            | qty if qty < trade.qty => OrderStatus::PartiallyFilled,
+            | _ => OrderStatus::Cancelled,
+        };
+        let mismatch = (remaining_qty > 0).then(|| TradeMismatch {
+            trade: *trade,
+            matched_qty: trade.qty - remaining_qty,
+            unmatched_qty: remaining_qty,
+        });
+        (exec_report, mismatch)
+    }
+
     /// # Errors
     ///
     /// Will return `Err` if atleast one `HalfBook` in `OrderBook` is empty
@@ -476,13 +648,45 @@ impl OrderBook {
             Err("No such order id")
         }
     }
+    /// Queues of every level holding at least one of `order_ids`,
+    /// unknown ids are skipped
+    pub fn get_level_queues(&self, order_ids: &[u64]) -> Vec<LevelQueue> {
+        let mut levels: Vec<(Side, usize, u32)> = Vec::new();
+        for loc in order_ids.iter().filter_map(|id| self.order_loc.get(id)) {
+            if !levels.contains(loc) {
+                levels.push(*loc);
+            }
+        }
+        levels
+            .into_iter()
+            .map(|(side, price_level, price)| {
+                let book = match side {
+                    | Side::Bid => &self.bid_book,
+                    | Side::Ask => &self.ask_book,
+                };
+                let mut queue = LevelQueue {
+                    side,
+                    price,
+                    orders: Vec::new(),
+                    gaps: vec![0],
+                };
+                for o in &book.price_levels[price_level] {
+                    if order_ids.contains(&o.id) {
+                        queue.orders.push(*o);
+                        queue.gaps.push(0);
+                    } else if let Some(gap) = queue.gaps.last_mut() {
+                        *gap += o.qty;
+                    }
+                }
+                queue
+            })
+            .collect()
+    }
+
     pub fn get_raw<S: Strategy>(&self, oms: &OrderManagementSystem<S>) -> Self {
         let mut raw_ob = self.clone();
-        if let Some(order) = oms.active_buy_order {
-            let _ = raw_ob.cancel_order(order.id);
-        }
-        if let Some(order) = oms.active_sell_order {
-            let _ = raw_ob.cancel_order(order.id);
+        for id in oms.own_order_ids() {
+            let _ = raw_ob.cancel_order(id);
         }
         raw_ob
     }
@@ -493,21 +697,13 @@ impl OrderBook {
         oms: &mut OrderManagementSystem<S>,
         body_f: impl Fn(&mut Self, Order) -> ExecutionReport,
     ) -> Self {
-        let buy_offset = self.get_offset(oms, Side::Bid);
-        let sell_offset = self.get_offset(oms, Side::Ask);
-        dbgp!("[OFFSET] {:?}", (buy_offset, sell_offset));
-        let (ob, _exec_report_bid, _exec_report_ask) =
-            next_snap(snap, (buy_offset, sell_offset), body_f);
-        if let Some(id) = oms.get_order_id(Side::Bid) {
-            if ob.get_order(id).is_none() {
-                oms.active_buy_order = None;
-            }
-        }
-        if let Some(id) = oms.get_order_id(Side::Ask) {
-            if ob.get_order(id).is_none() {
-                oms.active_sell_order = None;
-            }
-        }
+        oms.account.clock = snap.exch_epoch;
+        let levels = self.get_level_queues(&oms.own_order_ids());
+        dbgp!("[OFFSET] {:?}", levels);
+        let (mut ob, exec_reports) = next_snap(snap, levels, &mut oms.queue_model, body_f);
+        oms.snap_fills(&exec_reports);
+        oms.drop_missing(&ob);
+        oms.mark_risk(&mut ob);
         ob
     }
 }
@@ -520,31 +716,32 @@ impl OrderBook {
         body_f: impl Fn(&mut Self, Order) -> ExecutionReport,
     ) -> Self {
         let exch_epoch = snap.exch_epoch;
-        let buy_offset = self.get_offset(oms, Side::Bid);
-        let sell_offset = self.get_offset(oms, Side::Ask);
-        dbgp!("[OFFSET] {:?}", (buy_offset, sell_offset));
-        let (ob, exec_report_bid, exec_report_ask) =
-            next_snap(snap, (buy_offset, sell_offset), body_f);
-        if let Some(id) = oms.get_order_id(Side::Bid) {
-            if ob.get_order(id).is_none() {
-                oms.active_buy_order = None;
-            }
-        }
-        if let Some(id) = oms.get_order_id(Side::Ask) {
-            if ob.get_order(id).is_none() {
-                oms.active_sell_order = None;
-            }
-        }
+        oms.account.clock = exch_epoch;
+        let buy_id = oms.get_order_id(Side::Bid);
+        let sell_id = oms.get_order_id(Side::Ask);
+        let levels = self.get_level_queues(&oms.own_order_ids());
+        dbgp!("[OFFSET] {:?}", levels);
+        let (mut ob, exec_reports) = next_snap(snap, levels, &mut oms.queue_model, body_f);
+        oms.snap_fills(&exec_reports);
+        oms.drop_missing(&ob);
+        let exec_report_bid = exec_reports
+            .iter()
+            .find(|exec_report| Some(exec_report.own_id) == buy_id);
+        let exec_report_ask = exec_reports
+            .iter()
+            .find(|exec_report| Some(exec_report.own_id) == sell_id);
         if let Some(exec_report) = exec_report_bid {
             if exec_report.status == OrderStatus::Filled {
                 oms.strategy.buy_price = None;
+                let own_id = oms.dump_id(exec_report.own_id);
                 println!(
                     "[--DB--];{};{};{};{};",
                     exch_epoch + 3,
-                    exec_report.own_id - 3,
-                    (exch_epoch + 3 - exec_report.own_id) / 1000,
+                    own_id - 3,
+                    (exch_epoch + 3 - own_id) / 1000,
                     1
                 );
+                oms.strategy.fills.record(true);
                 oms.lock_release();
                 oms.schedule = Schedule::default();
             }
@@ -552,17 +749,20 @@ impl OrderBook {
         if let Some(exec_report) = exec_report_ask {
             if exec_report.status == OrderStatus::Filled {
                 oms.strategy.buy_price = None;
+                let own_id = oms.dump_id(exec_report.own_id);
                 println!(
                     "[--DB--];{};{};{};{};",
                     exch_epoch + 7,
-                    exec_report.own_id - 7,
-                    (exch_epoch + 7 - exec_report.own_id) / 1000,
+                    own_id - 7,
+                    (exch_epoch + 7 - own_id) / 1000,
                     1
                 );
+                oms.strategy.fills.record(true);
                 oms.lock_release();
                 oms.schedule = Schedule::default();
             }
         }
+        oms.mark_risk(&mut ob);
         ob
     }
 }
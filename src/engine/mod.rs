@@ -3,9 +3,15 @@ pub mod indicators;
 mod account;
 mod diff;
 mod event;
+mod fees;
+mod latency;
+mod ledger;
 mod matching_engine;
+mod money;
 mod obviz;
+mod queue;
 mod risk_control;
+mod session;
 mod snap;
 mod tick;
 
@@ -13,11 +19,16 @@ pub use account::*;
 #[allow(unused)]
 pub use diff::*;
 pub use event::*;
+pub use fees::*;
 pub use indicators::*;
+pub use latency::*;
+pub use ledger::*;
 pub use matching_engine::*;
+pub use money::*;
 #[allow(unused)]
 pub use obviz::*;
-#[allow(unused)]
+pub use queue::*;
 pub use risk_control::*;
+pub use session::*;
 pub use snap::*;
 pub use tick::*;
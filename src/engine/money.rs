@@ -0,0 +1,158 @@
+//! Exact amounts for balances, fees and pnl. Money is counted in price units
+//! of the book, a contract bought at `price` costs `price` units, with
+//! `Money::SCALE` raw units per unit. Cash is `units * Ticker::unit_cash`
+//! and is computed for reporting only.
+
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money(i64);
+
+impl Money {
+    // Fractions of a tick come from fee rates only
+    pub const SCALE: i64 = 10_000;
+    pub const ZERO: Self = Self(0);
+
+    pub const fn from_units(units: i64) -> Self {
+        Self(units * Self::SCALE)
+    }
+
+    pub const fn from_raw(raw: i64) -> Self {
+        Self(raw)
+    }
+
+    pub const fn raw(self) -> i64 {
+        self.0
+    }
+
+    /// Value of `qty` contracts at `price`
+    pub fn notional(qty: u32, price: u32) -> Self {
+        Self::from_units(i64::from(qty) * i64::from(price))
+    }
+
+    /// Rounds to the nearest unit
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn from_f64(units: f64) -> Self {
+        Self((units * Self::SCALE as f64).round() as i64)
+    }
+
+    pub fn units(self) -> f64 {
+        self.0 as f64 / Self::SCALE as f64
+    }
+
+    /// Cash value at `unit_cash` per unit, for reports
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn cash(self, unit_cash: f32) -> f32 {
+        (self.units() * f64::from(unit_cash)) as f32
+    }
+
+    /// Return on `volume` units traded in basis points, for reports
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn bps(self, volume: u64) -> f32 {
+        match volume {
+            | 0 => 0.0,
+            | _ => (self.units() / volume as f64 * 10000.0) as f32,
+        }
+    }
+}
+
+impl Add for Money {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Money {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0 - rhs.0)
+    }
+}
+
+impl Neg for Money {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self(-self.0)
+    }
+}
+
+impl Mul<i64> for Money {
+    type Output = Self;
+
+    fn mul(self, rhs: i64) -> Self {
+        Self(self.0 * rhs)
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 += rhs.0;
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ZERO, Add::add)
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.4}", self.units())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Ticker;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn exact_sums() {
+        // A day of buys at five digit prices, f32 drifts by whole units here
+        let mut balance = Money::ZERO;
+        let mut float = 0.0f32;
+        let mut exact = 0i64;
+        for i in 0..100_000u32 {
+            let price = 123_456 + i % 7;
+            balance -= Money::notional(3, price);
+            float -= (3 * price) as f32;
+            exact -= 3 * i64::from(price);
+        }
+        assert_eq!(balance, Money::from_units(exact));
+        assert!((f64::from(float) - balance.units()).abs() > 1.0);
+    }
+
+    #[test]
+    // Reported floats come from exact amounts
+    #[allow(clippy::float_cmp)]
+    fn conversions() {
+        assert_eq!(Money::from_f64(0.000_06), Money::from_raw(1));
+        assert_eq!(Money::from_f64(-2.5), -Money::from_f64(2.5));
+        assert_eq!(Money::from_units(2) * 3, Money::from_units(6));
+        assert_eq!(Money::from_units(415).cash(0.1), 41.5);
+        // Ticks of 5 worth 0.5 each, two contracts at 105 are 42 ticks
+        let ticker = Ticker {
+            tick_size: 5.0,
+            step_price: 0.5,
+            ..Ticker::default()
+        };
+        assert_eq!(Money::notional(2, 105).cash(ticker.unit_cash()), 21.0);
+        assert_eq!(Money::from_units(10).bps(100_000), 1.0);
+        assert_eq!(Money::ZERO.bps(0), 0.0);
+        assert_eq!(Money::from_f64(1.25).to_string(), "1.2500");
+    }
+}
@@ -0,0 +1,157 @@
+//! Where do cancellations happen in a price level that holds our order?
+//! Snapshots only show the total qty of a level, so when it shrinks
+//! between two snapshots the queue ahead of us has to be guessed.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+#[derive(Debug, Clone, Default)]
+pub enum QueueModel {
+    // Cancels come from behind us first, queue ahead only shrinks
+    // when everything behind is already gone
+    #[default]
+    Pessimistic,
+    // Cancels come from ahead of us first
+    Optimistic,
+    // Cancels are split in proportion to the qty ahead and behind
+    Proportional,
+    // Every lot in the queue is equally likely to be cancelled
+    Probabilistic(Box<StdRng>),
+}
+
+impl QueueModel {
+    pub fn probabilistic(seed: u64) -> Self {
+        Self::Probabilistic(Box::new(StdRng::seed_from_u64(seed)))
+    }
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            | Self::Pessimistic => "pessimistic",
+            | Self::Optimistic => "optimistic",
+            | Self::Proportional => "proportional",
+            | Self::Probabilistic(_) => "probabilistic",
+        }
+    }
+
+    /// Splits `need_to_cut` lots between the queue ahead and behind.
+    /// Returns qty cut from the head and from the tail,
+    /// `need_to_cut` is capped by `qty_head + qty_tail`.
+    pub fn cut(&mut self, qty_head: u32, qty_tail: u32, need_to_cut: u32) -> (u32, u32) {
+        let mut gaps = [qty_head, qty_tail];
+        self.cut_gaps(&mut gaps, need_to_cut);
+        (qty_head - gaps[0], qty_tail - gaps[1])
+    }
+
+    /// Removes `need_to_cut` lots from the queue segments between own orders,
+    /// front of the queue first. Several own orders in a level split
+    /// the queue into several segments, "ahead" and "behind" are relative
+    /// to the front one.
+    pub fn cut_gaps(&mut self, gaps: &mut [u32], need_to_cut: u32) {
+        let total = gaps.iter().sum::<u32>();
+        // Nothing queued around own orders, nothing to cut
+        if total == 0 {
+            return;
+        }
+        let mut left = need_to_cut.min(total);
+        match self {
+            | Self::Pessimistic => {}
+            | Self::Optimistic => {
+                for gap in gaps.iter_mut() {
+                    let cut = (*gap).min(left);
+                    *gap -= cut;
+                    left -= cut;
+                }
+            }
+            | Self::Proportional => {
+                for gap in gaps.iter_mut() {
+                    let share =
+                        u64::from(need_to_cut.min(total)) * u64::from(*gap) / u64::from(total);
+                    let cut = u32::try_from(share).unwrap_or(*gap).min(*gap);
+                    *gap -= cut;
+                    left -= cut;
+                }
+            }
+            | Self::Probabilistic(rng) => {
+                let mut remaining = total;
+                for _ in 0..left {
+                    // Every lot still in the queue is equally likely to go
+                    let mut lot = rng.random_range(0..remaining);
+                    for gap in gaps.iter_mut() {
+                        if lot < *gap {
+                            *gap -= 1;
+                            break;
+                        }
+                        lot -= *gap;
+                    }
+                    remaining -= 1;
+                }
+                left = 0;
+            }
+        }
+        // Rounding leftovers and the pessimistic cut come from the back
+        for gap in gaps.iter_mut().rev() {
+            let cut = (*gap).min(left);
+            *gap -= cut;
+            left -= cut;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(QueueModel::Pessimistic, 10, 5, 3, (0, 3))]
+    #[case(QueueModel::Pessimistic, 10, 5, 8, (3, 5))]
+    #[case(QueueModel::Optimistic, 10, 5, 3, (3, 0))]
+    #[case(QueueModel::Optimistic, 10, 5, 12, (10, 2))]
+    #[case(QueueModel::Proportional, 10, 5, 6, (4, 2))]
+    #[case(QueueModel::Proportional, 0, 5, 6, (0, 5))]
+    #[case(QueueModel::Proportional, 0, 0, 6, (0, 0))]
+    #[case(QueueModel::probabilistic(1), 0, 0, 6, (0, 0))]
+    #[case(QueueModel::probabilistic(1), 10, 0, 4, (4, 0))]
+    #[case(QueueModel::probabilistic(1), 0, 10, 4, (0, 4))]
+    fn cut(
+        #[case] mut model: QueueModel,
+        #[case] qty_head: u32,
+        #[case] qty_tail: u32,
+        #[case] need_to_cut: u32,
+        #[case] expected: (u32, u32),
+    ) {
+        assert_eq!(model.cut(qty_head, qty_tail, need_to_cut), expected);
+    }
+
+    #[rstest]
+    #[case(QueueModel::Pessimistic, vec![4, 0, 0])]
+    #[case(QueueModel::Optimistic, vec![0, 0, 4])]
+    #[case(QueueModel::Proportional, vec![2, 2, 0])]
+    fn cut_gaps(#[case] mut model: QueueModel, #[case] expected: Vec<u32>) {
+        let mut gaps = vec![4, 4, 4];
+        model.cut_gaps(&mut gaps, 8);
+        assert_eq!(gaps, expected);
+    }
+
+    #[rstest]
+    #[case(QueueModel::Pessimistic)]
+    #[case(QueueModel::Optimistic)]
+    #[case(QueueModel::Proportional)]
+    #[case(QueueModel::probabilistic(1))]
+    fn empty_gaps_stay_empty(#[case] mut model: QueueModel) {
+        let mut gaps = vec![0, 0, 0];
+        model.cut_gaps(&mut gaps, 5);
+        assert_eq!(gaps, vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn probabilistic_is_seeded() {
+        let mut model = QueueModel::probabilistic(7);
+        let mut same = QueueModel::probabilistic(7);
+        for _ in 0..10 {
+            let (head, tail) = model.cut(50, 50, 40);
+            assert_eq!(head + tail, 40);
+            assert_eq!((head, tail), same.cut(50, 50, 40));
+        }
+    }
+}
@@ -1,15 +1,505 @@
-use crate::{backtest::Strategy, engine::OrderBook, management::OrderManagementSystem};
+//! Pre-trade checks every order of the OMS passes on its way to the engine,
+//! and a kill switch on the marked to market pnl. OMS instances trading
+//! together share a `RiskBook` with limits on their aggregate position.
+
+use crate::{
+    backtest::Strategy,
+    engine::{indicators::Midprice, Money, Order, OrderBook, Side},
+    management::{OrderManagementSystem, OrderTag},
+};
+use log::warn;
+use std::{cell::RefCell, rc::Rc};
+use thiserror::Error;
+
+/// Unset limits are not checked
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct RiskLimits {
+    pub max_order_qty: Option<u32>,
+    // Of a single order
+    pub max_notional: Option<Money>,
+    pub max_open_orders: Option<usize>,
+    // (long, short) bounds of the account position if every open order
+    // of the side and the new one are filled, e.g. (100, -100)
+    pub position_limits: Option<(i32, i32)>,
+    // Largest distance of the price from mid, fraction of mid
+    pub price_collar: Option<f32>,
+    // Kill switch: marked to market loss since the account opened
+    pub max_loss: Option<Money>,
+    // Kill switch: drop of the marked to market pnl from its peak
+    pub max_drawdown: Option<Money>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KillReason {
+    MaxLoss { pnl: Money },
+    Drawdown { drawdown: Money },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Error)]
+pub enum RiskRejection {
+    #[error("qty {qty} above max order qty {limit}")]
+    MaxOrderQty { qty: u32, limit: u32 },
+    #[error("notional {notional} above max notional {limit}")]
+    MaxNotional { notional: Money, limit: Money },
+    #[error("{open} open orders, max {limit}")]
+    MaxOpenOrders { open: usize, limit: usize },
+    #[error("position could reach {position}, limits {limits:?}")]
+    PositionLimit { position: i32, limits: (i32, i32) },
+    #[error("aggregate position could reach {position}, limits {limits:?}")]
+    AggregatePositionLimit { position: i32, limits: (i32, i32) },
+    #[error("price {price} outside collar around mid {mid}")]
+    PriceCollar { price: u32, mid: f32 },
+    #[error("margin {required} above equity {equity}")]
+    Margin { required: Money, equity: Money },
+    #[error("kill switch is on: {0:?}")]
+    KillSwitch(KillReason),
+}
+
+/// Account state the checks run against
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Exposure {
+    pub position: i32,
+    pub open_orders: usize,
+    // Leaves qty of open orders by side
+    pub open_bid_qty: u32,
+    pub open_ask_qty: u32,
+    // Collateral with the position marked to mid
+    pub equity: Money,
+    // Per contract, zero skips the margin check
+    pub initial_margin: Money,
+}
+
+impl Exposure {
+    /// Initial margin of the position if every open order of the side
+    /// it grows to and `extra` more are filled, positive `extra` buys
+    fn required_margin(&self, extra: i32) -> Money {
+        let (bid, ask) = (
+            self.position + self.open_bid_qty as i32 + extra.max(0),
+            self.position - self.open_ask_qty as i32 + extra.min(0),
+        );
+        self.initial_margin * i64::from(bid.unsigned_abs().max(ask.unsigned_abs()))
+    }
+
+    /// `order` and every open order of its side only bring the position closer to zero
+    const fn reduces(&self, order: &Order) -> bool {
+        match order.side {
+            | Side::Bid => {
+                self.position < 0 && self.open_bid_qty + order.qty <= self.position.unsigned_abs()
+            }
+            | Side::Ask => {
+                self.position > 0 && self.open_ask_qty + order.qty <= self.position.unsigned_abs()
+            }
+        }
+    }
+}
+
+/// Exposures of every OMS trading together, e.g. a quoter and its hedger.
+/// Each OMS publishes its own as it sends orders and marks to the book.
+#[derive(Debug, Default)]
+pub struct RiskBook {
+    // (long, short) bounds of the net position of all members if every
+    // open order of the side and the new one are filled
+    pub position_limits: Option<(i32, i32)>,
+    // Latest exposure of each member by the slot it joined under
+    exposures: Vec<Exposure>,
+}
+
+pub type SharedRiskBook = Rc<RefCell<RiskBook>>;
+
+impl RiskBook {
+    pub fn shared(position_limits: Option<(i32, i32)>) -> SharedRiskBook {
+        Rc::new(RefCell::new(Self {
+            position_limits,
+            exposures: Vec::new(),
+        }))
+    }
+
+    /// Net position and open qty by side of every member but `slot`
+    fn others(&self, slot: usize) -> Exposure {
+        self.exposures
+            .iter()
+            .enumerate()
+            .filter(|&(member, _)| member != slot)
+            .fold(Exposure::default(), |sum, (_, exposure)| Exposure {
+                position: sum.position + exposure.position,
+                open_bid_qty: sum.open_bid_qty + exposure.open_bid_qty,
+                open_ask_qty: sum.open_ask_qty + exposure.open_ask_qty,
+                ..sum
+            })
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct RiskControl {
+    pub limits: RiskLimits,
+    pub killed: Option<KillReason>,
+    // Every rejected order id with its reason
+    pub rejections: Vec<(u64, RiskRejection)>,
+    mid: Option<f32>,
+    peak: Option<Money>,
+    // Book shared with other OMS instances and the slot of this one in it
+    book: Option<(SharedRiskBook, usize)>,
+}
+
+impl RiskControl {
+    pub const fn new(limits: RiskLimits) -> Self {
+        Self {
+            limits,
+            killed: None,
+            rejections: Vec::new(),
+            mid: None,
+            peak: None,
+            book: None,
+        }
+    }
+
+    /// Checks orders against the aggregate limits of `book` from now on
+    pub fn join(&mut self, book: &SharedRiskBook) {
+        let slot = {
+            let mut shared = book.borrow_mut();
+            shared.exposures.push(Exposure::default());
+            shared.exposures.len() - 1
+        };
+        self.book = Some((Rc::clone(book), slot));
+    }
+
+    /// Makes `exposure` the one the other members of the book see
+    pub fn publish(&self, exposure: Exposure) {
+        if let Some((book, slot)) = &self.book {
+            book.borrow_mut().exposures[*slot] = exposure;
+        }
+    }
+
+    pub const fn is_killed(&self) -> bool {
+        self.killed.is_some()
+    }
+
+    /// Runs every check, the first one failing is reported.
+    /// `marketable` orders cross the book at any price and skip the collar,
+    /// orders reducing the position pass the kill switch.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `order` breaks a limit or the kill switch is on
+    pub fn check(
+        &self,
+        order: &Order,
+        exposure: Exposure,
+        marketable: bool,
+    ) -> Result<(), RiskRejection> {
+        let limits = &self.limits;
+        if let Some(reason) = self.killed.filter(|_| !exposure.reduces(order)) {
+            return Err(RiskRejection::KillSwitch(reason));
+        }
+        if let Some(limit) = limits.max_order_qty.filter(|&limit| order.qty > limit) {
+            return Err(RiskRejection::MaxOrderQty {
+                qty: order.qty,
+                limit,
+            });
+        }
+        let notional = Money::notional(order.qty, order.price);
+        if let Some(limit) = limits.max_notional.filter(|&limit| notional > limit) {
+            return Err(RiskRejection::MaxNotional { notional, limit });
+        }
+        if let Some(limit) = limits
+            .max_open_orders
+            .filter(|&limit| exposure.open_orders >= limit)
+        {
+            return Err(RiskRejection::MaxOpenOrders {
+                open: exposure.open_orders,
+                limit,
+            });
+        }
+        if let Some(limits) = limits.position_limits {
+            let position = match order.side {
+                | Side::Bid => exposure.position + (exposure.open_bid_qty + order.qty) as i32,
+                | Side::Ask => exposure.position - (exposure.open_ask_qty + order.qty) as i32,
+            };
+            if position > limits.0 || position < limits.1 {
+                return Err(RiskRejection::PositionLimit { position, limits });
+            }
+        }
+        if let Some((book, slot)) = &self.book {
+            let book = book.borrow();
+            if let Some(limits) = book.position_limits {
+                let others = book.others(*slot);
+                let position = match order.side {
+                    | Side::Bid => {
+                        exposure.position
+                            + others.position
+                            + (exposure.open_bid_qty + others.open_bid_qty + order.qty) as i32
+                    }
+                    | Side::Ask => {
+                        exposure.position + others.position
+                            - (exposure.open_ask_qty + others.open_ask_qty + order.qty) as i32
+                    }
+                };
+                if position > limits.0 || position < limits.1 {
+                    return Err(RiskRejection::AggregatePositionLimit { position, limits });
+                }
+            }
+        }
+        // Orders that do not add to the margin pass, flattening included
+        if exposure.initial_margin > Money::ZERO {
+            let extra = match order.side {
+                | Side::Bid => order.qty as i32,
+                | Side::Ask => -(order.qty as i32),
+            };
+            let required = exposure.required_margin(extra);
+            if required > exposure.equity && required > exposure.required_margin(0) {
+                return Err(RiskRejection::Margin {
+                    required,
+                    equity: exposure.equity,
+                });
+            }
+        }
+        if let (Some(collar), Some(mid), false) = (limits.price_collar, self.mid, marketable) {
+            if (order.price as f32 - mid).abs() > collar * mid {
+                return Err(RiskRejection::PriceCollar {
+                    price: order.price,
+                    mid,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Updates the reference mid and the marked to market pnl,
+    /// returns the reason if the kill switch has just tripped
+    pub fn mark(&mut self, mid: Option<f32>, pnl: Option<Money>) -> Option<KillReason> {
+        self.mid = mid.or(self.mid);
+        let pnl = pnl?;
+        let peak = self.peak.map_or(pnl, |peak| peak.max(pnl));
+        self.peak = Some(peak);
+        if self.killed.is_some() {
+            return None;
+        }
+        let reason = if self.limits.max_loss.is_some_and(|limit| pnl < -limit) {
+            KillReason::MaxLoss { pnl }
+        } else if self
+            .limits
+            .max_drawdown
+            .is_some_and(|limit| peak - pnl > limit)
+        {
+            KillReason::Drawdown {
+                drawdown: peak - pnl,
+            }
+        } else {
+            return None;
+        };
+        self.killed = Some(reason);
+        Some(reason)
+    }
+}
 
 #[allow(dead_code)]
 impl<S: Strategy> OrderManagementSystem<'_, S> {
     pub fn cancel_all_orders(&mut self, ob: &mut OrderBook) {
         if let Some(order) = self.active_buy_order {
-            let _ = ob.cancel_order(order.id);
+            let _ = self.cancel(ob, order.id);
             self.active_buy_order = None;
         }
         if let Some(order) = self.active_sell_order {
-            let _ = ob.cancel_order(order.id);
+            let _ = self.cancel(ob, order.id);
             self.active_sell_order = None;
         }
+        for order in std::mem::take(&mut self.ladder) {
+            let _ = self.cancel(ob, order.id);
+        }
+    }
+
+    /// Exposure in the ticker of the strategy, positions of the account
+    /// in other tickers are neither counted nor marked at this book
+    pub fn exposure(&self) -> Exposure {
+        let ticker = self.strategy.ticker();
+        let mut exposure = Exposure {
+            position: self.position(),
+            equity: self.risk.mid.map_or(self.account.collateral, |mid| {
+                self.account.equity(ticker.ticker_id, mid)
+            }),
+            initial_margin: ticker.initial_margin,
+            ..Exposure::default()
+        };
+        for record in self.orders.open_orders() {
+            exposure.open_orders += 1;
+            match record.order.side {
+                | Side::Bid => exposure.open_bid_qty += record.leaves_qty(),
+                | Side::Ask => exposure.open_ask_qty += record.leaves_qty(),
+            }
+        }
+        exposure
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if `self.risk` does not let `order` through
+    pub fn pre_trade_check(&self, order: &Order) -> Result<(), RiskRejection> {
+        let marketable = self
+            .ids
+            .get(order.id)
+            .is_some_and(|meta| meta.tag == OrderTag::Flatten);
+        let exposure = self.exposure();
+        self.risk.publish(exposure);
+        self.risk.check(order, exposure, marketable)
+    }
+
+    /// Marks the account to the book and publishes the exposure to the
+    /// shared risk book, a tripped kill switch pulls every own order
+    pub fn mark_risk(&mut self, ob: &mut OrderBook) {
+        self.risk.publish(self.exposure());
+        let mid = Midprice::evaluate(ob);
+        let pnl = mid.map(|m| self.account.pnl(self.position(), m));
+        if let Some(reason) = self.risk.mark(mid, pnl) {
+            warn!(target: "risk", "kill switch: {reason:?}");
+            self.cancel_all_orders(ob);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    const LIMITS: RiskLimits = RiskLimits {
+        max_order_qty: Some(10),
+        max_notional: Some(Money::from_units(900)),
+        max_open_orders: Some(3),
+        position_limits: Some((20, -20)),
+        price_collar: Some(0.05),
+        max_loss: Some(Money::from_units(100)),
+        max_drawdown: Some(Money::from_units(50)),
+    };
+
+    fn bid(qty: u32, price: u32) -> Order {
+        Order {
+            id: 1,
+            side: Side::Bid,
+            price,
+            qty,
+        }
+    }
+
+    fn exposure(position: i32, open_orders: usize, open_bid_qty: u32) -> Exposure {
+        Exposure {
+            position,
+            open_orders,
+            open_bid_qty,
+            ..Exposure::default()
+        }
+    }
+
+    fn margined(position: i32, open_bid_qty: u32, equity: i64) -> Exposure {
+        Exposure {
+            position,
+            open_bid_qty,
+            equity: Money::from_units(equity),
+            initial_margin: Money::from_units(10),
+            ..Exposure::default()
+        }
+    }
+
+    #[rstest]
+    #[case(bid(5, 100), exposure(0, 0, 0), false, Ok(()))]
+    #[case(bid(11, 80), exposure(0, 0, 0), false, Err(RiskRejection::MaxOrderQty { qty: 11, limit: 10 }))]
+    #[case(
+        bid(10, 100),
+        exposure(0, 0, 0),
+        false,
+        Err(RiskRejection::MaxNotional { notional: Money::from_units(1000), limit: Money::from_units(900) })
+    )]
+    #[case(bid(5, 100), exposure(0, 3, 0), false, Err(RiskRejection::MaxOpenOrders { open: 3, limit: 3 }))]
+    // Open bids count as if filled
+    #[case(
+        bid(5, 100),
+        exposure(10, 1, 6),
+        false,
+        Err(RiskRejection::PositionLimit { position: 21, limits: (20, -20) })
+    )]
+    #[case(bid(5, 94), exposure(0, 0, 0), false, Err(RiskRejection::PriceCollar { price: 94, mid: 100.0 }))]
+    #[case(bid(5, 94), exposure(0, 0, 0), true, Ok(()))]
+    // 10 long with 4 bid open and 5 more need 190 of margin
+    #[case(
+        bid(5, 100),
+        margined(10, 4, 150),
+        false,
+        Err(RiskRejection::Margin { required: Money::from_units(190), equity: Money::from_units(150) })
+    )]
+    #[case(bid(5, 100), margined(10, 4, 190), false, Ok(()))]
+    // Buying back a short frees margin
+    #[case(bid(5, 100), margined(-15, 0, 50), false, Ok(()))]
+    fn checks(
+        #[case] order: Order,
+        #[case] exposure: Exposure,
+        #[case] marketable: bool,
+        #[case] expected: Result<(), RiskRejection>,
+    ) {
+        let mut risk = RiskControl::new(LIMITS);
+        risk.mark(Some(100.0), None);
+        assert_eq!(risk.check(&order, exposure, marketable), expected);
+    }
+
+    #[rstest]
+    #[case(&[0, -101], Some(KillReason::MaxLoss { pnl: Money::from_units(-101) }))]
+    #[case(&[40, -11], Some(KillReason::Drawdown { drawdown: Money::from_units(51) }))]
+    #[case(&[40, -10, 30], None)]
+    fn kill_switch(#[case] marks: &[i64], #[case] expected: Option<KillReason>) {
+        let mut risk = RiskControl::new(LIMITS);
+        for &pnl in marks {
+            risk.mark(Some(100.0), Some(Money::from_units(pnl)));
+        }
+        assert_eq!(risk.killed, expected);
+        if let Some(reason) = expected {
+            assert_eq!(
+                risk.check(&bid(1, 100), Exposure::default(), true),
+                Err(RiskRejection::KillSwitch(reason))
+            );
+            // Buying back a short of 5 only brings the position closer to zero
+            assert_eq!(risk.check(&bid(5, 100), exposure(-5, 0, 0), true), Ok(()));
+            assert_eq!(
+                risk.check(&bid(6, 100), exposure(-5, 0, 0), true),
+                Err(RiskRejection::KillSwitch(reason))
+            );
+            assert_eq!(
+                risk.check(&bid(3, 100), exposure(-5, 1, 3), true),
+                Err(RiskRejection::KillSwitch(reason))
+            );
+        }
+    }
+
+    #[test]
+    fn aggregate_position() {
+        let book = RiskBook::shared(Some((20, -20)));
+        let (mut quoter, mut hedger) = (RiskControl::default(), RiskControl::default());
+        quoter.join(&book);
+        hedger.join(&book);
+        quoter.publish(exposure(12, 1, 4));
+        // 12 long with 4 bid open in the quoter
+        assert_eq!(
+            hedger.check(&bid(5, 100), exposure(0, 0, 0), false),
+            Err(RiskRejection::AggregatePositionLimit {
+                position: 21,
+                limits: (20, -20)
+            })
+        );
+        assert_eq!(hedger.check(&bid(4, 100), exposure(0, 0, 0), false), Ok(()));
+        // The quoter sold down, its own exposure is never taken from the book
+        quoter.publish(exposure(-10, 0, 0));
+        assert_eq!(
+            hedger.check(&bid(10, 100), exposure(0, 0, 0), false),
+            Ok(())
+        );
+        assert_eq!(
+            quoter.check(&bid(10, 100), exposure(5, 0, 0), false),
+            Ok(())
+        );
+        assert_eq!(
+            quoter.check(&bid(16, 100), exposure(5, 0, 0), false),
+            Err(RiskRejection::AggregatePositionLimit {
+                position: 21,
+                limits: (20, -20)
+            })
+        );
     }
 }
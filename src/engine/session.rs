@@ -0,0 +1,267 @@
+//! Exchange trading sessions. Times of day are nanoseconds since local
+//! midnight, epochs are nanoseconds since unix epoch UTC.
+//! Weekends and holidays are not modelled, every day follows the same schedule.
+
+pub const NANOS_IN_MINUTE: u64 = 60 * 1_000_000_000;
+pub const NANOS_IN_HOUR: u64 = 60 * NANOS_IN_MINUTE;
+pub const NANOS_IN_DAY: u64 = 24 * NANOS_IN_HOUR;
+
+/// Local time of day
+pub const fn hm(hours: u64, minutes: u64) -> u64 {
+    hours * NANOS_IN_HOUR + minutes * NANOS_IN_MINUTE
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Session {
+    pub name: &'static str,
+    pub open: u64,
+    pub close: u64,
+    // Clearing breaks within the session, no matching happens
+    pub breaks: &'static [(u64, u64)],
+}
+
+impl Session {
+    const fn contains(&self, time_of_day: u64) -> bool {
+        self.open <= time_of_day && time_of_day < self.close
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Open,
+    Break,
+    Closed,
+}
+
+/// What a quoting strategy does at a point of the session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionAction {
+    Quote,
+    // Close the position, the session is about to end
+    Flatten,
+    // Matching is halted, nothing may rest in the book
+    CancelAll,
+}
+
+/// Identifies a single session of a single day
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SessionKey {
+    // Local days since unix epoch
+    pub day: u64,
+    // Index into `SessionCalendar::sessions`
+    pub session: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionCalendar {
+    pub name: &'static str,
+    // Local time minus UTC, nanoseconds
+    pub utc_offset: i64,
+    pub sessions: &'static [Session],
+    // Local time of the clearing that settles variation margin
+    pub clearing: Option<u64>,
+}
+
+pub const ALWAYS_OPEN: SessionCalendar = SessionCalendar {
+    name: "always open",
+    utc_offset: 0,
+    sessions: &[Session {
+        name: "all day",
+        open: 0,
+        close: NANOS_IN_DAY,
+        breaks: &[],
+    }],
+    clearing: None,
+};
+
+/// MOEX derivatives market: day session with the intraday clearing,
+/// evening clearing and the evening session
+pub const MOEX_FORTS: SessionCalendar = SessionCalendar {
+    name: "MOEX FORTS",
+    utc_offset: 3 * NANOS_IN_HOUR as i64,
+    sessions: &[
+        Session {
+            name: "day",
+            open: hm(10, 0),
+            close: hm(18, 50),
+            breaks: &[(hm(14, 0), hm(14, 5))],
+        },
+        Session {
+            name: "evening",
+            open: hm(19, 5),
+            close: hm(23, 50),
+            breaks: &[],
+        },
+    ],
+    clearing: Some(hm(18, 50)),
+};
+
+/// MOEX equities market: main session up to the closing auction
+/// and the evening session
+pub const MOEX_EQUITIES: SessionCalendar = SessionCalendar {
+    name: "MOEX equities",
+    utc_offset: 3 * NANOS_IN_HOUR as i64,
+    sessions: &[
+        Session {
+            name: "main",
+            open: hm(10, 0),
+            close: hm(18, 40),
+            breaks: &[],
+        },
+        Session {
+            name: "evening",
+            open: hm(19, 5),
+            close: hm(23, 50),
+            breaks: &[],
+        },
+    ],
+    clearing: None,
+};
+
+impl Default for SessionCalendar {
+    fn default() -> Self {
+        ALWAYS_OPEN
+    }
+}
+
+impl SessionCalendar {
+    const fn local(&self, epoch: u64) -> u64 {
+        epoch.wrapping_add_signed(self.utc_offset)
+    }
+
+    pub const fn time_of_day(&self, epoch: u64) -> u64 {
+        self.local(epoch) % NANOS_IN_DAY
+    }
+
+    pub const fn day(&self, epoch: u64) -> u64 {
+        self.local(epoch) / NANOS_IN_DAY
+    }
+
+    /// Session the epoch falls into, breaks included
+    pub fn session(&self, epoch: u64) -> Option<(SessionKey, &Session)> {
+        let time_of_day = self.time_of_day(epoch);
+        self.sessions
+            .iter()
+            .enumerate()
+            .find(|(_, session)| session.contains(time_of_day))
+            .map(|(idx, session)| {
+                (
+                    SessionKey {
+                        day: self.day(epoch),
+                        session: idx,
+                    },
+                    session,
+                )
+            })
+    }
+
+    pub fn phase(&self, epoch: u64) -> Phase {
+        let time_of_day = self.time_of_day(epoch);
+        match self.session(epoch) {
+            | Some((_, session))
+                if session
+                    .breaks
+                    .iter()
+                    .any(|&(start, end)| start <= time_of_day && time_of_day < end) =>
+            {
+                Phase::Break
+            }
+            | Some(_) => Phase::Open,
+            | None => Phase::Closed,
+        }
+    }
+
+    pub fn is_open(&self, epoch: u64) -> bool {
+        self.phase(epoch) == Phase::Open
+    }
+
+    /// Clearings held up to the epoch inclusive, counted from the unix epoch.
+    /// A change between two epochs means variation margin is due.
+    pub const fn clearings(&self, epoch: u64) -> Option<u64> {
+        match self.clearing {
+            | Some(clearing) => Some((self.local(epoch) + NANOS_IN_DAY - clearing) / NANOS_IN_DAY),
+            | None => None,
+        }
+    }
+
+    /// Nanoseconds left until the current session closes
+    pub fn until_close(&self, epoch: u64) -> Option<u64> {
+        self.session(epoch)
+            .map(|(_, session)| session.close - self.time_of_day(epoch))
+    }
+
+    /// True once the epoch is within `window` of the session close
+    pub fn is_closing(&self, epoch: u64, window: Option<u64>) -> bool {
+        window.is_some_and(|window| self.until_close(epoch).is_some_and(|left| left <= window))
+    }
+
+    /// Quote while open, flatten within `flatten_before_close` of the close
+    /// and pull everything while matching is halted
+    pub fn action(&self, epoch: u64, flatten_before_close: Option<u64>) -> SessionAction {
+        match self.phase(epoch) {
+            | Phase::Open if self.is_closing(epoch, flatten_before_close) => SessionAction::Flatten,
+            | Phase::Open => SessionAction::Quote,
+            | Phase::Break | Phase::Closed => SessionAction::CancelAll,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    // 2023-11-01 00:00 MSK
+    const MIDNIGHT: u64 = 1_698_786_000_000_000_000;
+
+    #[rstest]
+    #[case(hm(9, 59), Phase::Closed)]
+    #[case(hm(10, 0), Phase::Open)]
+    #[case(hm(14, 2), Phase::Break)]
+    #[case(hm(14, 5), Phase::Open)]
+    #[case(hm(18, 55), Phase::Closed)]
+    #[case(hm(19, 5), Phase::Open)]
+    #[case(hm(23, 50), Phase::Closed)]
+    fn forts_phase(#[case] time_of_day: u64, #[case] expected: Phase) {
+        assert_eq!(MOEX_FORTS.phase(MIDNIGHT + time_of_day), expected);
+    }
+
+    #[test]
+    fn session_key() {
+        let (key, session) = MOEX_FORTS.session(MIDNIGHT + hm(20, 0)).unwrap();
+        assert_eq!(session.name, "evening");
+        assert_eq!(key.session, 1);
+        assert_eq!(key.day, MOEX_FORTS.day(MIDNIGHT));
+        assert_eq!(
+            MOEX_FORTS.until_close(MIDNIGHT + hm(23, 45)),
+            Some(5 * NANOS_IN_MINUTE)
+        );
+        assert!(ALWAYS_OPEN.is_open(MIDNIGHT + hm(3, 0)));
+    }
+
+    #[rstest]
+    #[case(hm(12, 0), SessionAction::Quote)]
+    #[case(hm(14, 2), SessionAction::CancelAll)]
+    #[case(hm(18, 40), SessionAction::Flatten)]
+    #[case(hm(18, 55), SessionAction::CancelAll)]
+    fn forts_action(#[case] time_of_day: u64, #[case] expected: SessionAction) {
+        let window = Some(10 * NANOS_IN_MINUTE);
+        assert_eq!(MOEX_FORTS.action(MIDNIGHT + time_of_day, window), expected);
+    }
+
+    #[test]
+    fn clearings() {
+        let before = MOEX_FORTS.clearings(MIDNIGHT + hm(18, 49)).unwrap();
+        assert_eq!(MOEX_FORTS.clearings(MIDNIGHT + hm(10, 0)), Some(before));
+        assert_eq!(
+            MOEX_FORTS.clearings(MIDNIGHT + hm(18, 50)),
+            Some(before + 1)
+        );
+        assert_eq!(
+            MOEX_FORTS.clearings(MIDNIGHT + NANOS_IN_DAY + hm(18, 49)),
+            Some(before + 1)
+        );
+        assert_eq!(ALWAYS_OPEN.clearings(MIDNIGHT), None);
+    }
+}
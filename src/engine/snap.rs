@@ -1,12 +1,11 @@
-#![allow(clippy::too_many_arguments)]
 use crate::{
     dbgp,
     engine::event::LimitOrder,
     engine::matching_engine::{Order, OrderBook, Side},
-    engine::ExecutionReport,
+    engine::{ExecutionReport, QueueModel},
 };
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 #[allow(dead_code)]
 pub struct Snap {
     pub exch_epoch: u64,
@@ -34,7 +33,56 @@ impl IntoIterator for Snap {
     }
 }
 
-type Offset = (Side, u32, u32, u32, u32, u64);
+impl OrderBook {
+    /// Top `depth` non empty levels per side, bids then asks, best first
+    pub fn to_snap(&self, exch_epoch: u64, depth: usize) -> Snap {
+        let bids = self
+            .bid_book
+            .price_map
+            .keys()
+            .rev()
+            .map(|&price| LimitOrder {
+                side: Side::Bid,
+                price,
+                qty: self.bid_book.get_total_qty(price),
+            });
+        let asks = self.ask_book.price_map.keys().map(|&price| LimitOrder {
+            side: Side::Ask,
+            price,
+            qty: self.ask_book.get_total_qty(price),
+        });
+        Snap {
+            exch_epoch,
+            vec: bids
+                .filter(|level| level.qty > 0)
+                .take(depth)
+                .chain(asks.filter(|level| level.qty > 0).take(depth))
+                .collect(),
+        }
+    }
+}
+
+/// Own orders resting in one price level and the queue around them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LevelQueue {
+    pub side: Side,
+    pub price: u32,
+    // Own orders, front of the queue first
+    pub orders: Vec<Order>,
+    // Qty of other participants ahead of each own order and behind
+    // the last one, always one element longer than `orders`
+    pub gaps: Vec<u32>,
+}
+
+impl LevelQueue {
+    pub fn others_qty(&self) -> u32 {
+        self.gaps.iter().sum()
+    }
+}
+
+/// Synthetic ids of the queue around own orders start above this,
+/// far from epoch based ids
+pub const QUEUE_ID_BASE: u64 = 1 << 63;
 
 fn place_order_from_snap(snap: Snap, ob: &mut OrderBook) {
     for (id, level) in snap.into_iter().enumerate() {
@@ -75,141 +123,83 @@ pub fn place_body(allow_fill: bool) -> impl Fn(&mut OrderBook, Order) -> Executi
     }
 }
 
-fn place_head_tail(
+/// Restores own orders of a level with the queue adjusted to `new_qty`
+fn place_level(
     ob: &mut OrderBook,
+    queue_model: &mut QueueModel,
     body_f: impl Fn(&mut OrderBook, Order) -> ExecutionReport,
-    qty_head: u32,
-    qty_tail: u32,
-    qty: u32,
+    mut level: LevelQueue,
     new_qty: u32,
-    id: u64,
-    side: Side,
-    price: u32,
-) -> ExecutionReport {
-    dbgp!("{} {} {} {:?} {}", qty_head, qty, qty_tail, side, price);
-    let (qty_head, qty_tail) = if new_qty < qty_head + qty_tail {
-        let need_to_cut = qty_tail + qty_head - new_qty;
-        let cut_qty_tail = qty_tail.min(need_to_cut);
-        (
-            qty_head + cut_qty_tail - need_to_cut,
-            qty_tail - cut_qty_tail,
-        )
-    } else if new_qty >= qty_head + qty_tail {
-        (qty_head, new_qty - qty_head)
-    } else {
-        unreachable!()
-    };
-    if qty_head > 0 {
-        let _ = ob.add_limit_order(Order {
-            id: id - 1,
-            side,
-            price,
-            qty: qty_head,
-        });
+    next_id: &mut u64,
+) -> Vec<ExecutionReport> {
+    dbgp!("{:?} {}", level, new_qty);
+    let others_qty = level.others_qty();
+    if new_qty < others_qty {
+        queue_model.cut_gaps(&mut level.gaps, others_qty - new_qty);
+    } else if let Some(last) = level.gaps.last_mut() {
+        // New orders join the back of the queue
+        *last += new_qty - others_qty;
     }
-    let order = Order {
-        id,
-        side,
-        price,
-        qty,
-    };
-
-    let exec_report = body_f(ob, order);
-
-    if qty_tail > 0 {
+    let mut exec_reports = Vec::with_capacity(level.orders.len());
+    for (order, &gap) in level.orders.iter().zip(&level.gaps) {
+        if gap > 0 {
+            *next_id += 1;
+            let _ = ob.add_limit_order(Order {
+                id: *next_id,
+                side: level.side,
+                price: level.price,
+                qty: gap,
+            });
+        }
+        exec_reports.push(body_f(ob, *order));
+    }
+    if let Some(&tail) = level.gaps.last().filter(|&&tail| tail > 0) {
+        *next_id += 1;
         let _ = ob.add_limit_order(Order {
-            id: id + 1,
-            side,
-            price,
-            qty: qty_tail,
+            id: *next_id,
+            side: level.side,
+            price: level.price,
+            qty: tail,
         });
     }
-    exec_report
+    exec_reports
 }
 
+/// Builds the book from `snap` and puts own orders back into their queues.
+/// Returns one execution report per own order.
 pub fn next_snap(
     snap: Snap,
-    offsets: (Result<Offset, &str>, Result<Offset, &str>),
+    levels: Vec<LevelQueue>,
+    queue_model: &mut QueueModel,
     body_f: impl Fn(&mut OrderBook, Order) -> ExecutionReport,
-) -> (OrderBook, Option<ExecutionReport>, Option<ExecutionReport>) {
+) -> (OrderBook, Vec<ExecutionReport>) {
     let mut ob = OrderBook::new();
-    let mut exec_report_bid = None;
-    let mut exec_report_ask = None;
-    match (offsets.0.ok(), offsets.1.ok()) {
-        | (
-            Some((Side::Bid, price_bid, qty_head_bid, qty_bid, qty_tail_bid, id_bid)),
-            Some((Side::Ask, price_ask, qty_head_ask, qty_ask, qty_tail_ask, id_ask)),
-        ) => {
-            let mut filtered_snap = Snap::new();
-            let mut new_qty_bid = 0;
-            let mut new_qty_ask = 0;
-            for level in snap {
-                if level.price == price_bid && level.side == Side::Bid {
-                    new_qty_bid = level.qty;
-                } else if level.price == price_ask && level.side == Side::Ask {
-                    new_qty_ask = level.qty;
-                } else {
-                    filtered_snap.push(level);
-                }
-            }
-            place_order_from_snap(filtered_snap, &mut ob);
-            exec_report_bid = Some(place_head_tail(
-                &mut ob,
-                &body_f,
-                qty_head_bid,
-                qty_tail_bid,
-                qty_bid,
-                new_qty_bid,
-                id_bid,
-                Side::Bid,
-                price_bid,
-            ));
-            exec_report_ask = Some(place_head_tail(
-                &mut ob,
-                &body_f,
-                qty_head_ask,
-                qty_tail_ask,
-                qty_ask,
-                new_qty_ask,
-                id_ask,
-                Side::Ask,
-                price_ask,
-            ));
-        }
-        | (Some((side, bid_price, qty_head, qty, qty_tail, id)), None) => {
-            let mut filtered_snap = Snap::new();
-            let mut new_qty = 0;
-            for level in snap {
-                if level.price == bid_price && level.side == Side::Bid {
-                    new_qty = level.qty;
-                } else {
-                    filtered_snap.push(level);
-                }
-            }
-            place_order_from_snap(filtered_snap, &mut ob);
-            exec_report_bid = Some(place_head_tail(
-                &mut ob, body_f, qty_head, qty_tail, qty, new_qty, id, side, bid_price,
-            ));
+    let mut new_qtys = vec![0; levels.len()];
+    let mut filtered_snap = Snap::new();
+    for level in snap {
+        if let Some(idx) = levels
+            .iter()
+            .position(|own| own.side == level.side && own.price == level.price)
+        {
+            new_qtys[idx] = level.qty;
+        } else {
+            filtered_snap.push(level);
         }
-        | (None, Some((side, ask_price, qty_head, qty, qty_tail, id))) => {
-            let mut filtered_snap = Snap::new();
-            let mut new_qty = 0;
-            for level in snap {
-                if level.price == ask_price && level.side == Side::Ask {
-                    new_qty = level.qty;
-                } else {
-                    filtered_snap.push(level);
-                }
-            }
-            place_order_from_snap(filtered_snap, &mut ob);
-            exec_report_ask = Some(place_head_tail(
-                &mut ob, body_f, qty_head, qty_tail, qty, new_qty, id, side, ask_price,
-            ));
-        }
-        | (None, None) => place_order_from_snap(snap, &mut ob),
-        | (_, _) => unreachable!(),
     }
-    (ob, exec_report_bid, exec_report_ask)
+    place_order_from_snap(filtered_snap, &mut ob);
+    let mut next_id = QUEUE_ID_BASE;
+    let mut exec_reports = Vec::new();
+    for (level, new_qty) in levels.into_iter().zip(new_qtys) {
+        exec_reports.extend(place_level(
+            &mut ob,
+            queue_model,
+            &body_f,
+            level,
+            new_qty,
+            &mut next_id,
+        ));
+    }
+    (ob, exec_reports)
 }
 
 #[cfg(test)]
@@ -219,7 +209,7 @@ mod tests {
     use crate::engine::Ticker;
     use crate::{
         backtest::FixSpreadStrategy, engine::account::TradingAccount,
-        engine::matching_engine::Side, management::OrderManagementSystem,
+        engine::matching_engine::Side, engine::Money, management::OrderManagementSystem,
     };
     use pretty_assertions::assert_eq;
 
@@ -243,7 +233,7 @@ mod tests {
         // let offset = Ok((Side::Bid, 101, 0, 1, 0, 999));
         let mut ob = OrderBook::new();
         let strat = &mut FixSpreadStrategy::new(Ticker::default());
-        let oms = &mut OrderManagementSystem::new(strat, TradingAccount::new(0.0));
+        let oms = &mut OrderManagementSystem::new(strat, TradingAccount::new(Money::ZERO));
         ob = ob.process(snap, oms, place_body(false));
         assert_eq!(ob.get_bbo().unwrap(), (99, 101, 2));
     }
@@ -1,11 +1,15 @@
+use crate::engine::{FeeSchedule, Money, SessionCalendar, ALWAYS_OPEN};
+
 pub struct Ticker {
     pub ticker_id: u64,
     // Minimal increment
     pub tick_size: f32,
     // Cost of tick_size in cash
     pub step_price: f32,
-    pub taker_fee: f32,
-    pub maker_fee: f32,
+    pub fees: FeeSchedule,
+    pub calendar: SessionCalendar,
+    // Initial margin per contract, zero for instruments traded without margin
+    pub initial_margin: Money,
 }
 
 impl Default for Ticker {
@@ -14,8 +18,16 @@ impl Default for Ticker {
             ticker_id: 0,
             tick_size: 1.0,
             step_price: 0.1,
-            taker_fee: 0.0,
-            maker_fee: 0.0,
+            fees: FeeSchedule::NONE,
+            calendar: ALWAYS_OPEN,
+            initial_margin: Money::ZERO,
         }
     }
 }
+
+impl Ticker {
+    /// Cash value of a single price unit, `Money` and volumes are counted in them
+    pub const fn unit_cash(&self) -> f32 {
+        self.step_price / self.tick_size
+    }
+}
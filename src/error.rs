@@ -2,11 +2,15 @@ use thiserror::Error;
 #[derive(Debug, Error)]
 pub enum MyError {
     #[error("IO error: {0}")]
-    IoError(std::io::Error),
+    IoError(#[from] std::io::Error),
     #[error("Parse error: {0}")]
     ParseError(std::num::ParseIntError),
     #[error("CSV read error: {0}")]
     CsvError(#[from] csv::Error),
+    #[error("Binary format error: {0}")]
+    FormatError(String),
+    #[error("Order state error: {0}")]
+    OrderStateError(String),
 }
 
 // impl std::fmt::Display for MyError {
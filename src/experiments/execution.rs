@@ -1,13 +1,59 @@
 use crate::{
+    data::{open_market_data, CleaningPolicy, EventKind, MarketEvent},
     dbgp,
     engine::indicators::BestBidOffer,
-    engine::{place_body, Order, OrderBook, Snap},
+    engine::{place_body, OrderBook},
     experiments::{Ready, Schedule},
-    management::OrderManagementSystem,
+    management::{OrderManagementSystem, OrderTag, QuoteId},
 };
 
 use crate::backtest::FixPriceStrategy;
+use log::warn;
+use std::fmt;
 
+/// Outcomes of the experiment orders, every order is either filled
+/// or censored after 10 seconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FillStats {
+    pub queue_model: &'static str,
+    pub filled: u32,
+    pub censored: u32,
+}
+
+impl FillStats {
+    pub const fn record(&mut self, filled: bool) {
+        if filled {
+            self.filled += 1;
+        } else {
+            self.censored += 1;
+        }
+    }
+
+    pub fn fill_probability(&self) -> Option<f32> {
+        match self.filled + self.censored {
+            | 0 => None,
+            #[allow(clippy::cast_precision_loss)]
+            | total => Some(self.filled as f32 / total as f32),
+        }
+    }
+}
+
+impl fmt::Display for FillStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Queue model = {}\nFilled      = {}\nCensored    = {}\nFill prob   = {:.3}",
+            self.queue_model,
+            self.filled,
+            self.censored,
+            self.fill_probability().unwrap_or_default()
+        )
+    }
+}
+
+/// Replays the files and reports fills of the experiment orders
+/// under `oms.queue_model`, the files validated under `cleaning`.
+///
 /// # Panics
 ///
 /// Will panic if file read fails
@@ -16,52 +62,46 @@ pub fn execution_flow(
     ob: &mut OrderBook,
     ob_path: &str,
     orders_path: &str,
-) {
-    let mut snap_reader = csv::Reader::from_path(ob_path).unwrap();
-    let mut trade_reader = csv::Reader::from_path(orders_path).unwrap();
-    let mut srdr = snap_reader.deserialize::<Snap>();
-    let mut trdr = trade_reader.deserialize::<Order>();
-    let mut epoch = 0;
-    let mut trader_buy_id;
-    let mut trader_sell_id;
-    let mut next_order = Order::default();
+    cleaning: CleaningPolicy,
+) -> FillStats {
+    let calendar = oms.strategy.ticker.calendar;
+    let events = open_market_data(ob_path, orders_path, cleaning).unwrap();
+    // Skip all trades that occured before the first snapshot
+    let mut events = events
+        .map_while(Result::ok)
+        .skip_while(|event| event.kind() != EventKind::Snap);
     dbgp!("Crafting Orderbook");
     // Load first snapshot
-    if let Some(Ok(first_snap)) = srdr.next() {
-        epoch = first_snap.exch_epoch;
-        dbgp!("[ EPCH ] snap {:?}", epoch);
+    if let Some(MarketEvent::Snap(first_snap)) = events.next() {
+        dbgp!("[ EPCH ] snap {:?}", first_snap.exch_epoch);
         *ob = ob.process(first_snap, oms, place_body(true));
     }
 
-    // Skip all trades that occured before the first snapshot
-    while next_order.id < epoch {
-        if let Some(Ok(order)) = trdr.next() {
-            next_order = order;
-        }
-    }
-
-    'a: while let Some(Ok(snap)) = srdr.next() {
-        epoch = snap.exch_epoch;
-        // let strategy_epoch = epoch + 100;
-        loop {
-            if next_order.id <= epoch {
-                // Apply order
-                dbgp!("[ EPCH ] order {:?}", next_order.id);
-                let exec_report = ob.add_limit_order(next_order);
+    // Replay until last trade or snapshot
+    for event in events {
+        match event {
+            | MarketEvent::Trade(trade) => {
+                dbgp!("[ EPCH ] trade {:?}", trade.exch_epoch);
+                let (exec_report, mismatch) = oms.apply_trade(ob, &trade);
+                if let Some(mismatch) = mismatch {
+                    warn!(target: "replay", "{:?}", mismatch);
+                }
                 dbgp!("{:#?}", exec_report);
                 // Updates active order when filled, releases price lock, restarts scheduler
-                oms.update(&exec_report);
-                // Load next order
-                if let Some(Ok(order)) = trdr.next() {
-                    next_order = order;
-                } else {
-                    // Replay until last order
-                    break 'a;
-                }
-            // If next snap before order
-            } else if epoch < next_order.id {
-                // Load next snap
+                oms.update_experiment(&exec_report, trade.exch_epoch);
+            }
+            | MarketEvent::Snap(snap) => {
+                let epoch = snap.exch_epoch;
                 dbgp!("[ EPCH ] snap {:?}", epoch);
+                // No quoting while matching is halted
+                if !calendar.is_open(epoch) {
+                    if oms.active_buy_order.or(oms.active_sell_order).is_some() {
+                        oms.cancel_all_orders(ob);
+                        oms.lock_release();
+                        oms.schedule = Schedule::default();
+                    }
+                    continue;
+                }
                 // Trader's move
                 // Experiment is live
                 dbgp!(
@@ -73,16 +113,22 @@ pub fn execution_flow(
                 if let Some(order) = oms.active_buy_order.or(oms.active_sell_order) {
                     // 10s censoring
                     // add price logging
-                    if epoch - order.id >= 10_000_000_000 {
+                    if oms.ids.age(order.id, epoch).unwrap_or_default() >= 10_000_000_000 {
                         oms.cancel_all_orders(ob);
-                        println!("[  DB  ];{};{};{};{};", order.id, epoch, 10_000_000, 0);
+                        println!(
+                            "[  DB  ];{};{};{};{};",
+                            oms.dump_id(order.id),
+                            epoch,
+                            10_000_000,
+                            0
+                        );
+                        oms.strategy.fills.record(false);
                         oms.lock_release();
                         oms.schedule = Schedule::default();
                     } else {
                         *ob = ob.process_w_takers(snap, oms, place_body(true));
-                        trader_buy_id = epoch + 3;
-                        trader_sell_id = epoch + 7;
-                        oms.send_orders(ob, epoch, trader_buy_id, trader_sell_id);
+                        let id = QuoteId::fresh(epoch, OrderTag::Experiment);
+                        oms.send_orders(ob, epoch, id, id);
                     }
                 // No active orders
                 } else {
@@ -98,21 +144,21 @@ pub fn execution_flow(
                             oms.strategy.sell_price = oms.lock_ask_price(bbo).ok();
                             dbgp!("[ LOCK ] BID: {:?}", oms.strategy.buy_price);
                             dbgp!("[ LOCK ] ASK: {:?}", oms.strategy.sell_price);
-                            trader_buy_id = epoch + 3;
-                            trader_sell_id = epoch + 7;
-                            oms.send_orders(ob, epoch, trader_buy_id, trader_sell_id);
+                            let id = QuoteId::fresh(epoch, OrderTag::Experiment);
+                            oms.send_orders(ob, epoch, id, id);
                         }
                         | Ready::No => oms.schedule.incr_counter(),
                     }
                 }
-                // dbgp!("{:?}", ob.get_order(oms.active_buy_order));
-                // dbgp!("{:?}", ob.get_order(oms.active_sell_order));
-                break;
-                // } else if strategy_epoch < epoch.min(next_order.id) {
             }
+            | MarketEvent::Signal(_) => {}
         }
     }
     dbgp!("{:#?}", ob);
     let _ = ob.get_bbo();
     dbgp!("Done!");
+    FillStats {
+        queue_model: oms.queue_model.name(),
+        ..oms.strategy.fills
+    }
 }
@@ -11,7 +11,6 @@
 // TODO: Cover all mutants
 // TODO: Make FixPrice 1st class citizen
 // Store total qty on level
-// Add Strategy builder
 #![warn(clippy::pedantic)]
 #![warn(clippy::nursery)]
 #![allow(
@@ -27,6 +26,7 @@
 extern crate proc_macro;
 
 pub mod backtest;
+pub mod data;
 pub mod engine;
 pub mod error;
 pub mod experiments;
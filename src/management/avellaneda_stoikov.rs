@@ -0,0 +1,42 @@
+//! Quoting of `AvellanedaStoikovStrategy`. The OMS calibrates the
+//! strategy on the mid of the book without own orders and turns the
+//! prices of the model into offsets quoted as any other policy.
+
+use crate::{
+    backtest::AvellanedaStoikovStrategy,
+    engine::{indicators::Midprice, OrderBook, SessionAction},
+    management::{OrderManagementSystem, OrderTag, QuoteId},
+};
+
+impl OrderManagementSystem<'_, AvellanedaStoikovStrategy> {
+    /// Reacts to a snapshot decided at `epoch`: calibrates the volatility
+    /// on the mid of `seen`, the book without own orders at that time,
+    /// quotes the model prices once warmed up, flattens before the close
+    /// and pulls the quotes while matching is halted
+    pub fn requote(&mut self, ob: &mut OrderBook, seen: &OrderBook, epoch: u64) {
+        let calendar = self.strategy.ticker.calendar;
+        match calendar.action(epoch, self.strategy.flatten_before_close) {
+            | SessionAction::Flatten => {
+                let _ = self.flatten(ob, epoch);
+            }
+            | SessionAction::Quote => {
+                let m = Midprice::evaluate(seen);
+                if let Some(mid) = m {
+                    self.strategy.observe_mid(epoch, mid);
+                }
+                let position = self.position();
+                let quotes =
+                    m.and_then(|mid| Some((mid, self.strategy.quotes(mid, epoch, position)?)));
+                let Some((mid, (bid, ask))) = quotes else {
+                    self.cancel_all_orders(ob);
+                    return;
+                };
+                self.strategy.offsets = (bid / mid - 1.0, ask / mid - 1.0);
+                // A bid the model puts at or below zero is not quoted
+                let id = QuoteId::fresh(epoch, OrderTag::Quote);
+                let _ = self.send_quotes(ob, m, (bid > 0.0).then_some(id), Some(id));
+            }
+            | SessionAction::CancelAll => self.cancel_all_orders(ob),
+        }
+    }
+}
@@ -4,9 +4,11 @@
 use crate::{
     backtest::FixPriceStrategy,
     dbgp,
-    engine::{ExecutionReport, Order, OrderBook, OrderStatus, Side},
+    engine::{
+        indicators::BestBidOffer, ExecutionReport, Money, Order, OrderBook, OrderStatus, Side,
+    },
     experiments::Schedule,
-    management::OrderManagementSystem,
+    management::{OrderManagementSystem, OrderTag, QuoteId},
 };
 impl OrderManagementSystem<'_, FixPriceStrategy> {
     fn send_buy_order(&mut self, ob: &mut OrderBook, epoch: u64) {
@@ -18,20 +20,21 @@ impl OrderManagementSystem<'_, FixPriceStrategy> {
                 .unwrap();
             dbgp!("Amend buy order {:?}", exec_report);
         } else {
-            exec_report = ob.add_limit_order(self.strategy_buy_signal.unwrap());
+            exec_report = self.submit(ob, self.strategy_buy_signal.unwrap());
             dbgp!("New buy order {:?}", exec_report);
         }
         if exec_report.status == OrderStatus::Filled {
             println!(
                 "[  DB  ];{};{};{};{};",
-                exec_report.own_id,
+                self.dump_id(exec_report.own_id),
                 epoch,
-                (epoch + 3 - exec_report.own_id) / 1000,
+                self.ids.age(exec_report.own_id, epoch).unwrap_or_default() / 1000,
                 1
             );
+            self.strategy.fills.record(true);
             self.lock_release();
             self.schedule = Schedule::default();
-        } else {
+        } else if exec_report.status != OrderStatus::Rejected {
             self.active_buy_order = self.strategy_buy_signal;
         }
     }
@@ -44,20 +47,21 @@ impl OrderManagementSystem<'_, FixPriceStrategy> {
                 .unwrap();
             dbgp!("Amend sell order {:?}", exec_report);
         } else {
-            exec_report = ob.add_limit_order(self.strategy_sell_signal.unwrap());
+            exec_report = self.submit(ob, self.strategy_sell_signal.unwrap());
             dbgp!("New sell order {:?}", exec_report);
         }
         if exec_report.status == OrderStatus::Filled {
             println!(
                 "[  DB  ];{};{};{};{};",
-                exec_report.own_id,
+                self.dump_id(exec_report.own_id),
                 epoch,
-                (epoch + 7 - exec_report.own_id) / 1000,
+                self.ids.age(exec_report.own_id, epoch).unwrap_or_default() / 1000,
                 1
             );
+            self.strategy.fills.record(true);
             self.lock_release();
             self.schedule = Schedule::default();
-        } else {
+        } else if exec_report.status != OrderStatus::Rejected {
             self.active_sell_order = self.strategy_sell_signal;
         }
     }
@@ -99,6 +103,18 @@ impl OrderManagementSystem<'_, FixPriceStrategy> {
         Ok(ask_price)
     }
 
+    /// Own `id` as the fill dump prints it, the creation epoch
+    /// tagged by side: +3 for bids and +7 for asks
+    pub fn dump_id(&self, id: u64) -> u64 {
+        self.ids.get(id).map_or(id, |meta| {
+            meta.created_epoch
+                + match meta.side {
+                    | Side::Bid => 3,
+                    | Side::Ask => 7,
+                }
+        })
+    }
+
     pub const fn lock_release(&mut self) {
         self.strategy.buy_price = None;
         self.strategy.sell_price = None;
@@ -138,6 +154,8 @@ impl OrderManagementSystem<'_, FixPriceStrategy> {
         Ok(order)
     }
 
+    /// Places the locked prices, fresh ids are allocated only for orders sent
+    ///
     /// # Panics
     ///
     /// Will panic
@@ -145,8 +163,8 @@ impl OrderManagementSystem<'_, FixPriceStrategy> {
         &mut self,
         ob: &mut OrderBook,
         epoch: u64,
-        trader_buy_id: u64,
-        trader_sell_id: u64,
+        trader_buy_id: QuoteId,
+        trader_sell_id: QuoteId,
     ) {
         let mut send_buy_order = false;
         let mut send_sell_order = false;
@@ -156,23 +174,27 @@ impl OrderManagementSystem<'_, FixPriceStrategy> {
         //     self.active_sell_order
         // );
 
-        if let Ok(buy_order) = self.calculate_buy_order(trader_buy_id) {
+        if let Ok(buy_order) = self.calculate_buy_order(0) {
             match self.active_buy_order {
                 | None => {
+                    let buy_order = Order {
+                        id: self.resolve_id(trader_buy_id, Side::Bid),
+                        ..buy_order
+                    };
                     dbgp!("[ STRAT] Order not found, place new order");
                     dbgp!("[ STRAT] send {:#?}", buy_order);
                     self.strategy_buy_signal = Some(buy_order);
                     send_buy_order = true;
                 }
                 | Some(Order {
-                    id: _id,
+                    id,
                     side: Side::Bid,
                     price,
                     qty: _qty,
                 }) if price == buy_order.price => {
                     dbgp!("[ STRAT] Order found, passing");
                     dbgp!("[ STRAT] price = {}", price);
-                    self.strategy_buy_signal = Some(buy_order);
+                    self.strategy_buy_signal = Some(Order { id, ..buy_order });
                 }
                 | Some(Order {
                     id: _id,
@@ -199,19 +221,23 @@ impl OrderManagementSystem<'_, FixPriceStrategy> {
                 }) => unreachable!(),
             }
         } else if let Some(order) = self.active_buy_order {
-            let _ = ob.cancel_order(order.id);
+            let _ = self.cancel(ob, order.id);
         }
 
-        if let Ok(sell_order) = self.calculate_sell_order(trader_sell_id) {
+        if let Ok(sell_order) = self.calculate_sell_order(0) {
             match self.active_sell_order {
                 | None => {
+                    let sell_order = Order {
+                        id: self.resolve_id(trader_sell_id, Side::Ask),
+                        ..sell_order
+                    };
                     dbgp!("[ STRAT] Order not found, place new order");
                     dbgp!("[ STRAT] send {:#?}", sell_order);
                     self.strategy_sell_signal = Some(sell_order);
                     send_sell_order = true;
                 }
                 | Some(Order {
-                    id: _id,
+                    id,
                     side: Side::Ask,
                     price,
                     qty: _qty,
@@ -219,7 +245,7 @@ impl OrderManagementSystem<'_, FixPriceStrategy> {
                 }) if price == sell_order.price => {
                     dbgp!("[ STRAT] Order found, passing");
                     dbgp!("[ STRAT] price = {}", price);
-                    self.strategy_sell_signal = Some(sell_order);
+                    self.strategy_sell_signal = Some(Order { id, ..sell_order });
                 }
                 | Some(Order {
                     id: _id,
@@ -246,7 +272,7 @@ impl OrderManagementSystem<'_, FixPriceStrategy> {
                 }) => unreachable!(),
             }
         } else if let Some(order) = self.active_sell_order {
-            let _ = ob.cancel_order(order.id);
+            let _ = self.cancel(ob, order.id);
         }
         match (send_buy_order, send_sell_order) {
             | (true, true) => {
@@ -269,7 +295,28 @@ impl OrderManagementSystem<'_, FixPriceStrategy> {
         }
     }
 
-    pub fn update(&mut self, exec_report: &ExecutionReport) {
+    /// Reacts to a snapshot decided at `epoch`: locks prices off the touch
+    /// of `seen`, the book without own orders at that time, while nothing
+    /// rests and keeps them until filled. Pulls the orders while matching
+    /// is halted.
+    pub fn requote(&mut self, ob: &mut OrderBook, seen: &OrderBook, epoch: u64) {
+        if !self.strategy.ticker.calendar.is_open(epoch) {
+            self.cancel_all_orders(ob);
+            self.lock_release();
+            return;
+        }
+        if self.active_buy_order.or(self.active_sell_order).is_none() {
+            let bbo = BestBidOffer::evaluate(seen);
+            self.strategy.buy_price = self.lock_bid_price(bbo).ok();
+            self.strategy.sell_price = self.lock_ask_price(bbo).ok();
+        }
+        let id = QuoteId::fresh(epoch, OrderTag::Experiment);
+        self.send_orders(ob, epoch, id, id);
+    }
+
+    /// Books fills of the experiment orders by hand, a side filled in full
+    /// releases the locked prices and restarts the schedule
+    pub fn update_experiment(&mut self, exec_report: &ExecutionReport, epoch: u64) {
         //Filled as Maker
         if let Some(order) = self.active_buy_order {
             if exec_report.own_side == Side::Ask {
@@ -285,7 +332,7 @@ impl OrderManagementSystem<'_, FixPriceStrategy> {
                         trader_filled_qty,
                         trader_filled_price,
                     );
-                    self.account.balance -= (trader_filled_qty * trader_filled_price) as f32;
+                    self.account.balance -= Money::notional(trader_filled_qty, trader_filled_price);
                     dbgp!("TRADER FILLED: {}", trader_filled_qty);
                     if let Some(active_buy) = self.active_buy_order {
                         if trader_filled_qty == active_buy.qty {
@@ -293,11 +340,12 @@ impl OrderManagementSystem<'_, FixPriceStrategy> {
                             self.strategy.buy_price = None;
                             println!(
                                 "[  DB  ];{};{};{};{};",
-                                active_buy.id,
-                                exec_report.own_id,
-                                (exec_report.own_id - active_buy.id + 3) / 1000,
+                                self.dump_id(active_buy.id),
+                                epoch,
+                                self.ids.age(active_buy.id, epoch).unwrap_or_default() / 1000,
                                 1
                             );
+                            self.strategy.fills.record(true);
                             self.lock_release();
                             self.schedule = Schedule::default();
                         } else {
@@ -328,7 +376,7 @@ impl OrderManagementSystem<'_, FixPriceStrategy> {
                     trader_filled_qty,
                     trader_filled_price,
                 );
-                self.account.balance += (trader_filled_qty * trader_filled_price) as f32;
+                self.account.balance += Money::notional(trader_filled_qty, trader_filled_price);
                 dbgp!("TRADER FILLED: {}", trader_filled_qty);
                 if let Some(active_sell) = self.active_sell_order {
                     if trader_filled_qty == active_sell.qty {
@@ -336,11 +384,12 @@ impl OrderManagementSystem<'_, FixPriceStrategy> {
                         self.strategy.sell_price = None;
                         println!(
                             "[  DB  ];{};{};{};{};",
-                            active_sell.id,
-                            exec_report.own_id,
-                            (exec_report.own_id - active_sell.id + 3) / 1000,
+                            self.dump_id(active_sell.id),
+                            epoch,
+                            self.ids.age(active_sell.id, epoch).unwrap_or_default() / 1000,
                             1
                         );
+                        self.strategy.fills.record(true);
                         self.lock_release();
                         self.schedule = Schedule::default();
                     } else {
@@ -0,0 +1,153 @@
+#![allow(clippy::cast_possible_truncation)]
+
+//! Hedging of fills in one instrument with orders in another. The hedge
+//! OMS follows the source position, quotes the gap at its own touch and
+//! crosses the spread once the gap has been open for too long.
+
+use crate::{
+    backtest::HedgeStrategy,
+    dbgp,
+    engine::indicators::BestBidOffer,
+    engine::{ExecutionReport, Liquidity, Money, OrderBook, Side},
+    management::{OrderManagementSystem, OrderTag, QuoteId},
+};
+use std::fmt;
+
+/// Cost of hedging against the mid of the hedge instrument at the time
+/// the gap opened. Kept apart from the pnl of the hedge account.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HedgeStats {
+    // Paid above arrival on buys and received below it on sells
+    pub slippage: Money,
+    pub passive_qty: u32,
+    pub aggressive_qty: u32,
+    // Gaps that outlived `escalate_after`
+    pub escalations: u32,
+}
+
+impl fmt::Display for HedgeStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Slippage    = {}\nPassive qty = {}\nAggr qty    = {}\nEscalations = {}",
+            self.slippage, self.passive_qty, self.aggressive_qty, self.escalations
+        )
+    }
+}
+
+impl OrderManagementSystem<'_, HedgeStrategy> {
+    /// Contracts left to trade, positive to buy
+    pub fn gap(&self) -> i32 {
+        self.strategy.target - self.position()
+    }
+
+    /// Moves the target after the source position changed to `source_position`
+    /// at `epoch`. A gap that opens is measured against `mid` of the hedge book.
+    pub fn follow(&mut self, source_position: i32, epoch: u64, mid: Option<f32>) {
+        self.book_hedge_fills();
+        self.strategy.target = (-self.strategy.hedge_ratio * source_position as f32).round() as i32;
+        dbgp!(
+            "[HEDGE ] source {} target {} gap {}",
+            source_position,
+            self.strategy.target,
+            self.gap()
+        );
+        if self.gap() == 0 {
+            self.close_gap();
+        } else if self.strategy.gap_since.is_none() {
+            self.strategy.gap_since = Some(epoch);
+            self.strategy.arrival = mid;
+        }
+    }
+
+    /// Works the gap at `epoch`: joins the own touch first and crosses
+    /// the spread once the gap is `escalate_after` old. An empty gap
+    /// cancels the resting hedge.
+    pub fn hedge(
+        &mut self,
+        ob: &mut OrderBook,
+        epoch: u64,
+        trader_buy_id: Option<QuoteId>,
+        trader_sell_id: Option<QuoteId>,
+    ) -> (Option<ExecutionReport>, Option<ExecutionReport>) {
+        self.book_hedge_fills();
+        let gap = self.gap();
+        let aggressive = self
+            .strategy
+            .gap_since
+            .is_some_and(|since| epoch.saturating_sub(since) >= self.strategy.escalate_after);
+        if aggressive && !self.strategy.escalated {
+            dbgp!("[HEDGE ] escalate gap {}", gap);
+            self.strategy.escalated = true;
+            self.strategy.stats.escalations += 1;
+        }
+        let Some((bid, ask)) = BestBidOffer::evaluate(ob) else {
+            return (None, None);
+        };
+        let ref_price = match (gap.signum(), aggressive) {
+            | (0, _) => None,
+            | (1, false) | (-1, true) => Some(bid as f32),
+            | _ => Some(ask as f32),
+        };
+        // A resting hedge of the wrong size is sent anew
+        let qty = gap.unsigned_abs().min(self.strategy.qty);
+        if let Some(order) = self.active_buy_order.filter(|order| order.qty != qty) {
+            let _ = self.cancel(ob, order.id);
+            self.active_buy_order = None;
+        }
+        if let Some(order) = self.active_sell_order.filter(|order| order.qty != qty) {
+            let _ = self.cancel(ob, order.id);
+            self.active_sell_order = None;
+        }
+        let exec_reports =
+            self.send_quotes_at(ob, ref_price, (0.0, 0.0), trader_buy_id, trader_sell_id);
+        self.book_hedge_fills();
+        exec_reports
+    }
+
+    /// Reacts to the gap decided at `epoch`: works it while the session
+    /// is open, an id is allocated only for the side that closes it.
+    /// Pulls the hedge while matching is halted.
+    pub fn rehedge(&mut self, ob: &mut OrderBook, epoch: u64) {
+        if !self.strategy.ticker.calendar.is_open(epoch) {
+            self.cancel_all_orders(ob);
+            return;
+        }
+        let id = Some(QuoteId::fresh(epoch, OrderTag::Hedge));
+        let (trader_buy_id, trader_sell_id) = match self.gap().signum() {
+            | 1 => (id, None),
+            | -1 => (None, id),
+            | _ => (None, None),
+        };
+        let _ = self.hedge(ob, epoch, trader_buy_id, trader_sell_id);
+    }
+
+    /// Adds fills of the hedge account not seen yet to `stats`,
+    /// a gap they closed starts anew
+    pub fn book_hedge_fills(&mut self) {
+        let strategy = &mut *self.strategy;
+        for fill in &self.account.fills[strategy.booked..] {
+            match fill.liquidity {
+                | Liquidity::Maker => strategy.stats.passive_qty += fill.qty,
+                | Liquidity::Taker => strategy.stats.aggressive_qty += fill.qty,
+            }
+            if let Some(arrival) = strategy.arrival {
+                let paid = (f64::from(fill.price) - f64::from(arrival)) * f64::from(fill.qty);
+                strategy.stats.slippage += match fill.side {
+                    | Side::Bid => Money::from_f64(paid),
+                    | Side::Ask => Money::from_f64(-paid),
+                };
+            }
+        }
+        strategy.booked = self.account.fills.len();
+        if self.gap() == 0 {
+            self.close_gap();
+        }
+    }
+
+    const fn close_gap(&mut self) {
+        self.strategy.gap_since = None;
+        self.strategy.arrival = None;
+        self.strategy.escalated = false;
+    }
+}
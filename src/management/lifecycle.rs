@@ -0,0 +1,282 @@
+//! Lifecycle of own orders. The OMS sends requests, the engine answers
+//! with acks, fills and cancels, every answer moves the order along
+//!
+//! ```text
+//! PendingNew -> New -> PartiallyFilled -> Filled
+//!      |         |            |
+//!      |         +-> PendingCancel / PendingReplace -> Cancelled
+//!      +-> Rejected
+//! ```
+//!
+//! Fills may overtake a cancel: a pending cancel that loses the race
+//! ends up Filled and the late cancel answer is ignored.
+
+use crate::{engine::Order, error::MyError};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderState {
+    PendingNew,
+    New,
+    PartiallyFilled,
+    PendingCancel,
+    PendingReplace,
+    Filled,
+    Cancelled,
+    Rejected,
+}
+
+impl OrderState {
+    pub const fn is_terminal(self) -> bool {
+        matches!(self, Self::Filled | Self::Cancelled | Self::Rejected)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderEvent {
+    // Requests of the OMS
+    CancelRequest,
+    ReplaceRequest,
+    // Responses of the engine
+    Ack,
+    Reject,
+    Fill { qty: u32 },
+    // Cancel or replace done, or the engine dropped the order on its own
+    Cancelled,
+    // Nothing to cancel, the order is already gone or about to fill
+    CancelReject,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderRecord {
+    pub order: Order,
+    pub state: OrderState,
+    pub filled_qty: u32,
+}
+
+impl OrderRecord {
+    pub const fn leaves_qty(&self) -> u32 {
+        self.order.qty - self.filled_qty
+    }
+
+    // Where a rejected cancel or replace returns to
+    const fn working_state(&self) -> OrderState {
+        match self.filled_qty {
+            | 0 => OrderState::New,
+            | _ => OrderState::PartiallyFilled,
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if `event` is not expected in the current state
+    /// or a fill exceeds the leaves qty
+    pub fn apply(&mut self, event: OrderEvent) -> Result<OrderState, MyError> {
+        use OrderEvent as E;
+        use OrderState as S;
+        let state = match (self.state, event) {
+            | (S::PendingNew, E::Ack) => S::New,
+            | (S::PendingNew, E::Reject) => S::Rejected,
+            | (S::New | S::PartiallyFilled, E::CancelRequest) => S::PendingCancel,
+            | (S::New | S::PartiallyFilled, E::ReplaceRequest) => S::PendingReplace,
+            | (S::PendingCancel | S::PendingReplace, E::CancelReject) => self.working_state(),
+            | (
+                S::PendingNew | S::New | S::PartiallyFilled | S::PendingCancel | S::PendingReplace,
+                E::Cancelled,
+            ) => S::Cancelled,
+            | (
+                S::PendingNew | S::New | S::PartiallyFilled | S::PendingCancel | S::PendingReplace,
+                E::Fill { qty },
+            ) if qty <= self.leaves_qty() => {
+                self.filled_qty += qty;
+                match (self.leaves_qty(), self.state) {
+                    | (0, _) => S::Filled,
+                    // Cancel is still on its way
+                    | (_, S::PendingCancel | S::PendingReplace) => self.state,
+                    | _ => S::PartiallyFilled,
+                }
+            }
+            // Cancel lost the race to a fill
+            | (S::Filled, E::Cancelled | E::CancelReject) => S::Filled,
+            | (state, event) => {
+                return Err(MyError::OrderStateError(format!(
+                    "order {} got {event:?} in state {state:?}",
+                    self.order.id
+                )))
+            }
+        };
+        self.state = state;
+        Ok(state)
+    }
+}
+
+/// Every own order ever sent, terminal ones are kept for the record
+#[derive(Debug, Clone, Default)]
+pub struct OrderTracker {
+    orders: HashMap<u64, OrderRecord>,
+    // Ids not yet in a terminal state, the pre-trade checks scan only these
+    open: HashSet<u64>,
+}
+
+impl OrderTracker {
+    /// Starts tracking `order` as `PendingNew`
+    pub fn submit(&mut self, order: Order) {
+        self.orders.insert(
+            order.id,
+            OrderRecord {
+                order,
+                state: OrderState::PendingNew,
+                filled_qty: 0,
+            },
+        );
+        self.open.insert(order.id);
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the order is unknown or the transition is illegal
+    pub fn apply(&mut self, id: u64, event: OrderEvent) -> Result<OrderState, MyError> {
+        let state = self
+            .orders
+            .get_mut(&id)
+            .ok_or_else(|| MyError::OrderStateError(format!("unknown order {id}")))?
+            .apply(event)?;
+        if state.is_terminal() {
+            self.open.remove(&id);
+        }
+        Ok(state)
+    }
+
+    pub fn get(&self, id: u64) -> Option<&OrderRecord> {
+        self.orders.get(&id)
+    }
+
+    pub fn state(&self, id: u64) -> Option<OrderState> {
+        self.get(id).map(|record| record.state)
+    }
+
+    pub fn is_tracked(&self, id: u64) -> bool {
+        self.orders.contains_key(&id)
+    }
+
+    /// Orders that are not yet Filled, Cancelled or Rejected
+    pub fn open_orders(&self) -> impl Iterator<Item = &OrderRecord> {
+        self.open.iter().map(|id| &self.orders[id])
+    }
+
+    pub fn len(&self) -> usize {
+        self.orders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Side;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn record(state: OrderState, filled_qty: u32) -> OrderRecord {
+        OrderRecord {
+            order: Order {
+                id: 1,
+                side: Side::Bid,
+                price: 100,
+                qty: 10,
+            },
+            state,
+            filled_qty,
+        }
+    }
+
+    #[rstest]
+    #[case(OrderState::PendingNew, 0, OrderEvent::Ack, OrderState::New)]
+    #[case(OrderState::PendingNew, 0, OrderEvent::Reject, OrderState::Rejected)]
+    #[case(OrderState::New, 0, OrderEvent::Fill { qty: 4 }, OrderState::PartiallyFilled)]
+    #[case(OrderState::PartiallyFilled, 4, OrderEvent::Fill { qty: 6 }, OrderState::Filled)]
+    #[case(
+        OrderState::New,
+        0,
+        OrderEvent::CancelRequest,
+        OrderState::PendingCancel
+    )]
+    #[case(
+        OrderState::PendingCancel,
+        0,
+        OrderEvent::Cancelled,
+        OrderState::Cancelled
+    )]
+    #[case(
+        OrderState::PendingCancel,
+        4,
+        OrderEvent::CancelReject,
+        OrderState::PartiallyFilled
+    )]
+    #[case(
+        OrderState::PendingReplace,
+        0,
+        OrderEvent::CancelReject,
+        OrderState::New
+    )]
+    #[case(OrderState::New, 0, OrderEvent::Cancelled, OrderState::Cancelled)]
+    // Fills overtaking a cancel
+    #[case(OrderState::PendingCancel, 0, OrderEvent::Fill { qty: 4 }, OrderState::PendingCancel)]
+    #[case(OrderState::PendingCancel, 0, OrderEvent::Fill { qty: 10 }, OrderState::Filled)]
+    #[case(OrderState::Filled, 10, OrderEvent::Cancelled, OrderState::Filled)]
+    #[case(OrderState::Filled, 10, OrderEvent::CancelReject, OrderState::Filled)]
+    fn transitions(
+        #[case] state: OrderState,
+        #[case] filled_qty: u32,
+        #[case] event: OrderEvent,
+        #[case] expected: OrderState,
+    ) {
+        let mut record = record(state, filled_qty);
+        assert_eq!(record.apply(event).unwrap(), expected);
+        assert_eq!(record.state, expected);
+    }
+
+    #[rstest]
+    #[case(OrderState::New, 0, OrderEvent::Fill { qty: 11 })]
+    #[case(OrderState::Cancelled, 0, OrderEvent::Fill { qty: 1 })]
+    #[case(OrderState::Filled, 10, OrderEvent::CancelRequest)]
+    #[case(OrderState::Rejected, 0, OrderEvent::Ack)]
+    #[case(OrderState::New, 0, OrderEvent::Ack)]
+    fn illegal(#[case] state: OrderState, #[case] filled_qty: u32, #[case] event: OrderEvent) {
+        let mut record = record(state, filled_qty);
+        assert!(record.apply(event).is_err());
+        assert_eq!(record.state, state);
+    }
+
+    #[test]
+    fn unknown_order() {
+        let mut tracker = OrderTracker::default();
+        assert!(tracker.apply(1, OrderEvent::Ack).is_err());
+        tracker.submit(record(OrderState::New, 0).order);
+        assert_eq!(tracker.state(1), Some(OrderState::PendingNew));
+        assert_eq!(tracker.open_orders().count(), 1);
+    }
+
+    #[test]
+    fn terminal_orders_leave_the_open_set() {
+        let mut tracker = OrderTracker::default();
+        for id in 1..=3 {
+            tracker.submit(Order {
+                id,
+                ..record(OrderState::New, 0).order
+            });
+            tracker.apply(id, OrderEvent::Ack).unwrap();
+        }
+        tracker.apply(1, OrderEvent::Fill { qty: 10 }).unwrap();
+        tracker.apply(2, OrderEvent::Cancelled).unwrap();
+        tracker.apply(3, OrderEvent::Fill { qty: 4 }).unwrap();
+        // A late answer to the filled order does not reopen it
+        tracker.apply(1, OrderEvent::Cancelled).unwrap();
+        let open = tracker.open_orders().map(|record| record.order.id);
+        assert_eq!(open.collect::<Vec<_>>(), vec![3]);
+        assert_eq!(tracker.len(), 3);
+    }
+}
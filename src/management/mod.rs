@@ -1,12 +1,25 @@
+mod avellaneda_stoikov;
 mod fix_price;
 mod fix_spread;
+mod hedger;
+mod lifecycle;
 mod oms;
+mod order_ids;
+mod quoting;
 mod signal_strat;
+mod throttle;
 
+#[allow(unused)]
+pub use avellaneda_stoikov::*;
 #[allow(unused)]
 pub use fix_price::*;
 #[allow(unused)]
 pub use fix_spread::*;
+pub use hedger::*;
+pub use lifecycle::*;
 pub use oms::*;
+pub use order_ids::*;
+pub use quoting::*;
 #[allow(unused)]
 pub use signal_strat::*;
+pub use throttle::*;
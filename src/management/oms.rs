@@ -2,11 +2,13 @@
 use crate::{
     backtest::Strategy,
     dbgp,
-    engine::TradingAccount,
-    engine::{ExecutionReport, OrderBook, OrderStatus},
-    engine::{Order, Side},
+    engine::{indicators::Midprice, Money, TradingAccount},
+    engine::{ExecutionReport, OrderBook, OrderStatus, RiskControl, Trade, TradeMismatch},
+    engine::{FillModel, LatencyModel, Order, QueueModel, Side},
     experiments::Schedule,
+    management::{OrderEvent, OrderIds, OrderMeta, OrderTag, OrderTracker, QuoteId, Throttle},
 };
+use log::warn;
 pub struct OrderManagementSystem<'a, S: Strategy> {
     pub strategy: &'a mut S,
     pub account: TradingAccount,
@@ -14,7 +16,19 @@ pub struct OrderManagementSystem<'a, S: Strategy> {
     pub active_sell_order: Option<Order>,
     pub strategy_buy_signal: Option<Order>,
     pub strategy_sell_signal: Option<Order>,
+    // Own resting orders beside the active pair, e.g. deeper rungs of a quote ladder
+    pub ladder: Vec<Order>,
     pub schedule: Schedule,
+    // Guess of the queue ahead of our orders between snapshots
+    pub queue_model: QueueModel,
+    pub fill_model: FillModel,
+    pub latency: LatencyModel,
+    pub ids: OrderIds,
+    // Lifecycle of every own order sent to the engine
+    pub orders: OrderTracker,
+    pub risk: RiskControl,
+    // Message rate limit of the venue, unlimited by default
+    pub throttle: Throttle,
 }
 
 impl<'a, S: Strategy> OrderManagementSystem<'a, S> {
@@ -26,9 +40,47 @@ impl<'a, S: Strategy> OrderManagementSystem<'a, S> {
             active_sell_order: None,
             strategy_buy_signal: None,
             strategy_sell_signal: None,
+            ladder: Vec::new(),
             schedule: Schedule::default(),
+            queue_model: QueueModel::default(),
+            fill_model: FillModel::default(),
+            latency: LatencyModel::default(),
+            ids: OrderIds::default(),
+            orders: OrderTracker::default(),
+            risk: RiskControl::default(),
+            throttle: Throttle::default(),
         }
     }
+
+    /// Allocates a client order id decided at `epoch`.
+    /// Quotes name the active order of their side as parent,
+    /// ladder rungs and flattening orders have none.
+    pub fn new_order_id(&mut self, epoch: u64, side: Side, tag: OrderTag) -> u64 {
+        let parent = match tag {
+            | OrderTag::Ladder | OrderTag::Flatten => None,
+            | _ => self.get_order_id(side),
+        };
+        self.ids.allocate(OrderMeta {
+            created_epoch: epoch,
+            side,
+            tag,
+            parent,
+        })
+    }
+
+    /// Id the order of `side` is sent under
+    pub fn resolve_id(&mut self, id: QuoteId, side: Side) -> u64 {
+        match id {
+            | QuoteId::Given(id) => id,
+            | QuoteId::Fresh { epoch, tag } => self.new_order_id(epoch, side, tag),
+        }
+    }
+
+    /// Open position in the ticker of the strategy, as booked by the ledger
+    pub fn position(&self) -> i32 {
+        self.account.ledger.qty(self.strategy.ticker().ticker_id)
+    }
+
     pub fn get_order_id(&self, side: Side) -> Option<u64> {
         match side {
             | Side::Bid => self.active_buy_order.map(|order| order.id),
@@ -36,6 +88,230 @@ impl<'a, S: Strategy> OrderManagementSystem<'a, S> {
         }
     }
 
+    /// Ids of all own orders resting in the book
+    pub fn own_order_ids(&self) -> Vec<u64> {
+        self.active_buy_order
+            .iter()
+            .chain(&self.active_sell_order)
+            .chain(&self.ladder)
+            .map(|order| order.id)
+            .collect()
+    }
+
+    fn track(&mut self, id: u64, event: OrderEvent) {
+        if let Err(err) = self.orders.apply(id, event) {
+            warn!(target: "oms", "{err}");
+        }
+    }
+
+    // Own order matched on arrival against `exec_report.filled_orders`
+    fn track_taker_fills(&mut self, exec_report: &ExecutionReport) {
+        let qty = exec_report
+            .filled_orders
+            .iter()
+            .map(|&(_, qty, _)| qty)
+            .sum::<u32>();
+        if qty > 0 {
+            self.track(exec_report.own_id, OrderEvent::Fill { qty });
+        }
+    }
+
+    /// Sends a new order to the engine. An order stopped by the pre-trade
+    /// checks never reaches the book and is reported as `Rejected`.
+    pub fn submit(&mut self, ob: &mut OrderBook, order: Order) -> ExecutionReport {
+        if let Err(rejection) = self.pre_trade_check(&order) {
+            dbgp!("[ RISK ] {} rejected: {}", order.id, rejection);
+            self.orders.submit(order);
+            self.track(order.id, OrderEvent::Reject);
+            self.risk.rejections.push((order.id, rejection));
+            return ExecutionReport {
+                own_id: order.id,
+                own_side: order.side,
+                filled_orders: Vec::new(),
+                remaining_qty: 0,
+                status: OrderStatus::Rejected,
+            };
+        }
+        self.orders.submit(order);
+        self.throttle.record(self.account.clock);
+        let exec_report = ob.add_limit_order(order);
+        self.track(order.id, OrderEvent::Ack);
+        self.track_taker_fills(&exec_report);
+        exec_report
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the order is not in the book
+    pub fn cancel(&mut self, ob: &mut OrderBook, id: u64) -> Result<ExecutionReport, String> {
+        self.track(id, OrderEvent::CancelRequest);
+        self.throttle.record(self.account.clock);
+        let result = ob.cancel_order(id);
+        let event = match result {
+            | Ok(_) => OrderEvent::Cancelled,
+            | Err(_) => OrderEvent::CancelReject,
+        };
+        self.track(id, event);
+        result
+    }
+
+    /// Cancels order `id` and sends `order` in its place
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if order `id` is not in the book,
+    /// `order` is not sent then
+    pub fn replace(
+        &mut self,
+        ob: &mut OrderBook,
+        id: u64,
+        order: Order,
+    ) -> Result<ExecutionReport, String> {
+        self.track(id, OrderEvent::ReplaceRequest);
+        match ob.cancel_order(id) {
+            | Ok(_) => {
+                self.track(id, OrderEvent::Cancelled);
+                Ok(self.submit(ob, order))
+            }
+            | Err(err) => {
+                self.track(id, OrderEvent::CancelReject);
+                Err(err)
+            }
+        }
+    }
+
+    /// Replays a print against the book under `self.fill_model`,
+    /// own resting orders hit by the print are filled
+    pub fn apply_trade(
+        &mut self,
+        ob: &mut OrderBook,
+        trade: &Trade,
+    ) -> (ExecutionReport, Option<TradeMismatch>) {
+        self.account.clock = trade.exch_epoch;
+        let (exec_report, mismatch) =
+            ob.apply_trade_with(trade, self.fill_model, &self.own_order_ids());
+        for &(id, qty, _) in &exec_report.filled_orders {
+            if self.orders.is_tracked(id) {
+                self.track(id, OrderEvent::Fill { qty });
+            }
+        }
+        (exec_report, mismatch)
+    }
+
+    /// Settles variation margin once a clearing of the ticker calendar
+    /// passed before `epoch`. Matching is halted over the clearing,
+    /// so the mid of `ob` stands for the clearing price.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn settle_clearings(&mut self, ob: &OrderBook, epoch: u64) -> Option<Money> {
+        let ticker = self.strategy.ticker();
+        let clearings = ticker.calendar.clearings(epoch)?;
+        let settled = self.account.clearings.replace(clearings)?;
+        if settled == clearings {
+            return None;
+        }
+        let price = Midprice::evaluate(ob)?.round() as u32;
+        self.account.clock = epoch;
+        let variation = self.account.settle(ticker.ticker_id, price);
+        dbgp!("[CLEAR ] settled at {} variation {}", price, variation);
+        Some(variation)
+    }
+
+    /// Fills of own orders that crossed the book when replaced into a snapshot
+    pub fn snap_fills(&mut self, exec_reports: &[ExecutionReport]) {
+        for exec_report in exec_reports {
+            if self.orders.is_tracked(exec_report.own_id) {
+                self.track_taker_fills(exec_report);
+            }
+        }
+    }
+
+    /// Forgets own orders that did not survive a snapshot,
+    /// the engine cancelled them
+    pub fn drop_missing(&mut self, ob: &OrderBook) {
+        for id in self.own_order_ids() {
+            if ob.get_order(id).is_none() && self.orders.is_tracked(id) {
+                self.track(id, OrderEvent::Cancelled);
+            }
+        }
+        if let Some(id) = self.get_order_id(Side::Bid) {
+            if ob.get_order(id).is_none() {
+                self.active_buy_order = None;
+            }
+        }
+        if let Some(id) = self.get_order_id(Side::Ask) {
+            if ob.get_order(id).is_none() {
+                self.active_sell_order = None;
+            }
+        }
+        self.ladder.retain(|order| ob.get_order(order.id).is_some());
+    }
+
+    /// Replaces the ladder with `orders`. Rungs already resting at the same
+    /// side and price keep their place in the queue, rungs that would
+    /// cross the book are skipped.
+    pub fn place_ladder(&mut self, ob: &mut OrderBook, orders: &[Order]) {
+        self.place_rungs(ob, orders, None);
+    }
+
+    /// Same as `place_ladder`, but the ids of `orders` are ignored:
+    /// only rungs actually sent get fresh ids decided at `epoch`
+    pub fn place_ladder_at(&mut self, ob: &mut OrderBook, orders: &[Order], epoch: u64) {
+        self.place_rungs(ob, orders, Some(epoch));
+    }
+
+    fn place_rungs(&mut self, ob: &mut OrderBook, orders: &[Order], epoch: Option<u64>) {
+        let mut resting = std::mem::take(&mut self.ladder);
+        let mut new_orders = Vec::new();
+        for order in orders {
+            if let Some(idx) = resting
+                .iter()
+                .position(|rung| (rung.side, rung.price) == (order.side, order.price))
+            {
+                self.ladder.push(resting.swap_remove(idx));
+            } else {
+                new_orders.push(*order);
+            }
+        }
+        for order in resting {
+            let _ = self.cancel(ob, order.id);
+        }
+        for mut order in new_orders {
+            let crossing = match order.side {
+                | Side::Bid => ob.best_offer_price.is_some_and(|ask| order.price >= ask),
+                | Side::Ask => ob.best_bid_price.is_some_and(|bid| order.price <= bid),
+            };
+            if let Some(epoch) = epoch.filter(|_| !crossing) {
+                order.id = self.new_order_id(epoch, order.side, OrderTag::Ladder);
+            }
+            if crossing {
+                // A rung without an id of its own never existed
+                if epoch.is_none() {
+                    self.orders.submit(order);
+                    self.track(order.id, OrderEvent::Reject);
+                }
+            } else if self.submit(ob, order).status != OrderStatus::Rejected {
+                self.ladder.push(order);
+            }
+        }
+    }
+
+    /// Applies maker fills of ladder rungs, returns (side, qty, price) of each
+    pub fn ladder_fills(&mut self, exec_report: &ExecutionReport) -> Vec<(Side, u32, u32)> {
+        let mut fills = Vec::new();
+        for &(id, qty, price) in &exec_report.filled_orders {
+            if let Some(idx) = self.ladder.iter().position(|order| order.id == id) {
+                let order = &mut self.ladder[idx];
+                fills.push((order.side, qty, price));
+                if qty >= order.qty {
+                    self.ladder.remove(idx);
+                } else {
+                    order.qty -= qty;
+                }
+            }
+        }
+        fills
+    }
+
     /// # Panics
     ///
     /// Will panic
@@ -43,19 +319,19 @@ impl<'a, S: Strategy> OrderManagementSystem<'a, S> {
         let exec_report;
         if let Some(order) = self.active_buy_order {
             dbgp!("{} {:?}", order.id, ob.get_order(order.id));
-            exec_report = ob
-                .amend_limit_order(order.id, self.strategy_buy_signal.unwrap())
+            exec_report = self
+                .replace(ob, order.id, self.strategy_buy_signal.unwrap())
                 .unwrap();
             dbgp!("Amend buy order {:?}", exec_report);
         } else {
-            exec_report = ob.add_limit_order(self.strategy_buy_signal.unwrap());
+            exec_report = self.submit(ob, self.strategy_buy_signal.unwrap());
             dbgp!("New buy order {:?}", exec_report);
         }
-        if exec_report.status == OrderStatus::Created {
-            self.active_buy_order = self.strategy_buy_signal;
-        } else {
+        match exec_report.status {
+            | OrderStatus::Created => self.active_buy_order = self.strategy_buy_signal,
+            | OrderStatus::Rejected => self.active_buy_order = None,
             // Only maker orders allowed
-            unreachable!();
+            | _ => unreachable!(),
         }
         exec_report
     }
@@ -66,19 +342,19 @@ impl<'a, S: Strategy> OrderManagementSystem<'a, S> {
     pub fn send_sell_maker(&mut self, ob: &mut OrderBook) -> ExecutionReport {
         let exec_report;
         if let Some(order) = self.active_sell_order {
-            exec_report = ob
-                .amend_limit_order(order.id, self.strategy_sell_signal.unwrap())
+            exec_report = self
+                .replace(ob, order.id, self.strategy_sell_signal.unwrap())
                 .unwrap();
             dbgp!("Amend sell order {:?}", exec_report);
         } else {
-            exec_report = ob.add_limit_order(self.strategy_sell_signal.unwrap());
+            exec_report = self.submit(ob, self.strategy_sell_signal.unwrap());
             dbgp!("New buy order {:?}", exec_report);
         }
-        if exec_report.status == OrderStatus::Created {
-            self.active_sell_order = self.strategy_sell_signal;
-        } else {
+        match exec_report.status {
+            | OrderStatus::Created => self.active_sell_order = self.strategy_sell_signal,
+            | OrderStatus::Rejected => self.active_sell_order = None,
             // Only maker orders allowed
-            unreachable!();
+            | _ => unreachable!(),
         }
         exec_report
     }
@@ -0,0 +1,93 @@
+//! Client order ids. Every own order gets a fresh id from the OMS,
+//! whatever we need to know about the order later is kept in `OrderMeta`
+//! instead of being encoded in the id.
+
+use crate::engine::Side;
+use std::collections::HashMap;
+
+/// Own ids start here, far above snapshot ids and exchange epochs
+/// and below the synthetic queue ids of `engine::QUEUE_ID_BASE`
+pub const CLIENT_ID_BASE: u64 = 1 << 62;
+
+/// Part of the strategy that sent the order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderTag {
+    Quote,
+    Ladder,
+    Open,
+    Close,
+    Flatten,
+    Experiment,
+    Hedge,
+}
+
+/// Id of an order not sent yet, a fresh one is allocated
+/// only once the order actually goes to the engine
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuoteId {
+    Given(u64),
+    // Decided at `epoch` by the `tag` part of the strategy
+    Fresh { epoch: u64, tag: OrderTag },
+}
+
+impl QuoteId {
+    pub const fn fresh(epoch: u64, tag: OrderTag) -> Self {
+        Self::Fresh { epoch, tag }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderMeta {
+    // Epoch of the decision that created the order
+    pub created_epoch: u64,
+    pub side: Side,
+    pub tag: OrderTag,
+    // Order this one replaces, e.g. the quote it amends
+    pub parent: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct OrderIds {
+    next: u64,
+    meta: HashMap<u64, OrderMeta>,
+}
+
+impl Default for OrderIds {
+    fn default() -> Self {
+        Self {
+            next: CLIENT_ID_BASE,
+            meta: HashMap::new(),
+        }
+    }
+}
+
+impl OrderIds {
+    pub fn allocate(&mut self, meta: OrderMeta) -> u64 {
+        let id = self.next;
+        self.next += 1;
+        self.meta.insert(id, meta);
+        id
+    }
+
+    pub fn get(&self, id: u64) -> Option<&OrderMeta> {
+        self.meta.get(&id)
+    }
+
+    /// Nanoseconds since the order was created, None for foreign ids
+    pub fn age(&self, id: u64, epoch: u64) -> Option<u64> {
+        self.get(id)
+            .map(|meta| epoch.saturating_sub(meta.created_epoch))
+    }
+
+    pub fn is_own(&self, id: u64) -> bool {
+        self.meta.contains_key(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.meta.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.meta.is_empty()
+    }
+}
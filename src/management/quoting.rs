@@ -0,0 +1,717 @@
+#![allow(clippy::cast_sign_loss)]
+#![allow(clippy::cast_possible_truncation)]
+
+//! Quoting shared by strategies that place one order per side around
+//! a reference price. A strategy only exposes its offsets, limits and
+//! maker/taker ranges, the OMS turns them into orders and routes them.
+
+use crate::{
+    backtest::{
+        AvellanedaStoikovStrategy, FixSpreadStrategy, HedgeStrategy, SignalStrategy, Strategy,
+    },
+    dbgp,
+    engine::indicators::BestBidOffer,
+    engine::{ExecutionReport, Liquidity, Order, OrderBook, OrderStatus, Side},
+    management::{OrderManagementSystem, OrderTag, QuoteId, ThrottleMode},
+};
+
+/// Position is that of the account ledger in the ticker of the strategy,
+/// it is passed to whatever depends on it
+pub trait QuotingPolicy: Strategy {
+    // Qty of a single quote
+    fn qty(&self) -> u32;
+    // Qty quoted on `side` holding `position`, before the position limits
+    fn quote_qty(&self, _side: Side, _position: i32) -> u32 {
+        self.qty()
+    }
+    // Buy and sell limits, the sell one is negative
+    fn position_limits(&self) -> (i32, i32);
+    // Relative offsets of the bid and ask from the reference price
+    fn offsets(&self, position: i32) -> (f32, f32);
+    fn maker_range(&self) -> (f32, f32);
+    fn taker_range(&self) -> (f32, f32);
+}
+
+impl QuotingPolicy for FixSpreadStrategy {
+    fn qty(&self) -> u32 {
+        self.qty
+    }
+    fn quote_qty(&self, side: Side, position: i32) -> u32 {
+        Self::quote_qty(self, side, position)
+    }
+    fn position_limits(&self) -> (i32, i32) {
+        (self.buy_position_limit, self.sell_position_limit)
+    }
+    fn offsets(&self, position: i32) -> (f32, f32) {
+        self.skewed_criterions(position)
+    }
+    fn maker_range(&self) -> (f32, f32) {
+        self.maker_range
+    }
+    fn taker_range(&self) -> (f32, f32) {
+        self.taker_range
+    }
+}
+
+// Offsets are those of the last requote, around the mid it calibrated on
+impl QuotingPolicy for AvellanedaStoikovStrategy {
+    fn qty(&self) -> u32 {
+        self.qty
+    }
+    fn position_limits(&self) -> (i32, i32) {
+        (self.buy_position_limit, self.sell_position_limit)
+    }
+    fn offsets(&self, _position: i32) -> (f32, f32) {
+        self.offsets
+    }
+    fn maker_range(&self) -> (f32, f32) {
+        self.maker_range
+    }
+    fn taker_range(&self) -> (f32, f32) {
+        self.taker_range
+    }
+}
+
+// Opening criteria are the default quotes, closing ones are passed explicitly
+impl QuotingPolicy for SignalStrategy {
+    fn qty(&self) -> u32 {
+        self.qty
+    }
+    fn position_limits(&self) -> (i32, i32) {
+        (self.buy_position_limit, self.sell_position_limit)
+    }
+    fn offsets(&self, _position: i32) -> (f32, f32) {
+        (self.buy_open_criterion, self.sell_open_criterion)
+    }
+    fn maker_range(&self) -> (f32, f32) {
+        self.maker_range
+    }
+    fn taker_range(&self) -> (f32, f32) {
+        self.taker_range
+    }
+}
+
+// Both limits sit at the target, only the side closing the gap has qty
+impl QuotingPolicy for HedgeStrategy {
+    fn qty(&self) -> u32 {
+        self.qty
+    }
+    fn position_limits(&self) -> (i32, i32) {
+        (self.target, self.target)
+    }
+    fn offsets(&self, _position: i32) -> (f32, f32) {
+        (0.0, 0.0)
+    }
+    fn maker_range(&self) -> (f32, f32) {
+        self.maker_range
+    }
+    fn taker_range(&self) -> (f32, f32) {
+        self.taker_range
+    }
+}
+
+impl<S: QuotingPolicy> OrderManagementSystem<'_, S> {
+    /// Quote at `offset` from `ref_price`, bids are rounded down
+    /// and asks up to the tick size
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if either `Indicator` fails to provide reference price
+    /// or `Strategy` has no limit left for this side
+    pub fn calculate_quote(
+        &self,
+        side: Side,
+        ref_price: Option<f32>,
+        offset: f32,
+        id: Option<u64>,
+    ) -> Result<Order, String> {
+        let id = id.ok_or_else(|| "No order id".to_owned())?;
+        let tick_size = self.strategy.ticker().tick_size;
+        let ticks =
+            ref_price.ok_or_else(|| "Missing Ref Price".to_owned())? * (1.0 + offset) / tick_size;
+        let (buy_limit, sell_limit) = self.strategy.position_limits();
+        let position = self.position();
+        let (ticks, free_qty) = match side {
+            | Side::Bid => (ticks.floor(), buy_limit - position),
+            | Side::Ask => (ticks.ceil(), position - sell_limit),
+        };
+        let quote_qty = self.strategy.quote_qty(side, position);
+        let qty = quote_qty.min(free_qty.max(0) as u32);
+        dbgp!(
+            "free_qty = {}, strategy_qty = {}, qty = {}",
+            free_qty,
+            quote_qty,
+            qty
+        );
+        if qty > 0 {
+            Ok(Order {
+                id,
+                side,
+                price: (ticks * tick_size) as u32,
+                qty,
+            })
+        } else {
+            Err("No Limit left".to_owned())
+        }
+    }
+
+    /// Quotes both sides at the strategy offsets
+    pub fn send_quotes(
+        &mut self,
+        ob: &mut OrderBook,
+        m: Option<f32>,
+        trader_buy_id: Option<QuoteId>,
+        trader_sell_id: Option<QuoteId>,
+    ) -> (Option<ExecutionReport>, Option<ExecutionReport>) {
+        let offsets = self.strategy.offsets(self.position());
+        self.send_quotes_at(ob, m, offsets, trader_buy_id, trader_sell_id)
+    }
+
+    /// Keeps resting quotes that did not move, amends the rest.
+    /// A side without an id or without limit left is cancelled.
+    /// Fresh ids are allocated only for quotes that are sent or held back.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if a quote is outside both maker and taker ranges
+    pub fn send_quotes_at(
+        &mut self,
+        ob: &mut OrderBook,
+        m: Option<f32>,
+        (buy_offset, sell_offset): (f32, f32),
+        trader_buy_id: Option<QuoteId>,
+        trader_sell_id: Option<QuoteId>,
+    ) -> (Option<ExecutionReport>, Option<ExecutionReport>) {
+        // Prices and qty first, the id is known once the quote has to go
+        let buy_order = trader_buy_id
+            .and_then(|_| self.calculate_quote(Side::Bid, m, buy_offset, Some(0)).ok());
+        let send_buy_order = self.stage_quote(ob, Side::Bid, buy_order);
+        let sell_order = trader_sell_id.and_then(|_| {
+            self.calculate_quote(Side::Ask, m, sell_offset, Some(0))
+                .ok()
+        });
+        let send_sell_order = self.stage_quote(ob, Side::Ask, sell_order);
+        let send_buy_order = send_buy_order && self.admit_amend(Side::Bid, trader_buy_id);
+        let send_sell_order = send_sell_order && self.admit_amend(Side::Ask, trader_sell_id);
+        // A throttled side keeps its old quote, the other one must not trade against it
+        let send_buy_order = send_buy_order
+            && (send_sell_order || !self.reaches_own_quote(self.strategy_buy_signal.unwrap()));
+        let send_sell_order = send_sell_order
+            && (send_buy_order || !self.reaches_own_quote(self.strategy_sell_signal.unwrap()));
+        if send_buy_order {
+            self.name_signal(Side::Bid, trader_buy_id);
+        }
+        if send_sell_order {
+            self.name_signal(Side::Ask, trader_sell_id);
+        }
+
+        let mut buy_exec_report = None;
+        let mut sell_exec_report = None;
+        match (send_buy_order, send_sell_order) {
+            | (true, true) => {
+                // Free the price first if the new bid reaches the resting ask
+                if self.active_sell_order.is_some_and(|active_sell| {
+                    self.strategy_buy_signal.unwrap().price >= active_sell.price
+                }) {
+                    sell_exec_report = Some(self.send_sell(ob));
+                    buy_exec_report = Some(self.send_buy(ob));
+                } else {
+                    buy_exec_report = Some(self.send_buy(ob));
+                    sell_exec_report = Some(self.send_sell(ob));
+                }
+            }
+            | (true, false) => buy_exec_report = Some(self.send_buy(ob)),
+            | (false, true) => sell_exec_report = Some(self.send_sell(ob)),
+            | (false, false) => {}
+        }
+        (buy_exec_report, sell_exec_report)
+    }
+
+    // Gives the signal of `side` the id it is sent under
+    fn name_signal(&mut self, side: Side, id: Option<QuoteId>) {
+        let Some(id) = id else {
+            return;
+        };
+        let id = self.resolve_id(id, side);
+        let signal = match side {
+            | Side::Bid => self.strategy_buy_signal.as_mut(),
+            | Side::Ask => self.strategy_sell_signal.as_mut(),
+        };
+        if let Some(signal) = signal {
+            signal.id = id;
+        }
+    }
+
+    // Stores the quote as the signal of its side,
+    // returns whether it has to be sent
+    fn stage_quote(&mut self, ob: &mut OrderBook, side: Side, quote: Option<Order>) -> bool {
+        // A fresh decision supersedes the amendment held back for this side
+        let _ = self.throttle.take_held(side);
+        let active = match side {
+            | Side::Bid => self.active_buy_order,
+            | Side::Ask => self.active_sell_order,
+        };
+        let Some(quote) = quote else {
+            if let Some(order) = active {
+                let _ = self.cancel(ob, order.id);
+                match side {
+                    | Side::Bid => self.active_buy_order = None,
+                    | Side::Ask => self.active_sell_order = None,
+                }
+            }
+            return false;
+        };
+        match side {
+            | Side::Bid => self.strategy_buy_signal = Some(quote),
+            | Side::Ask => self.strategy_sell_signal = Some(quote),
+        }
+        match active {
+            | None => {
+                dbgp!("[ STRAT] Order not found, place new order");
+                dbgp!("[ STRAT] send {:#?}", quote);
+                true
+            }
+            | Some(order) if order.price == quote.price => {
+                // The resting order stands for the quote
+                let kept = Some(Order {
+                    id: order.id,
+                    ..quote
+                });
+                match side {
+                    | Side::Bid => self.strategy_buy_signal = kept,
+                    | Side::Ask => self.strategy_sell_signal = kept,
+                }
+                dbgp!("[ STRAT] Order found, passing");
+                dbgp!("[ STRAT] price = {}", order.price);
+                false
+            }
+            | Some(order) => {
+                dbgp!("[ STRAT] Order found, need amend");
+                dbgp!(
+                    "[ STRAT] Old price {}, New Price {}",
+                    order.price,
+                    quote.price
+                );
+                dbgp!("[ STRAT] Old qty {}, New qty {}", order.qty, quote.qty);
+                dbgp!("[ STRAT] send {:#?}", quote);
+                true
+            }
+        }
+    }
+
+    // New orders always go, an amendment takes a token of the throttle.
+    // An amendment held back for later needs its id now.
+    fn admit_amend(&mut self, side: Side, id: Option<QuoteId>) -> bool {
+        let active = match side {
+            | Side::Bid => self.active_buy_order,
+            | Side::Ask => self.active_sell_order,
+        };
+        if active.is_none() || self.throttle.try_acquire(self.account.clock) {
+            return true;
+        }
+        dbgp!("[THROTL] {:?} amendment over the limit", side);
+        if self.throttle.mode == ThrottleMode::Queue {
+            self.name_signal(side, id);
+        }
+        let signal = match side {
+            | Side::Bid => self.strategy_buy_signal,
+            | Side::Ask => self.strategy_sell_signal,
+        };
+        self.throttle.reject_amend(signal.unwrap());
+        false
+    }
+
+    fn send_buy(&mut self, ob: &mut OrderBook) -> ExecutionReport {
+        let strat_price = self.strategy_buy_signal.unwrap().price as f32;
+        let (bid, ask) = BestBidOffer::evaluate(ob).expect("Empty orderbook");
+        let maker_range = self.strategy.maker_range();
+        let taker_range = self.strategy.taker_range();
+        if strat_price >= bid as f32 * (1.0 + maker_range.0)
+            && strat_price <= bid as f32 * (1.0 + maker_range.1)
+            && strat_price < ask as f32
+        {
+            self.send_buy_maker(ob)
+        } else if strat_price >= ask as f32 && strat_price <= bid as f32 * (1.0 + taker_range.1) {
+            self.send_buy_taker(ob)
+        } else {
+            unreachable!()
+        }
+    }
+
+    fn send_sell(&mut self, ob: &mut OrderBook) -> ExecutionReport {
+        let strat_price = self.strategy_sell_signal.unwrap().price as f32;
+        let (bid, ask) = BestBidOffer::evaluate(ob).expect("Empty orderbook");
+        let maker_range = self.strategy.maker_range();
+        let taker_range = self.strategy.taker_range();
+        if strat_price <= ask as f32 * (1.0 - maker_range.0)
+            && strat_price >= ask as f32 * (1.0 + maker_range.0)
+            && strat_price > bid as f32
+        {
+            self.send_sell_maker(ob)
+        } else if strat_price <= bid as f32 && strat_price >= ask as f32 * (1.0 - taker_range.1) {
+            self.send_sell_taker(ob)
+        } else {
+            unreachable!()
+        }
+    }
+
+    pub fn get_pnl(&self, ref_price: Option<f32>, in_bps: bool) -> Option<f32> {
+        let pnl = self.account.pnl(self.position(), ref_price?);
+        if in_bps {
+            Some(pnl.bps(self.account.cumulative_volume))
+        } else {
+            Some(pnl.cash(self.strategy.ticker().unit_cash()))
+        }
+    }
+
+    /// Cancels quotes and closes the position with a marketable order.
+    /// Whatever the book could not absorb is cancelled.
+    pub fn flatten(&mut self, ob: &mut OrderBook, epoch: u64) -> Option<ExecutionReport> {
+        self.cancel_all_orders(ob);
+        let position = self.position();
+        if position == 0 {
+            return None;
+        }
+        let (side, price) = if position > 0 {
+            (Side::Ask, 0)
+        } else {
+            (Side::Bid, u32::MAX)
+        };
+        let qty = position.unsigned_abs();
+        dbgp!("[ FLAT ] {:?} {}", side, qty);
+        let id = self.new_order_id(epoch, side, OrderTag::Flatten);
+        let exec_report = self.submit(
+            ob,
+            Order {
+                id,
+                side,
+                price,
+                qty,
+            },
+        );
+        if exec_report.remaining_qty > 0 {
+            let _ = self.cancel(ob, id);
+        }
+        if !exec_report.filled_orders.is_empty() {
+            self.update_taker(&exec_report);
+        }
+        Some(exec_report)
+    }
+}
+
+// Held amendments go out for any strategy, whatever placed them
+impl<S: Strategy> OrderManagementSystem<'_, S> {
+    /// Books maker fills of own resting orders. Active quotes and ladder
+    /// rungs shrink, any other own order is booked on the side it was sent.
+    pub fn update(&mut self, exec_report: &ExecutionReport) {
+        let quoted: Vec<u64> = [self.active_buy_order, self.active_sell_order]
+            .into_iter()
+            .flatten()
+            .chain(self.ladder.iter().copied())
+            .map(|order| order.id)
+            .collect();
+        let mut fills = Vec::new();
+        if let Some(order) = self.active_buy_order {
+            if exec_report.own_side == Side::Ask {
+                if let Some(&(_, qty, price)) =
+                    exec_report.filled_orders.iter().find(|o| o.0 == order.id)
+                {
+                    self.active_buy_order = (qty != order.qty).then_some(Order {
+                        price,
+                        qty: order.qty - qty,
+                        ..order
+                    });
+                    fills.push((Side::Bid, qty, price));
+                }
+            }
+        }
+        if let Some(order) = self.active_sell_order {
+            if let Some(&(_, qty, price)) =
+                exec_report.filled_orders.iter().find(|o| o.0 == order.id)
+            {
+                self.active_sell_order = (qty != order.qty).then_some(Order {
+                    price,
+                    qty: order.qty - qty,
+                    ..order
+                });
+                fills.push((Side::Ask, qty, price));
+            }
+        }
+        fills.extend(self.ladder_fills(exec_report));
+        // Orders placed past the quoting, e.g. by a custom strategy
+        for &(id, qty, price) in &exec_report.filled_orders {
+            if let Some(meta) = self.ids.get(id).filter(|_| !quoted.contains(&id)) {
+                fills.push((meta.side, qty, price));
+            }
+        }
+        let traded = !fills.is_empty();
+        for (side, qty, price) in fills {
+            dbgp!("[TRADE ] {:?} qty = {:?}, price = {:?}", side, qty, price);
+            self.account
+                .record_fill(self.strategy.ticker(), side, qty, price, Liquidity::Maker);
+        }
+        if traded {
+            self.account.trade_count += 1;
+        }
+        dbgp!("POS {:#?}", self.position());
+        dbgp!("ACC {:#?}", self.account.balance);
+        dbgp!("#TRADES {:#?}", self.account.trade_count);
+    }
+
+    // Whether `order` would trade against the own quote of the other side
+    fn reaches_own_quote(&self, order: Order) -> bool {
+        match order.side {
+            | Side::Bid => self
+                .active_sell_order
+                .is_some_and(|ask| order.price >= ask.price),
+            | Side::Ask => self
+                .active_buy_order
+                .is_some_and(|bid| order.price <= bid.price),
+        }
+    }
+
+    /// Sends amendments held back by the throttle once the budget allows.
+    /// An amendment of an order that is gone, or one reaching the own
+    /// quote of the other side, is dropped.
+    pub fn release_held(&mut self, ob: &mut OrderBook) {
+        for side in [Side::Bid, Side::Ask] {
+            let Some(order) = self.throttle.held(side) else {
+                continue;
+            };
+            if self.get_order_id(side).is_none() || self.reaches_own_quote(order) {
+                let _ = self.throttle.take_held(side);
+                continue;
+            }
+            if !self.throttle.try_acquire(self.account.clock) {
+                return;
+            }
+            let _ = self.throttle.take_held(side);
+            dbgp!("[THROTL] release {:?}", order);
+            let crossing = match side {
+                | Side::Bid => ob.best_offer_price.is_some_and(|ask| order.price >= ask),
+                | Side::Ask => ob.best_bid_price.is_some_and(|bid| order.price <= bid),
+            };
+            match (side, crossing) {
+                | (Side::Bid, false) => {
+                    self.strategy_buy_signal = Some(order);
+                    let _ = self.send_buy_maker(ob);
+                }
+                | (Side::Bid, true) => {
+                    self.strategy_buy_signal = Some(order);
+                    let _ = self.send_buy_taker(ob);
+                }
+                | (Side::Ask, false) => {
+                    self.strategy_sell_signal = Some(order);
+                    let _ = self.send_sell_maker(ob);
+                }
+                | (Side::Ask, true) => {
+                    self.strategy_sell_signal = Some(order);
+                    let _ = self.send_sell_taker(ob);
+                }
+            }
+        }
+    }
+
+    fn send_buy_taker(&mut self, ob: &mut OrderBook) -> ExecutionReport {
+        let taker_order = self.strategy_buy_signal.unwrap();
+        let exec_report = if let Some(order) = self.active_buy_order {
+            self.replace(ob, order.id, taker_order).unwrap()
+        } else {
+            self.submit(ob, taker_order)
+        };
+        match exec_report.status {
+            | OrderStatus::Filled => {
+                self.update_taker(&exec_report);
+                self.active_buy_order = None;
+            }
+            | OrderStatus::PartiallyFilled => {
+                self.update_taker(&exec_report);
+                // What the book could not fill rests under the new id
+                self.active_buy_order = Some(Order {
+                    qty: exec_report.remaining_qty,
+                    ..taker_order
+                });
+            }
+            | OrderStatus::Rejected => self.active_buy_order = None,
+            // Only taker orders allowed
+            | _ => unreachable!(),
+        }
+        exec_report
+    }
+
+    fn send_sell_taker(&mut self, ob: &mut OrderBook) -> ExecutionReport {
+        let taker_order = self.strategy_sell_signal.unwrap();
+        let exec_report = if let Some(order) = self.active_sell_order {
+            self.replace(ob, order.id, taker_order).unwrap()
+        } else {
+            self.submit(ob, taker_order)
+        };
+        match exec_report.status {
+            | OrderStatus::Filled => {
+                self.update_taker(&exec_report);
+                self.active_sell_order = None;
+            }
+            | OrderStatus::PartiallyFilled => {
+                self.update_taker(&exec_report);
+                // What the book could not fill rests under the new id
+                self.active_sell_order = Some(Order {
+                    qty: exec_report.remaining_qty,
+                    ..taker_order
+                });
+            }
+            | OrderStatus::Rejected => self.active_sell_order = None,
+            // Only taker orders allowed
+            | _ => unreachable!(),
+        }
+        exec_report
+    }
+
+    /// Applies fills of an own aggressive order
+    pub fn update_taker(&mut self, exec_report: &ExecutionReport) {
+        let side = exec_report.own_side;
+        let mut traded_qty = 0;
+        for &(_, qty, price) in &exec_report.filled_orders {
+            self.account
+                .record_fill(self.strategy.ticker(), side, qty, price, Liquidity::Taker);
+            traded_qty += qty;
+        }
+        self.account.trade_count += 1;
+        dbgp!("[TRADE ] qty = {:?}", traded_qty);
+        dbgp!("POS {:#?}", self.position());
+        dbgp!("ACC {:#?}", self.account.balance);
+        dbgp!("#TRADES {:#?}", self.account.trade_count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::engine::Money;
+    use crate::engine::OrderStatus;
+    use crate::engine::Ticker;
+    use crate::engine::TradingAccount;
+    use crate::utils;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+    use utils::tests::full_ob;
+
+    #[rstest]
+    #[case(full_ob(), 99, 10, ExecutionReport {own_id: 3,
+                                                  own_side: Side::Bid,
+                                                  remaining_qty: 10,
+                                                  filled_orders: Vec::new(),
+                                                  status: OrderStatus::Created })]
+    #[case(full_ob(), 101, 15, ExecutionReport {own_id: 3,
+                                                  own_side: Side::Bid,
+                                                  remaining_qty: 5,
+                                                  filled_orders: vec![(999, 10, 101)],
+                                                  status: OrderStatus::PartiallyFilled})]
+    #[case(full_ob(), 103, 30, ExecutionReport {own_id: 3,
+                                                  own_side: Side::Bid,
+                                                  remaining_qty: 0,
+                                                  filled_orders: vec![(999, 10, 101), (1000, 10, 102), (1001, 10, 103)],
+                                                  status: OrderStatus::Filled})]
+    fn send_buy_test(
+        #[case] mut ob: OrderBook,
+        #[case] price: u32,
+        #[case] qty: u32,
+        #[case] exp_report: ExecutionReport,
+    ) {
+        let mut strat = FixSpreadStrategy::new(Ticker::default());
+        let account = TradingAccount::new(Money::ZERO);
+        let mut oms = OrderManagementSystem::new(&mut strat, account);
+        oms.strategy_buy_signal = Some(Order {
+            id: 3,
+            side: Side::Bid,
+            price,
+            qty,
+        });
+        let exec_report = oms.send_buy(&mut ob);
+        assert_eq!(exec_report, exp_report);
+    }
+
+    #[rstest]
+    #[case(full_ob(), 101, 10, ExecutionReport {own_id: 7,
+                                                  own_side: Side::Ask,
+                                                  remaining_qty: 10,
+                                                  filled_orders: Vec::new(),
+                                                  status: OrderStatus::Created })]
+    #[case(full_ob(), 99, 15, ExecutionReport {own_id: 7,
+                                                  own_side: Side::Ask,
+                                                  remaining_qty: 5,
+                                                  filled_orders: vec![(666, 10, 99)],
+                                                  status: OrderStatus::PartiallyFilled})]
+    #[case(full_ob(), 97, 30, ExecutionReport {own_id: 7,
+                                                  own_side: Side::Ask,
+                                                  remaining_qty: 0,
+                                                  filled_orders: vec![(666, 10, 99), (555, 10, 98), (444, 10, 97)],
+                                                  status: OrderStatus::Filled})]
+    fn send_sell_test(
+        #[case] mut ob: OrderBook,
+        #[case] price: u32,
+        #[case] qty: u32,
+        #[case] exp_report: ExecutionReport,
+    ) {
+        let mut strat = FixSpreadStrategy::new(Ticker::default());
+        let account = TradingAccount::new(Money::ZERO);
+        let mut oms = OrderManagementSystem::new(&mut strat, account);
+        oms.strategy_sell_signal = Some(Order {
+            id: 7,
+            side: Side::Ask,
+            price,
+            qty,
+        });
+        let exec_report = oms.send_sell(&mut ob);
+        assert_eq!(exec_report, exp_report);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::engine::Money;
+    use crate::engine::OrderStatus;
+    use crate::engine::Ticker;
+    use crate::engine::TradingAccount;
+
+    #[test]
+    fn send_buy_test() {
+        let mut strat = FixSpreadStrategy::new(Ticker::default());
+        strat.buy_criterion = -0.0001;
+        strat.sell_criterion = 0.0001;
+        strat.buy_position_limit = 10;
+        strat.sell_position_limit = -10;
+        strat.qty = 1;
+        let account = TradingAccount::new(Money::ZERO);
+        let mut ob = OrderBook::new();
+        let _ = ob.add_limit_order(Order {
+            id: 1,
+            side: Side::Bid,
+            price: 99,
+            qty: 1,
+        });
+        let _ = ob.add_limit_order(Order {
+            id: 2,
+            side: Side::Ask,
+            price: 100,
+            qty: 1,
+        });
+        let mut oms = OrderManagementSystem::new(&mut strat, account);
+        oms.strategy_buy_signal = Some(Order {
+            id: 333,
+            price: 100,
+            qty: 2,
+            side: Side::Bid,
+        });
+        let exec_report = oms.send_buy(&mut ob);
+        let exp_report = ExecutionReport {
+            own_id: 333,
+            own_side: Side::Bid,
+            filled_orders: [(2, 1, 100)].to_vec(),
+            remaining_qty: 1,
+            status: OrderStatus::PartiallyFilled,
+        };
+        assert_eq!(exp_report, exec_report);
+    }
+}
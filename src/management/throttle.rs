@@ -0,0 +1,193 @@
+//! Message rate limit of the venue. Every new order, amendment and cancel
+//! is a message; amendments over the limit are dropped or held back.
+
+use crate::engine::{Order, Side};
+
+const NANOS_IN_SEC: u64 = 1_000_000_000;
+
+/// At most `messages` per `window` epochs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub messages: u32,
+    pub window: u64,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ThrottleMode {
+    // Amendment over the limit is lost, the resting order stays
+    #[default]
+    Drop,
+    // The latest amendment of each side waits for the budget
+    Queue,
+}
+
+/// Token bucket refilled continuously, `messages` tokens per `window`
+#[derive(Debug, Default, Clone)]
+pub struct Throttle {
+    pub limit: Option<RateLimit>,
+    pub mode: ThrottleMode,
+    pub messages: u64,
+    pub throttled: u64,
+    // A token is `window` units, refill is `messages` units per epoch
+    level: u64,
+    // Tokens taken by amendments not yet recorded
+    prepaid: u32,
+    last_refill: Option<u64>,
+    first_message: Option<u64>,
+    last_message: u64,
+    held_bid: Option<Order>,
+    held_ask: Option<Order>,
+}
+
+impl Throttle {
+    pub const fn new(limit: RateLimit, mode: ThrottleMode) -> Self {
+        Self {
+            limit: Some(limit),
+            mode,
+            messages: 0,
+            throttled: 0,
+            level: limit.messages as u64 * limit.window,
+            prepaid: 0,
+            last_refill: None,
+            first_message: None,
+            last_message: 0,
+            held_bid: None,
+            held_ask: None,
+        }
+    }
+
+    fn refill(&mut self, epoch: u64) {
+        let Some(limit) = self.limit else {
+            return;
+        };
+        let capacity = u64::from(limit.messages) * limit.window;
+        let elapsed = self
+            .last_refill
+            .map_or(0, |last| epoch.saturating_sub(last));
+        self.level = elapsed
+            .saturating_mul(u64::from(limit.messages))
+            .saturating_add(self.level)
+            .min(capacity);
+        self.last_refill = Some(self.last_refill.map_or(epoch, |last| last.max(epoch)));
+    }
+
+    /// Takes a token for an amendment about to be sent at `epoch`,
+    /// `false` if the budget is used up
+    pub fn try_acquire(&mut self, epoch: u64) -> bool {
+        self.refill(epoch);
+        let Some(limit) = self.limit else {
+            return true;
+        };
+        if self.level < limit.window {
+            return false;
+        }
+        self.level -= limit.window;
+        self.prepaid += 1;
+        true
+    }
+
+    /// Books a message sent at `epoch`. New orders and cancels are
+    /// never held back, they only drain the budget.
+    pub fn record(&mut self, epoch: u64) {
+        self.refill(epoch);
+        if self.prepaid > 0 {
+            self.prepaid -= 1;
+        } else if let Some(limit) = self.limit {
+            self.level = self.level.saturating_sub(limit.window);
+        }
+        self.messages += 1;
+        self.first_message.get_or_insert(epoch);
+        self.last_message = self.last_message.max(epoch);
+    }
+
+    /// Amendment that did not fit the budget, held in `Queue` mode
+    pub fn reject_amend(&mut self, order: Order) {
+        self.throttled += 1;
+        if self.mode == ThrottleMode::Queue {
+            match order.side {
+                | Side::Bid => self.held_bid = Some(order),
+                | Side::Ask => self.held_ask = Some(order),
+            }
+        }
+    }
+
+    pub const fn held(&self, side: Side) -> Option<Order> {
+        match side {
+            | Side::Bid => self.held_bid,
+            | Side::Ask => self.held_ask,
+        }
+    }
+
+    /// Takes the held amendment of `side`
+    pub const fn take_held(&mut self, side: Side) -> Option<Order> {
+        match side {
+            | Side::Bid => self.held_bid.take(),
+            | Side::Ask => self.held_ask.take(),
+        }
+    }
+
+    /// Average rate between the first and the last message
+    pub fn messages_per_sec(&self) -> f32 {
+        let Some(first) = self.first_message else {
+            return 0.0;
+        };
+        let span = (self.last_message - first).max(NANOS_IN_SEC);
+        self.messages as f32 / (span as f32 / NANOS_IN_SEC as f32)
+    }
+
+    /// Messages per trade, infinite if nothing traded
+    pub fn order_to_trade(&self, trade_count: u32) -> f32 {
+        match (self.messages, trade_count) {
+            | (0, _) => 0.0,
+            | (_, 0) => f32::INFINITY,
+            | (messages, trades) => messages as f32 / trades as f32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn bucket_refills_over_window() {
+        // 2 messages per 100 epochs
+        let mut throttle = Throttle::new(
+            RateLimit {
+                messages: 2,
+                window: 100,
+            },
+            ThrottleMode::Drop,
+        );
+        assert!(throttle.try_acquire(0));
+        throttle.record(0);
+        // A new order drains the budget without asking
+        throttle.record(0);
+        assert!(!throttle.try_acquire(10));
+        // Half a window gives one token back
+        assert!(throttle.try_acquire(50));
+        throttle.record(50);
+        assert!(!throttle.try_acquire(50));
+        // Budget is capped at the window
+        assert!(throttle.try_acquire(10_000));
+        assert!(throttle.try_acquire(10_000));
+        assert!(!throttle.try_acquire(10_000));
+        throttle.record(10_000);
+        throttle.record(10_000);
+        assert_eq!(throttle.messages, 5);
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn unlimited() {
+        let mut throttle = Throttle::default();
+        for epoch in 0..10 {
+            assert!(throttle.try_acquire(epoch));
+            throttle.record(epoch);
+        }
+        assert_eq!(throttle.order_to_trade(2), 5.0);
+        // Ten messages within the first second
+        assert_eq!(throttle.messages_per_sec(), 10.0);
+    }
+}
@@ -1,12 +1,13 @@
-use crate::engine::Ticker;
+use crate::engine::{FeeSchedule, Money, Ticker, MOEX_EQUITIES, MOEX_FORTS};
 
 #[allow(unused)]
 pub const IMOEXF: Ticker = Ticker {
     ticker_id: 1,
     tick_size: 1.0,
     step_price: 1.0,
-    taker_fee: 0.000_066,
-    maker_fee: 0.0,
+    fees: FeeSchedule::proportional(0.0, 0.000_066),
+    calendar: MOEX_FORTS,
+    initial_margin: Money::from_units(4_000),
 };
 
 #[allow(unused)]
@@ -14,8 +15,9 @@ pub const MM: Ticker = Ticker {
     ticker_id: 2,
     tick_size: 5.0,
     step_price: 0.5,
-    taker_fee: 0.000_066,
-    maker_fee: 0.0,
+    fees: FeeSchedule::proportional(0.0, 0.000_066),
+    calendar: MOEX_FORTS,
+    initial_margin: Money::from_units(50_000),
 };
 
 #[allow(unused)]
@@ -23,6 +25,7 @@ pub const ALRS: Ticker = Ticker {
     ticker_id: 3,
     tick_size: 1.0,
     step_price: 0.1,
-    taker_fee: 0.0,
-    maker_fee: 0.0,
+    fees: FeeSchedule::NONE,
+    calendar: MOEX_EQUITIES,
+    initial_margin: Money::ZERO,
 };
@@ -0,0 +1,115 @@
+use orderbook::{
+    backtest::{AvellanedaStoikovStrategy, Engine, FixSpreadStrategy, StrategyMetrics},
+    data::{generate_csv, GeneratorConfig},
+    engine::{Money, OrderBook, Side, Ticker, Trade, TradingAccount},
+    management::OrderManagementSystem,
+};
+use pretty_assertions::assert_eq;
+
+const SECOND: u64 = 1_000_000_000;
+
+fn generated(name: &str) -> (String, String) {
+    let dir = std::env::temp_dir();
+    let ob_path = dir.join(format!("orderbook_{}_{name}_ob.csv", std::process::id()));
+    let orders_path = dir.join(format!(
+        "orderbook_{}_{name}_orders.csv",
+        std::process::id()
+    ));
+    let config = GeneratorConfig {
+        duration: 60_000_000_000,
+        ..GeneratorConfig::default()
+    };
+    generate_csv(config, &ob_path, &orders_path).unwrap();
+    (
+        ob_path.to_str().unwrap().to_owned(),
+        orders_path.to_str().unwrap().to_owned(),
+    )
+}
+
+fn avellaneda_stoikov() -> AvellanedaStoikovStrategy {
+    AvellanedaStoikovStrategy::builder(Ticker::default())
+        .qty(10)
+        .position_limits(100, -100)
+        .build()
+        .unwrap()
+}
+
+fn fix_spread() -> FixSpreadStrategy {
+    FixSpreadStrategy::builder(Ticker::default())
+        .qty(10)
+        .criterions(-0.0001, 0.0001)
+        .position_limits(100, -100)
+        .build()
+        .unwrap()
+}
+
+// Mid swings by a tick every second, prints land two ticks away from it
+fn calibrated() -> AvellanedaStoikovStrategy {
+    let mut strat = avellaneda_stoikov();
+    for step in 0..=strat.warmup {
+        let epoch = u64::from(step) * SECOND;
+        strat.observe_mid(epoch, 100.0 + f32::from(u8::from(step % 2 == 1)));
+        strat.observe_trade(&Trade {
+            exch_epoch: epoch,
+            price: 102 + step % 2,
+            qty: 1,
+            side: Side::Bid,
+            trade_id: None,
+        });
+    }
+    strat
+}
+
+#[test]
+fn quotes_nothing_while_warming_up() {
+    let mut strat = avellaneda_stoikov();
+    strat.observe_mid(0, 100.0);
+    strat.observe_mid(SECOND, 101.0);
+    assert_eq!(strat.volatility.variance(), Some(1.0));
+    assert_eq!(strat.quotes(101.0, SECOND, 0), None);
+}
+
+#[test]
+fn spread_from_volatility_and_intensity() {
+    let strat = calibrated();
+    assert_eq!(strat.volatility.variance(), Some(1.0));
+    assert_eq!(strat.intensity.decay(0.5), Some(0.5));
+    assert_eq!(strat.intensity.rate(), Some(1.0));
+
+    // Flat book: gamma * sigma^2 * tau + 2 / gamma * ln(1 + gamma / k) around the mid
+    let spread = 0.1f32.mul_add(10.0, 20.0 * 1.2f32.ln());
+    let (bid, ask) = strat.quotes(100.0, 0, 0).unwrap();
+    assert!((ask - bid - spread).abs() < 1e-3);
+    assert!(((bid + ask) / 2.0 - 100.0).abs() < 1e-3);
+
+    // Long two lots: the reservation price drops by q * gamma * sigma^2 * tau
+    let (long_bid, long_ask) = strat.quotes(100.0, 0, 20).unwrap();
+    assert!(((long_bid + long_ask) / 2.0 - 98.0).abs() < 1e-3);
+    assert!((long_ask - long_bid - spread).abs() < 1e-3);
+}
+
+#[test]
+fn compared_with_fix_spread() {
+    let (ob_path, orders_path) = generated("avellaneda_stoikov");
+    let engine = Engine::new(&ob_path, &orders_path);
+    let mut strat = avellaneda_stoikov();
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    let metrics: StrategyMetrics = engine.run(&mut oms, &mut OrderBook::new()).unwrap();
+    let position = oms.position();
+    let mut baseline = fix_spread();
+    let mut oms = OrderManagementSystem::new(&mut baseline, TradingAccount::new(Money::ZERO));
+    let baseline_metrics = engine.run(&mut oms, &mut OrderBook::new()).unwrap();
+    std::fs::remove_file(&ob_path).unwrap();
+    std::fs::remove_file(&orders_path).unwrap();
+
+    // Calibrated on the replay and quoted once warmed up
+    assert!(strat.volatility.samples >= strat.warmup);
+    assert!(strat.intensity.samples >= strat.warmup);
+    assert!(strat.volatility.variance().unwrap() > 0.0);
+    assert!(metrics.trade_count > 0);
+    assert!(metrics.messages.messages > 0);
+    // Reported the same way as the baseline
+    assert_eq!(metrics.sessions.len(), baseline_metrics.sessions.len());
+    assert_eq!(metrics.trade_count, metrics.sessions[0].trade_count);
+    assert!(position.abs() <= 100);
+}
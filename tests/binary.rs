@@ -0,0 +1,168 @@
+use orderbook::{
+    data::{
+        csv_to_binary, snap_stream, trade_stream, BinaryFile, BinaryWriter, Header, RecordKind,
+        VERSION,
+    },
+    engine::{LimitOrder, Side, Signal, Snap, Trade},
+};
+use pretty_assertions::assert_eq;
+use std::path::PathBuf;
+
+fn tmp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("orderbook_{}_{name}", std::process::id()))
+}
+
+#[test]
+fn snap_roundtrip() {
+    let bin_path = tmp_path("ob.bin");
+    let count = csv_to_binary("data/ob.csv", &bin_path, RecordKind::Snap, 3).unwrap();
+    let from_csv = snap_stream("data/ob.csv")
+        .unwrap()
+        .collect::<Result<Vec<Snap>, _>>()
+        .unwrap();
+    let from_bin = snap_stream(&bin_path)
+        .unwrap()
+        .collect::<Result<Vec<Snap>, _>>()
+        .unwrap();
+    assert_eq!(count, from_csv.len());
+    assert_eq!(from_csv, from_bin);
+
+    let file = BinaryFile::open(&bin_path).unwrap();
+    assert_eq!(file.header.depth, 10);
+    assert_eq!(file.header.ticker_id, 3);
+    let first = file.snaps().unwrap().next().unwrap();
+    assert_eq!(first.exch_epoch(), from_csv[0].exch_epoch);
+    assert_eq!(first.level(10), from_csv[0].vec[10]);
+    assert!(file.trades().is_err());
+    std::fs::remove_file(bin_path).unwrap();
+}
+
+fn snap(exch_epoch: u64, depth: u32) -> Snap {
+    let levels = |side, first: u32| {
+        (0..depth).map(move |level| LimitOrder {
+            side,
+            price: first + level,
+            qty: 10,
+        })
+    };
+    Snap {
+        exch_epoch,
+        vec: levels(Side::Bid, 100)
+            .chain(levels(Side::Ask, 200))
+            .collect(),
+    }
+}
+
+#[test]
+fn snaps_deeper_than_header_are_rejected() {
+    let mut writer = BinaryWriter::new(Vec::new(), Header::new(RecordKind::Snap, 2, 3)).unwrap();
+    writer.write(&snap(1, 1)).unwrap();
+    writer.write(&snap(2, 2)).unwrap();
+    assert!(writer.write(&snap(3, 3)).is_err());
+    let snaps = BinaryFile::from_bytes(writer.finish().unwrap())
+        .unwrap()
+        .into_records::<Snap>()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    // The shallow one comes back without its padding
+    assert_eq!(snaps, vec![snap(1, 1), snap(2, 2)]);
+}
+
+#[test]
+fn snaps_deeper_than_any_header_are_rejected() {
+    let header = Header::new(RecordKind::Snap, u8::MAX, 3);
+    let mut writer = BinaryWriter::new(Vec::new(), header).unwrap();
+    writer.write(&snap(1, 255)).unwrap();
+    assert!(writer.write(&snap(2, 256)).is_err());
+}
+
+#[test]
+fn empty_snap_csv_is_not_converted() {
+    let csv_path = tmp_path("empty_ob.csv");
+    let bin_path = tmp_path("empty_ob.bin");
+    let header = std::fs::read_to_string("data/ob.csv").unwrap();
+    std::fs::write(&csv_path, header.lines().next().unwrap()).unwrap();
+    assert!(csv_to_binary(&csv_path, &bin_path, RecordKind::Snap, 3).is_err());
+    assert!(!bin_path.exists());
+    std::fs::remove_file(csv_path).unwrap();
+}
+
+#[test]
+fn trade_roundtrip() {
+    let bin_path = tmp_path("orders.bin");
+    csv_to_binary("data/orders.csv", &bin_path, RecordKind::Trade, 3).unwrap();
+    let from_csv = trade_stream("data/orders.csv")
+        .unwrap()
+        .collect::<Result<Vec<Trade>, _>>()
+        .unwrap();
+    let from_bin = trade_stream(&bin_path)
+        .unwrap()
+        .collect::<Result<Vec<Trade>, _>>()
+        .unwrap();
+    assert_eq!(from_csv, from_bin);
+    std::fs::remove_file(bin_path).unwrap();
+}
+
+#[test]
+fn signal_roundtrip() {
+    let csv_path = tmp_path("signals.csv");
+    let bin_path = tmp_path("signals.bin");
+    std::fs::write(
+        &csv_path,
+        "exch_epoch,side,value,horizon,instrument_id\n100,1,,,\n200,,-0.5,1000,3\n",
+    )
+    .unwrap();
+    csv_to_binary(&csv_path, &bin_path, RecordKind::Signal, 3).unwrap();
+    let file = BinaryFile::open(&bin_path).unwrap();
+    assert_eq!(file.header.version, VERSION);
+    let signals = file
+        .signals()
+        .unwrap()
+        .map(|s| s.to_signal())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        signals,
+        vec![
+            Signal {
+                exch_epoch: 100,
+                side: Side::Bid,
+                value: 1.0,
+                ..Default::default()
+            },
+            Signal {
+                exch_epoch: 200,
+                side: Side::Ask,
+                value: -0.5,
+                horizon: Some(1000),
+                instrument_id: Some(3),
+                ..Default::default()
+            },
+        ]
+    );
+    std::fs::remove_file(csv_path).unwrap();
+    std::fs::remove_file(bin_path).unwrap();
+}
+
+#[test]
+fn signal_v1_compat() {
+    let mut bytes = Header {
+        version: 1,
+        ..Header::new(RecordKind::Signal, 0, 3)
+    }
+    .to_bytes()
+    .to_vec();
+    for (epoch, side) in [(100_u64, Side::Bid), (200, Side::Ask)] {
+        bytes.extend_from_slice(&epoch.to_le_bytes());
+        bytes.push(side as u8);
+        bytes.extend_from_slice(&[0; 7]);
+    }
+    let signals = BinaryFile::from_bytes(bytes)
+        .unwrap()
+        .into_records::<Signal>()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(signals.len(), 2);
+    assert_eq!(signals[1].side, Side::Ask);
+    assert_eq!(signals[1].value, -1.0);
+    assert_eq!(signals[1].horizon, None);
+}
@@ -0,0 +1,106 @@
+use orderbook::{
+    backtest::{
+        strategy_flow, AvellanedaStoikovStrategy, BuildError, CalendarName, ConfigError, Engine,
+        FixSpreadStrategy, InventorySkew, RunConfig, StrategyConfig,
+    },
+    engine::{Money, OrderBook, Ticker, TradingAccount},
+    management::OrderManagementSystem,
+};
+use pretty_assertions::assert_eq;
+
+const SAMPLE: &str = "configs/fix_spread.yaml";
+
+#[test]
+fn sample_config_runs_as_code() {
+    let config = RunConfig::load(SAMPLE).unwrap();
+    assert_eq!(config.ticker.calendar, CalendarName::AlwaysOpen);
+    let StrategyConfig::FixSpread(params) = &config.strategy else {
+        panic!("{:?}", config.strategy);
+    };
+    assert_eq!(params.skew, InventorySkew::Linear { max_shift: 0.0001 });
+    let metrics = config.run().unwrap();
+
+    let mut strat = FixSpreadStrategy::new(Ticker::default());
+    strat.qty = 10;
+    strat.buy_criterion = -0.0001;
+    strat.sell_criterion = 0.0001;
+    strat.buy_position_limit = 100;
+    strat.sell_position_limit = -100;
+    strat.skew = InventorySkew::Linear { max_shift: 0.0001 };
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    let expected = strategy_flow(
+        &mut oms,
+        &mut OrderBook::new(),
+        "data/ob.csv",
+        "data/orders.csv",
+    );
+    assert_eq!(metrics, expected);
+}
+
+fn with_strategy(strategy: &str) -> String {
+    format!(
+        "data:
+  ob_path: data/ob.csv
+  orders_path: data/orders.csv
+ticker:
+  tick_size: 1.0
+  step_price: 0.1
+strategy:
+{strategy}"
+    )
+}
+
+#[test]
+fn invalid_strategy_is_not_run() {
+    let yaml = with_strategy(
+        "  kind: fix_spread
+  qty: 10
+  criterions: [-0.0001, 0.0001]
+  position_limits: [-100, 100]",
+    );
+    let config = RunConfig::from_yaml(&yaml).unwrap();
+    assert!(matches!(
+        config.run(),
+        Err(ConfigError::Build(BuildError::PositionLimits {
+            buy: -100,
+            sell: 100
+        }))
+    ));
+}
+
+#[test]
+fn unknown_fields_are_rejected() {
+    let yaml = with_strategy(
+        "  kind: fix_spread
+  qty: 10
+  criterion: [-0.0001, 0.0001]
+  position_limits: [100, -100]",
+    );
+    assert!(matches!(
+        RunConfig::from_yaml(&yaml),
+        Err(ConfigError::Yaml(_))
+    ));
+    assert!(matches!(
+        RunConfig::from_yaml(&with_strategy("  kind: grid")),
+        Err(ConfigError::Yaml(_))
+    ));
+}
+
+#[test]
+fn avellaneda_stoikov_config_runs_as_code() {
+    let config = RunConfig::load("configs/avellaneda_stoikov.yaml").unwrap();
+    let metrics = config.run().unwrap();
+
+    let mut strat = AvellanedaStoikovStrategy::builder(Ticker::default())
+        .qty(10)
+        .position_limits(100, -100)
+        .warmup(5)
+        .build()
+        .unwrap();
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    let expected = Engine::new("data/ob.csv", "data/orders.csv")
+        .run(&mut oms, &mut OrderBook::new())
+        .unwrap();
+    assert_eq!(metrics, expected);
+    assert!(metrics.messages.messages > 0);
+}
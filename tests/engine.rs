@@ -0,0 +1,225 @@
+use orderbook::{
+    backtest::{
+        strategy_flow, Context, Engine, FixSpreadStrategy, SignalStrategy, Strategy,
+        StrategyMetrics,
+    },
+    data::{generate_csv, GeneratorConfig, SignalSource},
+    engine::{ExecutionReport, Money, Order, OrderBook, Side, Ticker, Trade, TradingAccount},
+    management::{OrderManagementSystem, OrderTag, CLIENT_ID_BASE},
+};
+use pretty_assertions::assert_eq;
+use rstest::rstest;
+use std::path::PathBuf;
+
+fn generated(name: &str) -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir();
+    let ob_path = dir.join(format!("orderbook_{}_{name}_ob.csv", std::process::id()));
+    let orders_path = dir.join(format!(
+        "orderbook_{}_{name}_orders.csv",
+        std::process::id()
+    ));
+    let config = GeneratorConfig {
+        duration: 60_000_000_000,
+        ..GeneratorConfig::default()
+    };
+    generate_csv(config, &ob_path, &orders_path).unwrap();
+    (ob_path, orders_path)
+}
+
+fn fix_spread() -> FixSpreadStrategy {
+    let mut strat = FixSpreadStrategy::new(Ticker::default());
+    strat.buy_criterion = -0.0001;
+    strat.sell_criterion = 0.0001;
+    strat.buy_position_limit = 100;
+    strat.sell_position_limit = -100;
+    strat.qty = 100;
+    strat
+}
+
+fn run_flow(ob_path: &str, orders_path: &str) -> StrategyMetrics {
+    let mut strat = fix_spread();
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    strategy_flow(&mut oms, &mut OrderBook::new(), ob_path, orders_path)
+}
+
+#[test]
+fn engine_drives_fix_spread_as_its_flow() {
+    let (ob_path, orders_path) = generated("engine_flow");
+    let (ob_path, orders_path) = (ob_path.to_str().unwrap(), orders_path.to_str().unwrap());
+    let mut strat = fix_spread();
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    let metrics = Engine::new(ob_path, orders_path)
+        .run(&mut oms, &mut OrderBook::new())
+        .unwrap();
+    assert_eq!(metrics, run_flow(ob_path, orders_path));
+    std::fs::remove_file(ob_path).unwrap();
+    std::fs::remove_file(orders_path).unwrap();
+}
+
+// Strategy written outside the crate, joins the best bid once and counts callbacks
+#[derive(Default)]
+struct Counting {
+    ticker: Ticker,
+    calls: Vec<&'static str>,
+    snapshots: usize,
+    trades: usize,
+    timers: Vec<u64>,
+    bid_id: u64,
+    fills: u32,
+}
+
+impl Strategy for Counting {
+    fn ticker(&self) -> &Ticker {
+        &self.ticker
+    }
+    fn on_start(ctx: &mut Context<'_, '_, Self>) {
+        ctx.strategy().calls.push("start");
+        let price = ctx.ob.best_bid_price.unwrap();
+        let id = ctx.oms.new_order_id(ctx.epoch, Side::Bid, OrderTag::Open);
+        ctx.strategy().bid_id = id;
+        let _ = ctx.oms.submit(
+            ctx.ob,
+            Order {
+                id,
+                side: Side::Bid,
+                price,
+                qty: 1,
+            },
+        );
+    }
+    fn on_snapshot(ctx: &mut Context<'_, '_, Self>, _seen: &OrderBook) {
+        ctx.strategy().snapshots += 1;
+    }
+    fn on_trade(ctx: &mut Context<'_, '_, Self>, _trade: &Trade) {
+        ctx.strategy().trades += 1;
+    }
+    fn on_fill(ctx: &mut Context<'_, '_, Self>, exec_report: &ExecutionReport) {
+        let bid_id = ctx.strategy().bid_id;
+        let qty = exec_report
+            .filled_orders
+            .iter()
+            .filter(|fill| fill.0 == bid_id)
+            .map(|fill| fill.1)
+            .sum::<u32>();
+        ctx.strategy().fills += qty;
+    }
+    fn on_timer(ctx: &mut Context<'_, '_, Self>) {
+        let epoch = ctx.epoch;
+        ctx.strategy().timers.push(epoch);
+    }
+    fn on_end(ctx: &mut Context<'_, '_, Self>) {
+        ctx.strategy().calls.push("end");
+    }
+}
+
+#[test]
+fn callbacks_of_custom_strategy() {
+    let (ob_path, orders_path) = generated("engine_callbacks");
+    let mut strat = Counting::default();
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    let engine = Engine {
+        timer: Some(10_000_000_000),
+        ..Engine::new(ob_path.to_str().unwrap(), orders_path.to_str().unwrap())
+    };
+    let metrics = engine.run(&mut oms, &mut OrderBook::new()).unwrap();
+    // Booked by the engine, the strategy only counts them
+    let position = oms.position();
+    std::fs::remove_file(&ob_path).unwrap();
+    std::fs::remove_file(&orders_path).unwrap();
+
+    assert_eq!(strat.calls, vec!["start", "end"]);
+    assert!(strat.snapshots > 0);
+    assert!(strat.trades > 0);
+    // Every 10 seconds of the minute after the first snapshot
+    assert_eq!(strat.timers.len(), 5);
+    assert!(strat
+        .timers
+        .windows(2)
+        .all(|pair| pair[1] - pair[0] == 10_000_000_000));
+    assert!(strat.fills <= 1);
+    assert_eq!(position, i32::try_from(strat.fills).unwrap());
+    assert_eq!(metrics.messages.messages, 1);
+}
+
+fn signal_strategy() -> SignalStrategy {
+    SignalStrategy {
+        ticker: Ticker::default(),
+        qty: 10,
+        buy_open_criterion: 0.0001,
+        sell_open_criterion: -0.0001,
+        buy_close_criterion: -0.0001,
+        sell_close_criterion: 0.0001,
+        buy_position_limit: 50,
+        sell_position_limit: -50,
+        maker_range: (-f32::INFINITY, f32::INFINITY),
+        taker_range: (-f32::INFINITY, f32::INFINITY),
+        ..SignalStrategy::default()
+    }
+}
+
+// A buy and a sell every ten seconds of the minute, each relevant for `horizon`
+fn run_signals(
+    name: &str,
+    oms: &mut OrderManagementSystem<SignalStrategy>,
+    horizon: Option<u64>,
+) -> StrategyMetrics {
+    let (ob_path, orders_path) = generated(name);
+    let signals_path = ob_path.with_extension("signals.csv");
+    let start = GeneratorConfig::default().start_epoch;
+    let horizon = horizon
+        .map(|horizon| horizon.to_string())
+        .unwrap_or_default();
+    let rows: String = (1..6)
+        .map(|step| {
+            let epoch = start + step * 10_000_000_000;
+            format!("{epoch},{},,{horizon},\n", step % 2)
+        })
+        .collect();
+    std::fs::write(
+        &signals_path,
+        format!("exch_epoch,side,value,horizon,instrument_id\n{rows}"),
+    )
+    .unwrap();
+    let engine = Engine {
+        signals: vec![SignalSource::new("test", &signals_path, 0)],
+        ..Engine::new(ob_path.to_str().unwrap(), orders_path.to_str().unwrap())
+    };
+    let metrics = engine.run(oms, &mut OrderBook::new()).unwrap();
+    for path in [&ob_path, &orders_path, &signals_path] {
+        std::fs::remove_file(path).unwrap();
+    }
+    metrics
+}
+
+fn close_orders(oms: &OrderManagementSystem<SignalStrategy>) -> usize {
+    (CLIENT_ID_BASE..CLIENT_ID_BASE + oms.ids.len() as u64)
+        .filter(|&id| oms.ids.get(id).unwrap().tag == OrderTag::Close)
+        .count()
+}
+
+#[test]
+fn signal_strategy_trades_its_signals() {
+    let mut strat = signal_strategy();
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    let metrics = run_signals("engine_signals", &mut oms, None);
+
+    // Signals open the position and the snapshots work it back
+    assert!(oms.strategy.opened_at.is_some());
+    assert!(metrics.trade_count > 0);
+    assert!((-50..=50).contains(&oms.position()));
+}
+
+#[rstest]
+#[case(None, false)]
+#[case(Some(1_000_000_000), true)]
+fn expired_signals_close_the_position(#[case] horizon: Option<u64>, #[case] closed: bool) {
+    // Only the first signal is taken and nothing is worked back but an expired one
+    let mut strat = SignalStrategy {
+        cooldown: u64::MAX,
+        ..signal_strategy()
+    };
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    let name = format!("engine_horizon_{closed}");
+    let _ = run_signals(&name, &mut oms, horizon);
+    assert_eq!(close_orders(&oms) > 0, closed);
+}
@@ -0,0 +1,102 @@
+mod common;
+use common::full_ob;
+use orderbook::{
+    backtest::FixSpreadStrategy,
+    engine::indicators::Midprice,
+    engine::{
+        FeeRates, FeeSchedule, FeeTier, Liquidity, Money, Order, Side, Ticker, Trade,
+        TradingAccount,
+    },
+    management::{OrderManagementSystem, QuoteId},
+};
+use pretty_assertions::assert_eq;
+
+// Rebate for makers, both rates improve after 400 of turnover
+const SCHEDULE: FeeSchedule = FeeSchedule {
+    base: FeeRates::proportional(-0.001, 0.002),
+    tiers: &[FeeTier {
+        min_turnover: 400,
+        rates: FeeRates::proportional(-0.002, 0.001),
+    }],
+    clearing_per_contract: 0.0,
+    min_fee: 0.0,
+};
+
+#[test]
+fn tiers_follow_turnover() {
+    let schedule = FeeSchedule {
+        clearing_per_contract: 0.01,
+        min_fee: 0.5,
+        ..SCHEDULE
+    };
+    let ticker = Ticker {
+        fees: schedule,
+        ..Ticker::default()
+    };
+    let mut account = TradingAccount::new(Money::ZERO);
+    // 300 * 0.002 + 3 * 0.01
+    let fee = account.record_fill(&ticker, Side::Bid, 3, 100, Liquidity::Taker);
+    assert_eq!(fee, Money::from_f64(0.63));
+    // Still the base tier: 200 * 0.002 raised to the minimum
+    let fee = account.record_fill(&ticker, Side::Ask, 2, 100, Liquidity::Taker);
+    assert_eq!(fee, Money::from_f64(0.52));
+    // Turnover is 500 now: rebate of 100 * 0.002 minus clearing
+    let fee = account.record_fill(&ticker, Side::Ask, 1, 100, Liquidity::Maker);
+    assert_eq!(fee, Money::from_f64(-0.19));
+    assert_eq!(account.cumulative_volume, 600);
+    assert_eq!(account.fills.len(), 3);
+    assert_eq!(account.fills[2].liquidity, Liquidity::Maker);
+    assert_eq!(account.fees, Money::from_f64(0.63 + 0.52 - 0.19));
+    assert_eq!(account.balance, -account.fees);
+}
+
+#[test]
+fn fills_are_charged_by_liquidity() {
+    let mut strat = FixSpreadStrategy::new(Ticker {
+        fees: SCHEDULE,
+        ..Ticker::default()
+    });
+    strat.qty = 5;
+    strat.buy_criterion = -0.01;
+    strat.sell_criterion = 0.01;
+    strat.buy_position_limit = 5;
+    strat.sell_position_limit = -5;
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    let mut ob = full_ob();
+    let m = Midprice::evaluate(&ob);
+    oms.send_orders(&mut ob, m, Some(QuoteId::Given(1)), Some(QuoteId::Given(2)));
+
+    // Own bid at 99 is filled passively after the resting 10 lots
+    let (exec_report, _) = oms.apply_trade(
+        &mut ob,
+        &Trade {
+            exch_epoch: 1,
+            side: Side::Ask,
+            price: 99,
+            qty: 15,
+            trade_id: None,
+        },
+    );
+    oms.update(&exec_report);
+    assert_eq!(oms.account.fills.len(), 1);
+    assert_eq!(oms.account.fills[0].liquidity, Liquidity::Maker);
+    assert_eq!(oms.account.fills[0].fee, Money::from_f64(-0.495));
+
+    // Closing the long takes liquidity at the upper tier
+    ob.add_limit_order(Order {
+        id: 667,
+        side: Side::Bid,
+        price: 98,
+        qty: 10,
+    });
+    oms.flatten(&mut ob, 2).unwrap();
+    let taker = oms.account.fills[1];
+    assert_eq!(
+        (taker.side, taker.qty, taker.price, taker.liquidity),
+        (Side::Ask, 5, 98, Liquidity::Taker)
+    );
+    assert_eq!(taker.fee, Money::from_f64(0.49));
+    assert_eq!(oms.position(), 0);
+    assert_eq!(oms.account.fees, Money::from_f64(-0.005));
+    assert_eq!(oms.account.balance, Money::from_f64(-5.0 + 0.005));
+}
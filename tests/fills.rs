@@ -0,0 +1,136 @@
+use orderbook::{
+    backtest::{strategy_flow, FixSpreadStrategy},
+    data::{generate_csv, GeneratorConfig},
+    engine::{
+        ExecutionReport, FillModel, Money, Order, OrderBook, OrderStatus, Side, Ticker, Trade,
+        TradingAccount,
+    },
+    management::OrderManagementSystem,
+};
+use pretty_assertions::assert_eq;
+use rstest::rstest;
+
+const OWN_ID: u64 = 100;
+
+fn bid(id: u64, price: u32, qty: u32) -> Order {
+    Order {
+        id,
+        side: Side::Bid,
+        price,
+        qty,
+    }
+}
+
+// 10 lots ahead of our 5 lots at 100, then 20 lots at 99
+fn book() -> OrderBook {
+    let mut ob = OrderBook::new();
+    for order in [bid(1, 100, 10), bid(OWN_ID, 100, 5), bid(2, 99, 20)] {
+        let _ = ob.add_limit_order(order);
+    }
+    let _ = ob.add_limit_order(Order {
+        id: 3,
+        side: Side::Ask,
+        price: 102,
+        qty: 10,
+    });
+    ob
+}
+
+fn sell_print(price: u32, qty: u32) -> Trade {
+    Trade {
+        exch_epoch: 1_000,
+        side: Side::Ask,
+        price,
+        qty,
+        trade_id: None,
+    }
+}
+
+fn report(filled_orders: Vec<(u64, u32, u32)>, remaining_qty: u32) -> ExecutionReport {
+    ExecutionReport {
+        own_id: 1_000,
+        own_side: Side::Ask,
+        remaining_qty,
+        filled_orders,
+        status: OrderStatus::Filled,
+    }
+}
+
+#[rstest]
+// Print at 99 trades through our bid, fills are capped by the printed qty
+#[case(FillModel::TradeThrough, sell_print(99, 1), report(vec![(OWN_ID, 1, 100)], 0), Some(4))]
+#[case(FillModel::TradeThrough, sell_print(99, 6), report(vec![(OWN_ID, 5, 100), (2, 1, 99)], 0), None)]
+#[case(FillModel::BookSweep, sell_print(99, 1), report(vec![(1, 1, 100)], 0), Some(5))]
+// Print at our price fills what is left after the queue ahead
+#[case(FillModel::TradeThrough, sell_print(100, 13), report(vec![(1, 10, 100), (OWN_ID, 3, 100)], 0), Some(2))]
+#[case(FillModel::TradeThrough, sell_print(100, 8), report(vec![(1, 8, 100)], 0), Some(5))]
+#[case(FillModel::TradeThrough, sell_print(100, 20), ExecutionReport {
+    status: OrderStatus::PartiallyFilled,
+    ..report(vec![(1, 10, 100), (OWN_ID, 5, 100)], 5)
+}, None)]
+// Deeper than the book, everything foreign above is gone
+#[case(FillModel::TradeThrough, sell_print(90, 4), report(vec![(OWN_ID, 4, 100)], 0), Some(1))]
+#[case(FillModel::TradeThrough, sell_print(90, 9), ExecutionReport {
+    status: OrderStatus::PartiallyFilled,
+    ..report(vec![(OWN_ID, 5, 100)], 4)
+}, None)]
+fn own_fills(
+    #[case] fill_model: FillModel,
+    #[case] trade: Trade,
+    #[case] expected: ExecutionReport,
+    #[case] own_left: Option<u32>,
+) {
+    let mut ob = book();
+    let (exec_report, _) = ob.apply_trade_with(&trade, fill_model, &[OWN_ID]);
+    assert_eq!(exec_report, expected);
+    assert_eq!(ob.get_order(OWN_ID).map(|order| order.qty), own_left);
+}
+
+#[test]
+fn through_fills_reach_the_account() {
+    let mut strat = FixSpreadStrategy::new(Ticker::default());
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    oms.fill_model = FillModel::TradeThrough;
+    oms.active_buy_order = Some(bid(OWN_ID, 100, 5));
+    let mut ob = book();
+    let (exec_report, mismatch) = oms.apply_trade(&mut ob, &sell_print(98, 8));
+    oms.update(&exec_report);
+    assert_eq!(oms.position(), 5);
+    assert_eq!(oms.active_buy_order, None);
+    assert_eq!(oms.account.balance, Money::from_units(-500));
+    assert_eq!(mismatch.map(|m| m.unmatched_qty), Some(3));
+    assert_eq!(ob.best_bid_price, None);
+}
+
+#[test]
+fn trade_through_flow() {
+    let dir = std::env::temp_dir();
+    let ob_path = dir.join(format!("orderbook_{}_fills_ob.csv", std::process::id()));
+    let orders_path = dir.join(format!("orderbook_{}_fills_orders.csv", std::process::id()));
+    let config = GeneratorConfig {
+        duration: 60_000_000_000,
+        ..GeneratorConfig::default()
+    };
+    generate_csv(config, &ob_path, &orders_path).unwrap();
+    let mut volumes = Vec::new();
+    for fill_model in [FillModel::BookSweep, FillModel::TradeThrough] {
+        let mut strat = FixSpreadStrategy::new(Ticker::default());
+        strat.buy_criterion = -0.0001;
+        strat.sell_criterion = 0.0001;
+        strat.buy_position_limit = 100;
+        strat.sell_position_limit = -100;
+        strat.qty = 10;
+        let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+        oms.fill_model = fill_model;
+        let metrics = strategy_flow(
+            &mut oms,
+            &mut OrderBook::new(),
+            ob_path.to_str().unwrap(),
+            orders_path.to_str().unwrap(),
+        );
+        volumes.push(metrics.volume);
+    }
+    std::fs::remove_file(&ob_path).unwrap();
+    std::fs::remove_file(&orders_path).unwrap();
+    assert!(volumes[1] >= volumes[0]);
+}
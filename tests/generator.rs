@@ -0,0 +1,89 @@
+use orderbook::{
+    data::{
+        generate_csv, snap_stream, trade_stream, validate_file, Arrivals, CleaningPolicy,
+        Generator, GeneratorConfig, CSV_DEPTH,
+    },
+    engine::{Side, Snap, Trade},
+};
+use pretty_assertions::assert_eq;
+use rstest::rstest;
+use std::path::PathBuf;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("orderbook_{}_{name}", std::process::id()))
+}
+
+fn config(seed: u64, market_flow: Arrivals) -> GeneratorConfig {
+    GeneratorConfig {
+        seed,
+        duration: 20_000_000_000,
+        market_flow,
+        ..GeneratorConfig::default()
+    }
+}
+
+#[test]
+fn same_seed_same_data() {
+    let (snaps, trades) = Generator::new(config(7, GeneratorConfig::default().market_flow)).run();
+    let (snaps_2, trades_2) =
+        Generator::new(config(7, GeneratorConfig::default().market_flow)).run();
+    assert_eq!(snaps, snaps_2);
+    assert_eq!(trades, trades_2);
+    let (snaps_3, _) = Generator::new(config(8, GeneratorConfig::default().market_flow)).run();
+    assert_ne!(snaps, snaps_3);
+}
+
+#[rstest]
+#[case(Arrivals::Poisson { rate: 2.0 })]
+#[case(Arrivals::Hawkes { base: 1.0, alpha: 0.5, beta: 1.0 })]
+fn books_are_sane(#[case] market_flow: Arrivals) {
+    let config = config(42, market_flow);
+    let (snaps, trades) = Generator::new(config.clone()).run();
+    assert_eq!(snaps.len(), 200);
+    assert!(!trades.is_empty());
+    for snap in &snaps {
+        let bids = snap.vec.iter().filter(|level| level.side == Side::Bid);
+        let asks = snap.vec.iter().filter(|level| level.side == Side::Ask);
+        assert_eq!(bids.clone().count(), CSV_DEPTH);
+        assert_eq!(asks.clone().count(), CSV_DEPTH);
+        let best_bid = bids.map(|level| level.price).max().unwrap();
+        let best_ask = asks.map(|level| level.price).min().unwrap();
+        assert!(best_bid < best_ask);
+    }
+    let end = config.start_epoch + config.duration;
+    assert!(trades
+        .windows(2)
+        .all(|pair| pair[0].exch_epoch <= pair[1].exch_epoch));
+    assert!(trades.iter().all(|trade| trade.qty > 0
+        && config.start_epoch < trade.exch_epoch
+        && trade.exch_epoch <= end));
+}
+
+#[test]
+fn csv_roundtrip() {
+    let ob_path = temp_path("gen_ob.csv");
+    let orders_path = temp_path("gen_orders.csv");
+    let config = config(3, GeneratorConfig::default().market_flow);
+    let (snaps, trades) = Generator::new(config.clone()).run();
+    let counts = generate_csv(config, &ob_path, &orders_path).unwrap();
+    assert_eq!(counts, (snaps.len(), trades.len()));
+
+    let read_snaps = snap_stream(&ob_path)
+        .unwrap()
+        .collect::<Result<Vec<Snap>, _>>()
+        .unwrap();
+    let read_trades = trade_stream(&orders_path)
+        .unwrap()
+        .collect::<Result<Vec<Trade>, _>>()
+        .unwrap();
+    assert_eq!(read_snaps, snaps);
+    assert_eq!(read_trades, trades);
+
+    let (_, snap_report) = validate_file::<Snap>(&ob_path, CleaningPolicy::default()).unwrap();
+    let (_, trade_report) =
+        validate_file::<Trade>(&orders_path, CleaningPolicy::default()).unwrap();
+    std::fs::remove_file(&ob_path).unwrap();
+    std::fs::remove_file(&orders_path).unwrap();
+    assert!(snap_report.is_clean());
+    assert!(trade_report.is_clean());
+}
@@ -0,0 +1,144 @@
+mod common;
+use common::full_ob;
+use orderbook::{
+    backtest::{hedge_flow, FixSpreadStrategy, HedgeStrategy},
+    data::{generate_csv, CleaningPolicy, GeneratorConfig},
+    engine::indicators::Midprice,
+    engine::{Money, OrderBook, Side, Ticker, TradingAccount},
+    management::{HedgeStats, OrderManagementSystem, QuoteId},
+};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn passive_then_aggressive() {
+    let mut strat = HedgeStrategy::new(Ticker::default(), 0.5);
+    strat.escalate_after = 100;
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    let mut ob = full_ob();
+    // Long 10 in the source is hedged by selling 5
+    oms.follow(10, 0, Midprice::evaluate(&ob));
+    assert_eq!(oms.gap(), -5);
+    oms.hedge(
+        &mut ob,
+        50,
+        Some(QuoteId::Given(1)),
+        Some(QuoteId::Given(2)),
+    );
+    assert_eq!(oms.active_buy_order, None);
+    assert_eq!(
+        oms.active_sell_order.map(|order| (order.price, order.qty)),
+        Some((101, 5))
+    );
+
+    // Still passive before the timer, then the bid is hit
+    oms.hedge(
+        &mut ob,
+        99,
+        Some(QuoteId::Given(3)),
+        Some(QuoteId::Given(4)),
+    );
+    assert_eq!(oms.active_sell_order.map(|order| order.id), Some(2));
+    oms.hedge(
+        &mut ob,
+        100,
+        Some(QuoteId::Given(5)),
+        Some(QuoteId::Given(6)),
+    );
+    assert_eq!(oms.active_sell_order, None);
+    assert_eq!((oms.position(), oms.gap()), (-5, 0));
+    assert_eq!(
+        oms.strategy.stats,
+        HedgeStats {
+            // Sold 5 at 99 against arrival mid of 100
+            slippage: Money::from_units(5),
+            passive_qty: 0,
+            aggressive_qty: 5,
+            escalations: 1,
+        }
+    );
+
+    // Source back to flat, the hedge buys back passively on a fresh timer
+    oms.follow(0, 200, Midprice::evaluate(&ob));
+    oms.hedge(
+        &mut ob,
+        200,
+        Some(QuoteId::Given(7)),
+        Some(QuoteId::Given(8)),
+    );
+    let bid = oms.active_buy_order.unwrap();
+    assert_eq!((bid.side, bid.price, bid.qty), (Side::Bid, 99, 5));
+}
+
+#[test]
+fn rehedge_takes_an_id_for_the_gap_side_only() {
+    let mut strat = HedgeStrategy::new(Ticker::default(), 0.5);
+    strat.escalate_after = 100;
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    let mut ob = full_ob();
+    oms.follow(10, 0, Midprice::evaluate(&ob));
+    oms.rehedge(&mut ob, 50);
+    assert_eq!(oms.active_buy_order, None);
+    assert_eq!(oms.active_sell_order.map(|order| order.qty), Some(5));
+    assert_eq!(oms.ids.len(), 1);
+}
+
+#[test]
+fn hedge_follows_flow() {
+    let dir = std::env::temp_dir();
+    let name = format!("orderbook_{}_hedge", std::process::id());
+    let paths: Vec<_> = ["ob", "orders", "hedge_ob", "hedge_orders"]
+        .iter()
+        .map(|kind| dir.join(format!("{name}_{kind}.csv")))
+        .collect();
+    let config = GeneratorConfig {
+        duration: 60_000_000_000,
+        ..GeneratorConfig::default()
+    };
+    generate_csv(config.clone(), &paths[0], &paths[1]).unwrap();
+    let hedge_config = GeneratorConfig {
+        seed: 7,
+        initial_price: 13_400,
+        ..config
+    };
+    generate_csv(hedge_config, &paths[2], &paths[3]).unwrap();
+
+    let mut strat = FixSpreadStrategy::new(Ticker::default());
+    strat.buy_criterion = -0.0001;
+    strat.sell_criterion = 0.0001;
+    strat.buy_position_limit = 100;
+    strat.sell_position_limit = -100;
+    strat.qty = 100;
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    let mut hedge_strat = HedgeStrategy::new(Ticker::default(), 2.0);
+    hedge_strat.escalate_after = 1_000_000_000;
+    let mut hedge = OrderManagementSystem::new(&mut hedge_strat, TradingAccount::new(Money::ZERO));
+    let (mut ob, mut hedge_ob) = (OrderBook::new(), OrderBook::new());
+    let path = |idx: usize| paths[idx].to_str().unwrap();
+    let metrics = hedge_flow(
+        &mut oms,
+        &mut ob,
+        (path(0), path(1)),
+        &mut hedge,
+        &mut hedge_ob,
+        (path(2), path(3)),
+        CleaningPolicy::default(),
+    );
+    for path in &paths {
+        std::fs::remove_file(path).unwrap();
+    }
+
+    assert!(metrics.source.trade_count > 0);
+    assert!(metrics.hedge.trade_count > 0);
+    let stats = metrics.stats;
+    assert!(stats.passive_qty + stats.aggressive_qty > 0);
+    // The hedge targets twice the source position the other way
+    assert_eq!(hedge.strategy.target, -2 * oms.position());
+    assert_eq!(hedge.position() + metrics.gap, hedge.strategy.target);
+    let traded: u32 = hedge.account.fills.iter().map(|fill| fill.qty).sum();
+    assert_eq!(stats.passive_qty + stats.aggressive_qty, traded);
+    // Slippage is kept out of the hedge pnl
+    assert_eq!(
+        metrics.hedge.pnl_abs,
+        hedge.get_pnl(Midprice::evaluate(&hedge_ob), false).unwrap()
+    );
+}
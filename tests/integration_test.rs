@@ -1,9 +1,10 @@
 mod common;
 use orderbook::{
     backtest::FixSpreadStrategy,
+    engine::Money,
     engine::Ticker,
     engine::TradingAccount,
-    engine::{place_body, LimitOrder, Order, OrderBook, Side, Snap},
+    engine::{place_body, LimitOrder, Order, OrderBook, Side, Signal, Snap, Trade, QUEUE_ID_BASE},
     management::OrderManagementSystem,
 };
 use pretty_assertions::assert_eq;
@@ -27,11 +28,79 @@ fn deser_level(deser: Snap) {
     assert_eq!(snap.qty, 52);
 }
 
+#[test]
+fn deser_trade() {
+    let data = "exch_epoch,side,price,qty,trade_id\n170000,0,26847,135,\n170001,1,26850,45,42";
+    let mut reader = csv::Reader::from_reader(data.as_bytes());
+    let trades = reader
+        .deserialize::<Trade>()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(
+        trades,
+        vec![
+            Trade {
+                exch_epoch: 170_000,
+                price: 26847,
+                qty: 135,
+                side: Side::Ask,
+                trade_id: None,
+            },
+            Trade {
+                exch_epoch: 170_001,
+                price: 26850,
+                qty: 45,
+                side: Side::Bid,
+                trade_id: Some(42),
+            },
+        ]
+    );
+}
+
+#[test]
+fn deser_signal() {
+    let data = "exch_epoch,side,value,horizon,instrument_id\n1,-1,,,\n2,,0.25,500,\n3,1,-0.1,,7";
+    let mut reader = csv::Reader::from_reader(data.as_bytes());
+    let signals = reader
+        .deserialize::<Signal>()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(
+        signals,
+        vec![
+            Signal {
+                exch_epoch: 1,
+                side: Side::Ask,
+                value: -1.0,
+                ..Default::default()
+            },
+            Signal {
+                exch_epoch: 2,
+                side: Side::Bid,
+                value: 0.25,
+                horizon: Some(500),
+                ..Default::default()
+            },
+            // Explicit side wins over the sign of value
+            Signal {
+                exch_epoch: 3,
+                side: Side::Bid,
+                value: -0.1,
+                instrument_id: Some(7),
+                ..Default::default()
+            },
+        ]
+    );
+    let data = "exch_epoch,side,value\n1,,0.0";
+    let mut reader = csv::Reader::from_reader(data.as_bytes());
+    assert!(reader.deserialize::<Signal>().next().unwrap().is_err());
+}
+
 #[rstest]
 fn deser_to_ob(deser: Snap) {
     let mut ob = OrderBook::new();
     let strat = &mut FixSpreadStrategy::new(Ticker::default());
-    let oms = &mut OrderManagementSystem::new(strat, TradingAccount::new(0.0));
+    let oms = &mut OrderManagementSystem::new(strat, TradingAccount::new(Money::ZERO));
 
     let snap = deser;
     ob = ob.process(snap, oms, place_body(false));
@@ -43,7 +112,7 @@ fn exec_report_test() {
     let trader_order_id = 333;
     let mut ob = OrderBook::new();
     let strat = &mut FixSpreadStrategy::new(Ticker::default());
-    let oms = &mut OrderManagementSystem::new(strat, TradingAccount::new(0.0));
+    let oms = &mut OrderManagementSystem::new(strat, TradingAccount::new(Money::ZERO));
     let snap = Snap {
         exch_epoch: 0,
         vec: vec![
@@ -105,6 +174,11 @@ fn exec_report_test() {
         qty: 135,
         id: 1010,
     });
-    let filled_orders = vec![(332, 100, 99), (333, 10, 99), (334, 25, 99)];
+    // Queue ahead and behind our order get synthetic ids
+    let filled_orders = vec![
+        (QUEUE_ID_BASE + 1, 100, 99),
+        (333, 10, 99),
+        (QUEUE_ID_BASE + 2, 25, 99),
+    ];
     assert_eq!(exec_report.filled_orders, filled_orders);
 }
@@ -0,0 +1,139 @@
+use orderbook::{
+    backtest::{strategy_flow, FixSpreadStrategy},
+    data::{generate_csv, GeneratorConfig},
+    engine::{
+        place_body, LimitOrder, Money, Order, OrderBook, QueueModel, Side, Snap, Ticker, Trade,
+        TradingAccount,
+    },
+    management::OrderManagementSystem,
+};
+use pretty_assertions::assert_eq;
+use rstest::rstest;
+
+fn bid(id: u64, price: u32, qty: u32) -> Order {
+    Order {
+        id,
+        side: Side::Bid,
+        price,
+        qty,
+    }
+}
+
+fn snap(qty_99: u32) -> Snap {
+    let level = |side, price, qty| LimitOrder { side, price, qty };
+    Snap {
+        exch_epoch: 0,
+        vec: vec![
+            level(Side::Bid, 99, qty_99),
+            level(Side::Bid, 98, 7),
+            level(Side::Ask, 101, 10),
+        ],
+    }
+}
+
+// Two own orders at 99 split the queue into three parts, one more rung at 98
+#[rstest]
+#[case(QueueModel::Pessimistic, vec![10, 2, 0])]
+#[case(QueueModel::Optimistic, vec![3, 5, 4])]
+#[case(QueueModel::Proportional, vec![7, 4, 1])]
+fn queue_per_own_order(#[case] queue_model: QueueModel, #[case] expected: Vec<u32>) {
+    let mut strat = FixSpreadStrategy::new(Ticker::default());
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    oms.queue_model = queue_model;
+    oms.ladder = vec![bid(100, 99, 2), bid(101, 99, 3), bid(102, 98, 1)];
+    let mut ob = OrderBook::new();
+    for order in [
+        bid(1, 99, 10),
+        oms.ladder[0],
+        bid(2, 99, 5),
+        oms.ladder[1],
+        bid(3, 99, 4),
+        bid(4, 98, 7),
+        oms.ladder[2],
+    ] {
+        let _ = ob.add_limit_order(order);
+    }
+    let levels = ob.get_level_queues(&oms.own_order_ids());
+    assert_eq!(levels.len(), 2);
+    assert_eq!(levels[0].gaps, vec![10, 5, 4]);
+    assert_eq!(levels[1].gaps, vec![7, 0]);
+
+    // Level at 99 shrinks from 19 to 12 lots
+    let mut ob = ob.process(snap(12), &mut oms, place_body(false));
+    let levels = ob.get_level_queues(&oms.own_order_ids());
+    assert_eq!(levels[0].orders, oms.ladder[..2].to_vec());
+    assert_eq!(levels[0].gaps, expected);
+    assert_eq!(levels[1].gaps, vec![7, 0]);
+
+    // A print through the whole level fills both rungs
+    let (exec_report, _) = ob.apply_trade(&Trade {
+        exch_epoch: 1,
+        side: Side::Ask,
+        price: 99,
+        qty: 17,
+        trade_id: None,
+    });
+    oms.update(&exec_report);
+    assert_eq!(oms.position(), 5);
+    assert_eq!(oms.ladder, vec![bid(102, 98, 1)]);
+}
+
+#[test]
+fn ladder_keeps_queue_position() {
+    let mut strat = FixSpreadStrategy::new(Ticker::default());
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    let mut ob = OrderBook::new();
+    let _ = ob.add_limit_order(Order {
+        id: 1,
+        side: Side::Ask,
+        price: 101,
+        qty: 10,
+    });
+    oms.place_ladder(&mut ob, &[bid(10, 99, 1), bid(11, 98, 1), bid(12, 101, 1)]);
+    // Crossing rung is skipped
+    assert_eq!(oms.ladder, vec![bid(10, 99, 1), bid(11, 98, 1)]);
+    let _ = ob.add_limit_order(bid(2, 99, 5));
+    oms.place_ladder(&mut ob, &[bid(20, 99, 1), bid(21, 97, 1)]);
+    assert_eq!(oms.ladder, vec![bid(10, 99, 1), bid(21, 97, 1)]);
+    assert!(ob.get_order(11).is_none());
+    let levels = ob.get_level_queues(&[10]);
+    assert_eq!(levels[0].gaps, vec![0, 5]);
+}
+
+#[test]
+fn ladder_flow() {
+    let dir = std::env::temp_dir();
+    let ob_path = dir.join(format!("orderbook_{}_ladder_ob.csv", std::process::id()));
+    let orders_path = dir.join(format!(
+        "orderbook_{}_ladder_orders.csv",
+        std::process::id()
+    ));
+    let config = GeneratorConfig {
+        duration: 60_000_000_000,
+        ..GeneratorConfig::default()
+    };
+    generate_csv(config, &ob_path, &orders_path).unwrap();
+    let mut volumes = Vec::new();
+    for ladder_depth in [0, 3] {
+        let mut strat = FixSpreadStrategy::new(Ticker::default());
+        strat.buy_criterion = -0.0001;
+        strat.sell_criterion = 0.0001;
+        strat.buy_position_limit = 100;
+        strat.sell_position_limit = -100;
+        strat.qty = 10;
+        strat.ladder_depth = ladder_depth;
+        let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+        let metrics = strategy_flow(
+            &mut oms,
+            &mut OrderBook::new(),
+            ob_path.to_str().unwrap(),
+            orders_path.to_str().unwrap(),
+        );
+        assert!(oms.position().abs() <= 100);
+        volumes.push(metrics.volume);
+    }
+    std::fs::remove_file(&ob_path).unwrap();
+    std::fs::remove_file(&orders_path).unwrap();
+    // Deeper rungs get hit by the prints sweeping several levels
+    assert!(volumes[1] > volumes[0]);
+}
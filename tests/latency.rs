@@ -0,0 +1,111 @@
+use orderbook::{
+    backtest::{strategy_flow, FixSpreadStrategy, StrategyMetrics},
+    data::{generate_csv, write_snaps_csv, write_trades_csv, GeneratorConfig},
+    engine::{
+        Latency, LatencyModel, LimitOrder, Money, OrderBook, Side, Snap, Ticker, Trade,
+        TradingAccount,
+    },
+    management::OrderManagementSystem,
+};
+use pretty_assertions::assert_eq;
+use rstest::rstest;
+use std::path::PathBuf;
+
+const START: u64 = 1_000_000_000;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("orderbook_{}_{name}", std::process::id()))
+}
+
+// 99 / 102 book, ten levels per side
+fn snap(exch_epoch: u64) -> Snap {
+    let bids = (90..100).rev().map(|price| LimitOrder {
+        side: Side::Bid,
+        price,
+        qty: 10,
+    });
+    let asks = (102..112).map(|price| LimitOrder {
+        side: Side::Ask,
+        price,
+        qty: 10,
+    });
+    Snap {
+        exch_epoch,
+        vec: bids.chain(asks).collect(),
+    }
+}
+
+fn run(latency: LatencyModel, ob_path: &str, orders_path: &str) -> (StrategyMetrics, i32) {
+    let mut strat = FixSpreadStrategy::new(Ticker::default());
+    // Bid inside the spread at 100, ask joins the 102 level
+    strat.buy_criterion = -0.001;
+    strat.sell_criterion = 0.01;
+    strat.buy_position_limit = 10;
+    strat.sell_position_limit = -10;
+    strat.qty = 1;
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    oms.latency = latency;
+    let metrics = strategy_flow(&mut oms, &mut OrderBook::new(), ob_path, orders_path);
+    (metrics, oms.position())
+}
+
+// A sell print at 100 arrives 9ns after the quoting snapshot
+#[rstest]
+#[case(Latency::Constant(0), Latency::Constant(0), 1)]
+#[case(Latency::Constant(9), Latency::Constant(0), 1)]
+#[case(Latency::Constant(10), Latency::Constant(0), 0)]
+#[case(Latency::Constant(20), Latency::Constant(0), 0)]
+#[case(Latency::Constant(4), Latency::Constant(5), 1)]
+#[case(Latency::Constant(0), Latency::Constant(15), 0)]
+#[case(Latency::Jitter { base: 11, jitter: 5 }, Latency::Constant(0), 0)]
+fn orders_arrive_late(#[case] order: Latency, #[case] feed: Latency, #[case] position: i32) {
+    let ob_path = temp_path(&format!("latency_ob_{position}_{order:?}_{feed:?}.csv"));
+    let orders_path = temp_path(&format!("latency_orders_{position}_{order:?}_{feed:?}.csv"));
+    // The first snapshot only builds the book, quoting starts from the second
+    let snaps = [snap(START), snap(START + 1), snap(START + 30)];
+    write_snaps_csv(&ob_path, &snaps).unwrap();
+    let print = Trade {
+        exch_epoch: START + 10,
+        side: Side::Ask,
+        price: 100,
+        qty: 1,
+        trade_id: None,
+    };
+    write_trades_csv(&orders_path, &[print]).unwrap();
+    let (_, held) = run(
+        LatencyModel::new(order, feed, 0),
+        ob_path.to_str().unwrap(),
+        orders_path.to_str().unwrap(),
+    );
+    std::fs::remove_file(&ob_path).unwrap();
+    std::fs::remove_file(&orders_path).unwrap();
+    assert_eq!(held, position);
+}
+
+#[test]
+fn seeded_latency_replays_the_same() {
+    let ob_path = temp_path("latency_flow_ob.csv");
+    let orders_path = temp_path("latency_flow_orders.csv");
+    let config = GeneratorConfig {
+        duration: 30_000_000_000,
+        ..GeneratorConfig::default()
+    };
+    generate_csv(config, &ob_path, &orders_path).unwrap();
+    let latency = || {
+        LatencyModel::new(
+            Latency::Jitter {
+                base: 2_000_000,
+                jitter: 1_000_000,
+            },
+            Latency::Empirical(vec![100_000, 500_000, 3_000_000]),
+            7,
+        )
+    };
+    let paths = (ob_path.to_str().unwrap(), orders_path.to_str().unwrap());
+    let (metrics, position) = run(latency(), paths.0, paths.1);
+    let (same_metrics, same_position) = run(latency(), paths.0, paths.1);
+    std::fs::remove_file(&ob_path).unwrap();
+    std::fs::remove_file(&orders_path).unwrap();
+    assert_eq!(metrics, same_metrics);
+    assert_eq!(position, same_position);
+}
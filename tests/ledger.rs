@@ -0,0 +1,77 @@
+use orderbook::{
+    backtest::{strategy_flow, FixSpreadStrategy},
+    data::{generate_csv, GeneratorConfig},
+    engine::{
+        indicators::Midprice, LotMatching, Money, OrderBook, PositionLedger, Side, Ticker,
+        TradingAccount,
+    },
+    management::OrderManagementSystem,
+};
+use pretty_assertions::assert_eq;
+use rstest::rstest;
+
+#[rstest]
+#[case(LotMatching::Fifo, 0)]
+#[case(LotMatching::Lifo, 0)]
+// Pnl is counted from the initial balance
+#[case(LotMatching::Fifo, 1_000_000)]
+fn ledger_follows_flow(#[case] matching: LotMatching, #[case] initial_balance: i64) {
+    let dir = std::env::temp_dir();
+    let name = format!(
+        "orderbook_{}_ledger_{matching:?}_{initial_balance}",
+        std::process::id()
+    );
+    let ob_path = dir.join(format!("{name}_ob.csv"));
+    let orders_path = dir.join(format!("{name}_orders.csv"));
+    let config = GeneratorConfig {
+        duration: 30_000_000_000,
+        ..GeneratorConfig::default()
+    };
+    generate_csv(config, &ob_path, &orders_path).unwrap();
+    let mut strat = FixSpreadStrategy::new(Ticker::default());
+    strat.buy_criterion = -0.0001;
+    strat.sell_criterion = 0.0001;
+    strat.buy_position_limit = 100;
+    strat.sell_position_limit = -100;
+    strat.qty = 10;
+    let account = TradingAccount {
+        ledger: PositionLedger::new(matching),
+        ..TradingAccount::new(Money::from_units(initial_balance))
+    };
+    let mut oms = OrderManagementSystem::new(&mut strat, account);
+    let mut ob = OrderBook::new();
+    let metrics = strategy_flow(
+        &mut oms,
+        &mut ob,
+        ob_path.to_str().unwrap(),
+        orders_path.to_str().unwrap(),
+    );
+    std::fs::remove_file(&ob_path).unwrap();
+    std::fs::remove_file(&orders_path).unwrap();
+
+    let ticker = &oms.strategy.ticker;
+    let ledger = &oms.account.ledger;
+    assert!(!ledger.entries().is_empty());
+    assert_eq!(ledger.entries().len(), oms.account.fills.len());
+    assert_eq!(ledger.qty(ticker.ticker_id), oms.position());
+
+    // Realized and unrealized add up to the cash based pnl whatever the matching
+    let m = Midprice::evaluate(&ob).unwrap();
+    let position = ledger.position(ticker.ticker_id).unwrap();
+    assert_eq!(position.pnl(m), oms.account.pnl(oms.position(), m));
+    assert_eq!(position.pnl(m).cash(ticker.unit_cash()), metrics.pnl_abs);
+
+    // Replaying history up to a fill gives the position right after it
+    let entries = ledger.entries();
+    let mid = entries[entries.len() / 2];
+    let expected: i32 = entries
+        .iter()
+        .filter(|entry| entry.epoch <= mid.epoch)
+        .map(|entry| match entry.side {
+            | Side::Bid => entry.qty as i32,
+            | Side::Ask => -(entry.qty as i32),
+        })
+        .sum();
+    assert_eq!(ledger.at(mid.epoch).qty(ticker.ticker_id), expected);
+    assert!(entries.windows(2).all(|w| w[0].epoch <= w[1].epoch));
+}
@@ -0,0 +1,105 @@
+use orderbook::{
+    backtest::{strategy_flow, FixSpreadStrategy},
+    data::{generate_csv, GeneratorConfig},
+    engine::{Money, Order, OrderBook, Side, Ticker, Trade, TradingAccount},
+    management::{OrderManagementSystem, OrderState},
+};
+use pretty_assertions::assert_eq;
+use std::collections::BTreeSet;
+
+fn bid(id: u64, price: u32, qty: u32) -> Order {
+    Order {
+        id,
+        side: Side::Bid,
+        price,
+        qty,
+    }
+}
+
+#[test]
+fn engine_responses_drive_states() {
+    let mut strat = FixSpreadStrategy::new(Ticker::default());
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    let mut ob = OrderBook::new();
+    let _ = ob.add_limit_order(Order {
+        id: 1,
+        side: Side::Ask,
+        price: 101,
+        qty: 5,
+    });
+    // Crossing rung is rejected, resting one acknowledged
+    oms.place_ladder(&mut ob, &[bid(10, 99, 4), bid(11, 101, 1)]);
+    assert_eq!(oms.orders.state(10), Some(OrderState::New));
+    assert_eq!(oms.orders.state(11), Some(OrderState::Rejected));
+
+    let print = |qty| Trade {
+        exch_epoch: 1,
+        side: Side::Ask,
+        price: 99,
+        qty,
+        trade_id: None,
+    };
+    let _ = oms.apply_trade(&mut ob, &print(1));
+    assert_eq!(oms.orders.state(10), Some(OrderState::PartiallyFilled));
+    assert_eq!(oms.orders.get(10).unwrap().leaves_qty(), 3);
+
+    // Replace cancels the old rung and sends a new one
+    let _ = oms.replace(&mut ob, 10, bid(12, 98, 3)).unwrap();
+    assert_eq!(oms.orders.state(10), Some(OrderState::Cancelled));
+    assert_eq!(oms.orders.state(12), Some(OrderState::New));
+
+    // Nothing left to cancel: the cancel is rejected and the order keeps working
+    let _ = ob.cancel_order(12);
+    assert!(oms.cancel(&mut ob, 12).is_err());
+    assert_eq!(oms.orders.state(12), Some(OrderState::New));
+
+    // Taker fill on arrival
+    let _ = oms.submit(&mut ob, bid(13, 101, 5));
+    assert_eq!(oms.orders.state(13), Some(OrderState::Filled));
+}
+
+#[test]
+fn open_orders_match_the_book() {
+    let dir = std::env::temp_dir();
+    let ob_path = dir.join(format!("orderbook_{}_lifecycle_ob.csv", std::process::id()));
+    let orders_path = dir.join(format!(
+        "orderbook_{}_lifecycle_orders.csv",
+        std::process::id()
+    ));
+    let config = GeneratorConfig {
+        duration: 10_000_000_000,
+        ..GeneratorConfig::default()
+    };
+    generate_csv(config, &ob_path, &orders_path).unwrap();
+    let mut strat = FixSpreadStrategy::new(Ticker::default());
+    strat.buy_criterion = -0.0001;
+    strat.sell_criterion = 0.0001;
+    strat.buy_position_limit = 100;
+    strat.sell_position_limit = -100;
+    strat.qty = 10;
+    strat.ladder_depth = 2;
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    let mut ob = OrderBook::new();
+    let _ = strategy_flow(
+        &mut oms,
+        &mut ob,
+        ob_path.to_str().unwrap(),
+        orders_path.to_str().unwrap(),
+    );
+    std::fs::remove_file(&ob_path).unwrap();
+    std::fs::remove_file(&orders_path).unwrap();
+    let open = oms
+        .orders
+        .open_orders()
+        .map(|record| record.order.id)
+        .collect::<BTreeSet<_>>();
+    let own = oms.own_order_ids().into_iter().collect::<BTreeSet<_>>();
+    assert!(!own.is_empty());
+    assert_eq!(open, own);
+    for record in oms.orders.open_orders() {
+        let resting = ob.get_order(record.order.id).unwrap();
+        assert_eq!(resting.qty, record.leaves_qty());
+    }
+    // Replaced and filled quotes stay on record
+    assert!(oms.orders.len() > own.len());
+}
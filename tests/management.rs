@@ -3,10 +3,11 @@ use common::{empty_ob, full_ob};
 use orderbook::{
     backtest::FixSpreadStrategy,
     engine::indicators::Midprice,
+    engine::Money,
     engine::Ticker,
     engine::TradingAccount,
     engine::{Order, OrderBook, Side},
-    management::OrderManagementSystem,
+    management::{OrderManagementSystem, QuoteId},
 };
 use pretty_assertions::assert_eq;
 use rstest::rstest;
@@ -21,7 +22,7 @@ fn calculate_order_test(
     #[case] expected: Result<Order, String>,
 ) {
     let mut strat = FixSpreadStrategy::new(Ticker::default());
-    let account = TradingAccount::new(0.0);
+    let account = TradingAccount::new(Money::ZERO);
     strat.buy_criterion = -0.01;
     strat.sell_criterion = 0.01;
     strat.buy_position_limit = 10;
@@ -54,11 +55,11 @@ fn update_test(
     #[case] mut ob: OrderBook,
     #[case] side: Side,
     #[case] expected_count: u32,
-    #[case] expected_volume: u32,
+    #[case] expected_volume: u64,
     #[case] expected_order: Option<Order>,
 ) {
     let mut strat = FixSpreadStrategy::new(Ticker::default());
-    let account = TradingAccount::new(0.0);
+    let account = TradingAccount::new(Money::ZERO);
     strat.buy_criterion = -0.01;
     strat.sell_criterion = 0.01;
     strat.buy_position_limit = 10;
@@ -127,10 +128,10 @@ fn send_orders_test(
     strat.buy_position_limit = 10;
     strat.sell_position_limit = -10;
     strat.qty = 1;
-    let buy_id = Some(333);
-    let sell_id = Some(777);
-    let account = TradingAccount::new(0.0);
+    let buy_id = Some(QuoteId::Given(333));
+    let sell_id = Some(QuoteId::Given(777));
+    let account = TradingAccount::new(Money::ZERO);
     let mut oms = OrderManagementSystem::new(&mut strat, account);
     oms.send_orders(&mut ob, m, buy_id, sell_id);
-    assert_eq!(exp_position, oms.strategy.master_position);
+    assert_eq!(exp_position, oms.position());
 }
@@ -0,0 +1,97 @@
+mod common;
+use common::full_ob;
+use orderbook::{
+    backtest::FixSpreadStrategy,
+    engine::indicators::Midprice,
+    engine::{
+        hm, FeeSchedule, Liquidity, Money, RiskRejection, Side, Ticker, TradingAccount, MOEX_FORTS,
+        NANOS_IN_DAY,
+    },
+    management::{OrderManagementSystem, QuoteId},
+};
+use pretty_assertions::assert_eq;
+
+// 2023-11-01 00:00 MSK
+const MIDNIGHT: u64 = 1_698_786_000_000_000_000;
+
+const FUTURE: Ticker = Ticker {
+    ticker_id: 7,
+    tick_size: 1.0,
+    step_price: 1.0,
+    fees: FeeSchedule::NONE,
+    calendar: MOEX_FORTS,
+    initial_margin: Money::from_units(100),
+};
+
+#[test]
+fn quotes_over_free_margin_are_rejected() {
+    let mut strat = FixSpreadStrategy::new(FUTURE);
+    strat.buy_criterion = -0.01;
+    strat.sell_criterion = 0.01;
+    strat.buy_position_limit = 10;
+    strat.sell_position_limit = -10;
+    strat.qty = 5;
+    let mut oms =
+        OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::from_units(400)));
+    let mut ob = full_ob();
+    let m = Midprice::evaluate(&ob);
+    oms.send_orders(&mut ob, m, Some(QuoteId::Given(1)), Some(QuoteId::Given(2)));
+    assert_eq!((oms.active_buy_order, oms.active_sell_order), (None, None));
+    let rejection = RiskRejection::Margin {
+        required: Money::from_units(500),
+        equity: Money::from_units(400),
+    };
+    assert_eq!(oms.risk.rejections, vec![(1, rejection), (2, rejection)]);
+
+    // Either side alone fits, the bid and the ask are never both filled
+    oms.account.collateral = Money::from_units(500);
+    oms.send_orders(&mut ob, m, Some(QuoteId::Given(3)), Some(QuoteId::Given(4)));
+    assert_eq!(oms.active_buy_order.map(|order| order.id), Some(3));
+    assert_eq!(oms.active_sell_order.map(|order| order.id), Some(4));
+}
+
+#[test]
+fn variation_margin_moves_into_collateral() {
+    let mut account = TradingAccount::new(Money::from_units(1_000));
+    account.record_fill(&FUTURE, Side::Bid, 3, 95, Liquidity::Taker);
+    assert_eq!(account.blocked_margin(&FUTURE), Money::from_units(300));
+    assert_eq!(account.equity(7, 100.0), Money::from_units(1_015));
+    assert_eq!(
+        account.free_margin(&FUTURE, 100.0),
+        Money::from_units(1_015 - 300)
+    );
+    // 300 of 1015
+    let utilisation = account.margin_utilisation(&FUTURE, 100.0);
+    assert!((0.295..0.296).contains(&utilisation));
+
+    let mut strat = FixSpreadStrategy::new(FUTURE);
+    let mut oms = OrderManagementSystem::new(&mut strat, account);
+    let ob = full_ob();
+    // The first clearing seen is only remembered
+    assert_eq!(oms.settle_clearings(&ob, MIDNIGHT + hm(12, 0)), None);
+    assert_eq!(oms.settle_clearings(&ob, MIDNIGHT + hm(18, 49)), None);
+    // Settled at the mid of 100 after the evening clearing
+    assert_eq!(
+        oms.settle_clearings(&ob, MIDNIGHT + hm(19, 5)),
+        Some(Money::from_units(15))
+    );
+    assert_eq!(oms.account.collateral, Money::from_units(1_015));
+    assert_eq!(oms.account.equity(7, 100.0), Money::from_units(1_015));
+    let settlement = oms.account.ledger.settlements()[0];
+    assert_eq!(
+        (settlement.epoch, settlement.price),
+        (MIDNIGHT + hm(19, 5), 100)
+    );
+    assert_eq!(oms.settle_clearings(&ob, MIDNIGHT + hm(23, 0)), None);
+
+    // Selling at 104 next day realizes only the move since the clearing
+    oms.account
+        .record_fill(&FUTURE, Side::Ask, 3, 104, Liquidity::Taker);
+    assert_eq!(oms.account.collateral, Money::from_units(1_027));
+    assert_eq!(oms.account.blocked_margin(&FUTURE), Money::ZERO);
+    assert_eq!(
+        oms.settle_clearings(&ob, MIDNIGHT + NANOS_IN_DAY + hm(19, 0)),
+        Some(Money::ZERO)
+    );
+    assert_eq!(oms.account.ledger.settlements().len(), 1);
+}
@@ -0,0 +1,131 @@
+use orderbook::{
+    data::{EventKind, EventMerger, MarketEvent, StreamEnd, TieBreak, Timestamped},
+    engine::{Side, Signal, Snap, Trade},
+    error::MyError,
+};
+use pretty_assertions::assert_eq;
+use rstest::rstest;
+
+fn snaps(epochs: &[u64]) -> Vec<Result<Snap, MyError>> {
+    epochs
+        .iter()
+        .map(|&exch_epoch| {
+            Ok(Snap {
+                exch_epoch,
+                vec: vec![],
+            })
+        })
+        .collect()
+}
+
+fn trades(epochs: &[u64]) -> Vec<Result<Trade, MyError>> {
+    epochs
+        .iter()
+        .map(|&exch_epoch| {
+            Ok(Trade {
+                exch_epoch,
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+fn signals(epochs: &[u64]) -> Vec<Result<Signal, MyError>> {
+    epochs
+        .iter()
+        .map(|&exch_epoch| {
+            Ok(Signal {
+                exch_epoch,
+                side: Side::Bid,
+                value: 1.0,
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+fn collect(merger: EventMerger) -> Vec<(u64, EventKind)> {
+    merger
+        .map(|event| {
+            let event = event.unwrap();
+            (event.exch_epoch(), event.kind())
+        })
+        .collect()
+}
+
+#[test]
+fn merge_in_epoch_order() {
+    let merger = EventMerger::default()
+        .with_stream(snaps(&[10, 30, 50]).into_iter(), StreamEnd::Continue)
+        .with_stream(trades(&[5, 20, 40, 60]).into_iter(), StreamEnd::Continue)
+        .with_stream(signals(&[25]).into_iter(), StreamEnd::Continue);
+    assert_eq!(
+        collect(merger),
+        vec![
+            (5, EventKind::Trade),
+            (10, EventKind::Snap),
+            (20, EventKind::Trade),
+            (25, EventKind::Signal),
+            (30, EventKind::Snap),
+            (40, EventKind::Trade),
+            (50, EventKind::Snap),
+            (60, EventKind::Trade),
+        ]
+    );
+}
+
+#[rstest]
+#[case(
+    TieBreak::default(),
+    vec![EventKind::Trade, EventKind::Signal, EventKind::Snap]
+)]
+#[case(
+    TieBreak::Kind([EventKind::Snap, EventKind::Trade, EventKind::Signal]),
+    vec![EventKind::Snap, EventKind::Trade, EventKind::Signal]
+)]
+#[case(
+    TieBreak::StreamOrder,
+    vec![EventKind::Signal, EventKind::Snap, EventKind::Trade]
+)]
+fn same_epoch_order(#[case] tie_break: TieBreak, #[case] expected: Vec<EventKind>) {
+    let merger = EventMerger::new(tie_break)
+        .with_stream(signals(&[10]).into_iter(), StreamEnd::Continue)
+        .with_stream(snaps(&[10]).into_iter(), StreamEnd::Continue)
+        .with_stream(trades(&[10]).into_iter(), StreamEnd::Continue);
+    let kinds = collect(merger)
+        .into_iter()
+        .map(|(_, kind)| kind)
+        .collect::<Vec<_>>();
+    assert_eq!(kinds, expected);
+}
+
+#[rstest]
+#[case(StreamEnd::Stop, vec![1, 2, 3])]
+#[case(StreamEnd::Continue, vec![1, 2, 3, 4, 5])]
+fn stream_exhausted(#[case] end: StreamEnd, #[case] expected: Vec<u64>) {
+    let merger = EventMerger::default()
+        .with_stream(trades(&[1, 3]).into_iter(), end)
+        .with_stream(snaps(&[2, 4, 5]).into_iter(), StreamEnd::Continue);
+    let epochs = collect(merger)
+        .into_iter()
+        .map(|(epoch, _)| epoch)
+        .collect::<Vec<_>>();
+    assert_eq!(epochs, expected);
+}
+
+#[test]
+fn errors_are_passed_through() {
+    let mut broken = trades(&[1]);
+    broken.push(Err(MyError::FormatError("broken record".to_string())));
+    broken.extend(trades(&[7]));
+    let mut merger = EventMerger::default()
+        .with_stream(broken.into_iter(), StreamEnd::Continue)
+        .with_stream(snaps(&[2, 3]).into_iter(), StreamEnd::Continue);
+    assert!(matches!(merger.next(), Some(Ok(MarketEvent::Trade(_)))));
+    assert!(matches!(merger.next(), Some(Err(MyError::FormatError(_)))));
+    // Broken stream is not read any further
+    let epochs = merger
+        .map(|event| event.unwrap().exch_epoch())
+        .collect::<Vec<_>>();
+    assert_eq!(epochs, vec![2, 3]);
+}
@@ -0,0 +1,105 @@
+use orderbook::{
+    backtest::{strategy_flow, FixSpreadStrategy},
+    data::{generate_csv, GeneratorConfig},
+    engine::{Money, Order, OrderBook, Side, Ticker, TradingAccount},
+    management::{OrderManagementSystem, OrderMeta, OrderTag, CLIENT_ID_BASE},
+};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn ids_carry_meta() {
+    let mut strat = FixSpreadStrategy::new(Ticker::default());
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    let first = oms.new_order_id(1_000, Side::Bid, OrderTag::Quote);
+    assert_eq!(first, CLIENT_ID_BASE);
+    oms.active_buy_order = Some(Order {
+        id: first,
+        side: Side::Bid,
+        price: 99,
+        qty: 1,
+    });
+    // Amend of the resting quote and a rung decided at the same epoch
+    let amend = oms.new_order_id(5_000, Side::Bid, OrderTag::Quote);
+    let rung = oms.new_order_id(5_000, Side::Bid, OrderTag::Ladder);
+    assert_eq!(oms.ids.len(), 3);
+    assert_eq!(
+        oms.ids.get(amend),
+        Some(&OrderMeta {
+            created_epoch: 5_000,
+            side: Side::Bid,
+            tag: OrderTag::Quote,
+            parent: Some(first),
+        })
+    );
+    assert_eq!(oms.ids.get(rung).unwrap().parent, None);
+    assert_eq!(oms.ids.age(first, 5_000), Some(4_000));
+    assert_eq!(oms.ids.age(7, 5_000), None);
+    assert!(!oms.ids.is_own(7));
+}
+
+#[test]
+fn ids_only_for_sent_orders() {
+    let mut strat = FixSpreadStrategy::new(Ticker::default());
+    strat.buy_criterion = -0.01;
+    strat.sell_criterion = 0.01;
+    strat.buy_position_limit = 10;
+    strat.sell_position_limit = -10;
+    strat.qty = 1;
+    strat.ladder_depth = 1;
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    let mut ob = OrderBook::new();
+    for (id, side, price) in [(1, Side::Bid, 990), (2, Side::Ask, 1010)] {
+        let _ = ob.add_limit_order(Order {
+            id,
+            side,
+            price,
+            qty: 10,
+        });
+    }
+    let seen = ob.clone();
+    oms.requote(&mut ob, &seen, 1_000);
+    assert_eq!(oms.ids.len(), 4);
+    // Nothing moved, nothing is sent and no id is taken
+    oms.requote(&mut ob, &seen, 2_000);
+    assert_eq!(oms.ids.len(), 4);
+    for id in oms.own_order_ids() {
+        assert_eq!(oms.ids.get(id).unwrap().created_epoch, 1_000);
+    }
+}
+
+#[test]
+fn flow_orders_are_own() {
+    let dir = std::env::temp_dir();
+    let ob_path = dir.join(format!("orderbook_{}_ids_ob.csv", std::process::id()));
+    let orders_path = dir.join(format!("orderbook_{}_ids_orders.csv", std::process::id()));
+    let config = GeneratorConfig {
+        duration: 10_000_000_000,
+        ..GeneratorConfig::default()
+    };
+    generate_csv(config.clone(), &ob_path, &orders_path).unwrap();
+    let mut strat = FixSpreadStrategy::new(Ticker::default());
+    strat.buy_criterion = -0.0001;
+    strat.sell_criterion = 0.0001;
+    strat.buy_position_limit = 100;
+    strat.sell_position_limit = -100;
+    strat.qty = 10;
+    strat.ladder_depth = 2;
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    let mut ob = OrderBook::new();
+    let _ = strategy_flow(
+        &mut oms,
+        &mut ob,
+        ob_path.to_str().unwrap(),
+        orders_path.to_str().unwrap(),
+    );
+    std::fs::remove_file(&ob_path).unwrap();
+    std::fs::remove_file(&orders_path).unwrap();
+    assert!(!oms.ids.is_empty());
+    let own_ids = oms.own_order_ids();
+    assert!(!own_ids.is_empty());
+    for id in own_ids {
+        let meta = oms.ids.get(id).unwrap();
+        assert_eq!(Some(meta.side), ob.get_order(id).map(|order| order.side));
+        assert!(meta.created_epoch >= config.start_epoch);
+    }
+}
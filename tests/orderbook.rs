@@ -1,6 +1,6 @@
 mod common;
 use common::{empty_ob, full_ob, taker_buy_order};
-use orderbook::engine::{ExecutionReport, Order, OrderBook, OrderStatus, Side};
+use orderbook::engine::{ExecutionReport, Order, OrderBook, OrderStatus, Side, Trade};
 use pretty_assertions::assert_eq;
 use rstest::rstest;
 
@@ -37,3 +37,25 @@ fn add_limit_order_test(
 ) {
     assert_eq!(ob.add_limit_order(order), expected);
 }
+
+#[rstest]
+#[case(full_ob(), Trade {exch_epoch: 1, price: 101, qty: 5, side: Side::Bid, trade_id: None}, 0, None)]
+#[case(full_ob(), Trade {exch_epoch: 1, price: 101, qty: 15, side: Side::Bid, trade_id: None}, 5, Some(10))]
+#[case(empty_ob(), Trade {exch_epoch: 1, price: 99, qty: 5, side: Side::Ask, trade_id: Some(7)}, 5, Some(0))]
+fn apply_trade_test(
+    #[case] mut ob: OrderBook,
+    #[case] trade: Trade,
+    #[case] expected_unmatched: u32,
+    #[case] expected_matched: Option<u32>,
+) {
+    let (exec_report, mismatch) = ob.apply_trade(&trade);
+    assert_eq!(exec_report.remaining_qty, expected_unmatched);
+    assert_eq!(mismatch.map(|m| m.matched_qty), expected_matched);
+    // Prints never rest in the book
+    assert_eq!(ob.get_order(trade.trade_id.unwrap_or_default()), None);
+    let own_side_best = match trade.side {
+        | Side::Bid => ob.best_bid_price,
+        | Side::Ask => ob.best_offer_price,
+    };
+    assert_ne!(own_side_best, Some(trade.price));
+}
@@ -0,0 +1,111 @@
+use orderbook::{
+    backtest::{FixPriceStrategy, FixSpreadStrategy},
+    data::{generate_csv, CleaningPolicy, GeneratorConfig},
+    engine::{
+        place_body, LimitOrder, Money, Order, OrderBook, QueueModel, Side, Snap, Ticker,
+        TradingAccount,
+    },
+    experiments::execution_flow,
+    management::OrderManagementSystem,
+};
+use pretty_assertions::assert_eq;
+use rstest::rstest;
+
+const OWN_ID: u64 = 100;
+
+fn snap(bid_qty: u32) -> Snap {
+    Snap {
+        exch_epoch: 0,
+        vec: vec![
+            LimitOrder {
+                side: Side::Bid,
+                price: 99,
+                qty: bid_qty,
+            },
+            LimitOrder {
+                side: Side::Ask,
+                price: 101,
+                qty: 10,
+            },
+        ],
+    }
+}
+
+// 10 lots ahead of our order and 5 behind, the level shrinks by 7
+#[rstest]
+#[case(QueueModel::Pessimistic, (8, 0))]
+#[case(QueueModel::Optimistic, (3, 5))]
+#[case(QueueModel::Proportional, (6, 2))]
+fn queue_after_cancels(#[case] queue_model: QueueModel, #[case] expected: (u32, u32)) {
+    let mut strat = FixSpreadStrategy::new(Ticker::default());
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    oms.queue_model = queue_model;
+    let own = Order {
+        id: OWN_ID,
+        side: Side::Bid,
+        price: 99,
+        qty: 1,
+    };
+    let mut ob = OrderBook::new();
+    for order in [
+        Order {
+            id: 1,
+            qty: 10,
+            ..own
+        },
+        own,
+        Order {
+            id: 2,
+            qty: 5,
+            ..own
+        },
+    ] {
+        let _ = ob.add_limit_order(order);
+    }
+    oms.active_buy_order = Some(own);
+
+    let ob = ob.process(snap(8), &mut oms, place_body(false));
+    let (_, price, qty_head, qty, qty_tail, id) = ob.get_offset(&oms, Side::Bid).unwrap();
+    assert_eq!((price, qty, id), (99, 1, OWN_ID));
+    assert_eq!((qty_head, qty_tail), expected);
+}
+
+#[test]
+fn fill_stats_per_model() {
+    let dir = std::env::temp_dir();
+    let ob_path = dir.join(format!("orderbook_{}_queue_ob.csv", std::process::id()));
+    let orders_path = dir.join(format!("orderbook_{}_queue_orders.csv", std::process::id()));
+    let config = GeneratorConfig {
+        duration: 60_000_000_000,
+        ..GeneratorConfig::default()
+    };
+    generate_csv(config, &ob_path, &orders_path).unwrap();
+    let models = [
+        QueueModel::Pessimistic,
+        QueueModel::Optimistic,
+        QueueModel::Proportional,
+        QueueModel::probabilistic(42),
+    ];
+    for queue_model in models {
+        let name = queue_model.name();
+        let mut strat = FixPriceStrategy {
+            buy_tick_criterion: Some(-2),
+            qty: 1,
+            ..Default::default()
+        };
+        let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+        oms.queue_model = queue_model;
+        let stats = execution_flow(
+            &mut oms,
+            &mut OrderBook::new(),
+            ob_path.to_str().unwrap(),
+            orders_path.to_str().unwrap(),
+            CleaningPolicy::default(),
+        );
+        assert_eq!(stats.queue_model, name);
+        assert!(stats.filled + stats.censored > 0);
+        assert!((0.0..=1.0).contains(&stats.fill_probability().unwrap()));
+    }
+    std::fs::remove_file(&ob_path).unwrap();
+    std::fs::remove_file(&orders_path).unwrap();
+}
@@ -0,0 +1,172 @@
+mod common;
+use common::full_ob;
+use orderbook::{
+    backtest::{FixSpreadStrategy, InventorySkew, Strategy},
+    engine::indicators::Midprice,
+    engine::{Liquidity, Money, Order, OrderBook, Side, Ticker, Trade, TradingAccount},
+    management::{OrderManagementSystem, QuoteId, QuotingPolicy},
+};
+use pretty_assertions::assert_eq;
+use rstest::rstest;
+
+// Strategy written outside the crate, quoting comes from the OMS
+struct Symmetric {
+    ticker: Ticker,
+    offset: f32,
+}
+
+impl Strategy for Symmetric {
+    fn ticker(&self) -> &Ticker {
+        &self.ticker
+    }
+}
+
+impl QuotingPolicy for Symmetric {
+    fn qty(&self) -> u32 {
+        5
+    }
+    fn position_limits(&self) -> (i32, i32) {
+        (5, -10)
+    }
+    fn offsets(&self, _position: i32) -> (f32, f32) {
+        (-self.offset, self.offset)
+    }
+    fn maker_range(&self) -> (f32, f32) {
+        (-f32::INFINITY, f32::INFINITY)
+    }
+    fn taker_range(&self) -> (f32, f32) {
+        (0.0, f32::INFINITY)
+    }
+}
+
+fn order(id: u64, side: Side, price: u32, qty: u32) -> Order {
+    Order {
+        id,
+        side,
+        price,
+        qty,
+    }
+}
+
+#[test]
+fn custom_strategy_quotes() {
+    let mut strat = Symmetric {
+        ticker: Ticker::default(),
+        offset: 0.01,
+    };
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    let mut ob = full_ob();
+    let m = Midprice::evaluate(&ob);
+    let (buy, sell) = oms.send_quotes(&mut ob, m, Some(QuoteId::Given(1)), Some(QuoteId::Given(2)));
+    assert_eq!(buy.unwrap().remaining_qty, 5);
+    assert_eq!(sell.unwrap().remaining_qty, 5);
+    assert_eq!(oms.active_buy_order, Some(order(1, Side::Bid, 99, 5)));
+    assert_eq!(oms.active_sell_order, Some(order(2, Side::Ask, 101, 5)));
+
+    // A sell print through 99 fills the bid behind the resting 10 lots
+    let (exec_report, _) = oms.apply_trade(
+        &mut ob,
+        &Trade {
+            exch_epoch: 1,
+            side: Side::Ask,
+            price: 99,
+            qty: 15,
+            trade_id: None,
+        },
+    );
+    oms.update(&exec_report);
+    assert_eq!(oms.position(), 5);
+    assert_eq!(oms.active_buy_order, None);
+    assert_eq!(oms.account.trade_count, 1);
+
+    // Buy limit is used up, the resting ask is kept
+    let (buy, sell) = oms.send_quotes(&mut ob, m, Some(QuoteId::Given(3)), Some(QuoteId::Given(4)));
+    assert_eq!((buy, sell), (None, None));
+    assert_eq!(oms.active_sell_order, Some(order(2, Side::Ask, 101, 5)));
+}
+
+#[test]
+fn quotes_through_the_book_take() {
+    let mut strat = Symmetric {
+        ticker: Ticker::default(),
+        offset: -0.02,
+    };
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    let mut ob = full_ob();
+    let m = Midprice::evaluate(&ob);
+    // Bid at 102 crosses the ask at 101, ask at 98 sweeps the bid at 99
+    let (buy, sell) = oms.send_quotes(&mut ob, m, Some(QuoteId::Given(1)), Some(QuoteId::Given(2)));
+    assert_eq!(buy.unwrap().filled_orders, vec![(999, 5, 101)]);
+    assert_eq!(sell.unwrap().filled_orders, vec![(666, 5, 99)]);
+    assert_eq!(oms.position(), 0);
+    assert_eq!(oms.account.trade_count, 2);
+    // Bought at 101, sold at 99
+    assert_eq!(oms.account.balance, Money::from_units(-10));
+}
+
+#[rstest]
+// Half the long limit: both quotes 5 bps lower, the bid loses a quarter
+#[case(InventorySkew::Linear { max_shift: 0.001 }, 5, (9985, 3), (10006, 4))]
+#[case(InventorySkew::Linear { max_shift: 0.001 }, -5, (9995, 4), (10016, 3))]
+// (e - 1) / (e^2 - 1) of the shift at half the limit
+#[case(InventorySkew::Exponential { max_shift: 0.001, rate: 2.0 }, 5, (9987, 3), (10008, 4))]
+// No rate is the linear skew
+#[case(InventorySkew::Exponential { max_shift: 0.001, rate: 0.0 }, 5, (9985, 3), (10006, 4))]
+#[case(InventorySkew::None, 5, (9990, 3), (10011, 4))]
+fn inventory_skew(
+    #[case] skew: InventorySkew,
+    #[case] position: i32,
+    #[case] bid: (u32, u32),
+    #[case] ask: (u32, u32),
+) {
+    let mut strat = FixSpreadStrategy::new(Ticker::default());
+    strat.buy_criterion = -0.001;
+    strat.sell_criterion = 0.001;
+    strat.buy_position_limit = 10;
+    strat.sell_position_limit = -10;
+    strat.qty = 4;
+    strat.skew = skew;
+    strat.size_taper = 0.5;
+    let mut account = TradingAccount::new(Money::ZERO);
+    let side = if position > 0 { Side::Bid } else { Side::Ask };
+    let _ = account.record_fill(
+        &Ticker::default(),
+        side,
+        position.unsigned_abs(),
+        10000,
+        Liquidity::Maker,
+    );
+    let oms = OrderManagementSystem::new(&mut strat, account);
+    let m = Some(10000.5);
+    let buy = oms.calculate_buy_order(m, Some(1)).unwrap();
+    let sell = oms.calculate_sell_order(m, Some(2)).unwrap();
+    assert_eq!(((buy.price, buy.qty), (sell.price, sell.qty)), (bid, ask));
+}
+
+#[test]
+fn requote_leans_after_fill() {
+    let mut strat = FixSpreadStrategy::new(Ticker::default());
+    strat.buy_criterion = -0.001;
+    strat.sell_criterion = 0.001;
+    strat.buy_position_limit = 10;
+    strat.sell_position_limit = -10;
+    strat.qty = 4;
+    strat.skew = InventorySkew::Linear { max_shift: 0.001 };
+    strat.size_taper = 0.5;
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    let mut ob = OrderBook::new();
+    let _ = ob.add_limit_order(order(100, Side::Bid, 9900, 10));
+    let _ = ob.add_limit_order(order(101, Side::Ask, 10101, 10));
+    let m = Midprice::evaluate(&ob);
+    oms.send_orders(&mut ob, m, Some(QuoteId::Given(1)), Some(QuoteId::Given(2)));
+    assert_eq!(oms.active_buy_order, Some(order(1, Side::Bid, 9990, 4)));
+    assert_eq!(oms.active_sell_order, Some(order(2, Side::Ask, 10011, 4)));
+
+    // Our bid is hit, the next quotes lean down and the bid shrinks
+    let exec_report = ob.add_limit_order(order(102, Side::Ask, 9990, 4));
+    oms.update(&exec_report);
+    assert_eq!(oms.position(), 4);
+    oms.send_orders(&mut ob, m, Some(QuoteId::Given(3)), Some(QuoteId::Given(4)));
+    assert_eq!(oms.active_buy_order, Some(order(3, Side::Bid, 9986, 3)));
+    assert_eq!(oms.active_sell_order, Some(order(4, Side::Ask, 10007, 4)));
+}
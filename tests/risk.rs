@@ -0,0 +1,138 @@
+mod common;
+use common::full_ob;
+use orderbook::{
+    backtest::{strategy_flow, FixSpreadStrategy},
+    data::{generate_csv, GeneratorConfig},
+    engine::indicators::Midprice,
+    engine::{
+        KillReason, Liquidity, Money, OrderBook, RiskLimits, RiskRejection, Side, Ticker,
+        TradingAccount,
+    },
+    management::{OrderManagementSystem, OrderState, QuoteId},
+};
+use pretty_assertions::assert_eq;
+use rstest::rstest;
+
+#[test]
+fn quotes_over_limits_are_rejected() {
+    let mut strat = FixSpreadStrategy::new(Ticker::default());
+    strat.buy_criterion = -0.01;
+    strat.sell_criterion = 0.01;
+    strat.buy_position_limit = 10;
+    strat.sell_position_limit = -10;
+    strat.qty = 5;
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    oms.risk.limits = RiskLimits {
+        max_order_qty: Some(4),
+        ..RiskLimits::default()
+    };
+    let mut ob = full_ob();
+    let m = Midprice::evaluate(&ob);
+    oms.send_orders(&mut ob, m, Some(QuoteId::Given(1)), Some(QuoteId::Given(2)));
+    assert_eq!((oms.active_buy_order, oms.active_sell_order), (None, None));
+    assert!(oms.own_order_ids().is_empty());
+    assert_eq!(
+        oms.risk.rejections,
+        vec![
+            (1, RiskRejection::MaxOrderQty { qty: 5, limit: 4 }),
+            (2, RiskRejection::MaxOrderQty { qty: 5, limit: 4 })
+        ]
+    );
+    assert_eq!(oms.orders.state(1), Some(OrderState::Rejected));
+
+    // Resting bid counts against the position limit of the next one
+    oms.risk.limits = RiskLimits {
+        position_limits: Some((8, -8)),
+        ..RiskLimits::default()
+    };
+    oms.send_orders(&mut ob, m, Some(QuoteId::Given(3)), None);
+    assert_eq!(oms.active_buy_order.map(|order| order.id), Some(3));
+    oms.strategy.buy_criterion = -0.02;
+    oms.send_orders(&mut ob, m, Some(QuoteId::Given(4)), None);
+    // The amend cancels 3 first, so 4 alone is checked
+    assert_eq!(oms.active_buy_order.map(|order| order.id), Some(4));
+    assert_eq!(oms.orders.state(3), Some(OrderState::Cancelled));
+}
+
+#[test]
+fn exposure_is_per_ticker() {
+    let mut strat = FixSpreadStrategy::new(Ticker::default());
+    let mut account = TradingAccount::new(Money::ZERO);
+    // Long in another instrument of the same account
+    let other = Ticker {
+        ticker_id: 1,
+        ..Ticker::default()
+    };
+    let _ = account.record_fill(&other, Side::Bid, 7, 100, Liquidity::Taker);
+    let _ = account.record_fill(&Ticker::default(), Side::Ask, 2, 100, Liquidity::Taker);
+    let oms = OrderManagementSystem::new(&mut strat, account);
+    assert_eq!(oms.exposure().position, -2);
+}
+
+#[rstest]
+// Bought 10 at 110 with mid at 100
+#[case(0, 50, Some(KillReason::MaxLoss { pnl: Money::from_units(-100) }))]
+#[case(1_000_000, 50, Some(KillReason::MaxLoss { pnl: Money::from_units(-100) }))]
+#[case(1_000_000, 200, None)]
+fn max_loss_counts_from_initial_balance(
+    #[case] initial_balance: i64,
+    #[case] max_loss: i64,
+    #[case] expected: Option<KillReason>,
+) {
+    let mut strat = FixSpreadStrategy::new(Ticker::default());
+    let mut account = TradingAccount::new(Money::from_units(initial_balance));
+    let _ = account.record_fill(&Ticker::default(), Side::Bid, 10, 110, Liquidity::Taker);
+    let mut oms = OrderManagementSystem::new(&mut strat, account);
+    oms.risk.limits = RiskLimits {
+        max_loss: Some(Money::from_units(max_loss)),
+        ..RiskLimits::default()
+    };
+    oms.mark_risk(&mut full_ob());
+    assert_eq!(oms.risk.killed, expected);
+}
+
+#[test]
+fn kill_switch_stops_trading() {
+    let dir = std::env::temp_dir();
+    let ob_path = dir.join(format!("orderbook_{}_risk_ob.csv", std::process::id()));
+    let orders_path = dir.join(format!("orderbook_{}_risk_orders.csv", std::process::id()));
+    let config = GeneratorConfig {
+        duration: 60_000_000_000,
+        ..GeneratorConfig::default()
+    };
+    generate_csv(config, &ob_path, &orders_path).unwrap();
+    let mut strat = FixSpreadStrategy::new(Ticker::default());
+    strat.buy_criterion = -0.0001;
+    strat.sell_criterion = 0.0001;
+    strat.buy_position_limit = 100;
+    strat.sell_position_limit = -100;
+    strat.qty = 100;
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    oms.risk.limits = RiskLimits {
+        max_drawdown: Some(Money::from_units(1)),
+        ..RiskLimits::default()
+    };
+    let mut ob = OrderBook::new();
+    let _ = strategy_flow(
+        &mut oms,
+        &mut ob,
+        ob_path.to_str().unwrap(),
+        orders_path.to_str().unwrap(),
+    );
+    std::fs::remove_file(&ob_path).unwrap();
+    std::fs::remove_file(&orders_path).unwrap();
+
+    assert!(oms.risk.is_killed());
+    assert!(oms.own_order_ids().is_empty());
+    assert_eq!(oms.orders.open_orders().count(), 0);
+    // Once tripped every further order is blocked
+    let first = oms
+        .risk
+        .rejections
+        .iter()
+        .position(|(_, reason)| matches!(reason, RiskRejection::KillSwitch(_)))
+        .unwrap();
+    assert!(oms.risk.rejections[first..]
+        .iter()
+        .all(|(_, reason)| matches!(reason, RiskRejection::KillSwitch(_))));
+}
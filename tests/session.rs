@@ -0,0 +1,105 @@
+use orderbook::{
+    backtest::{strategy_flow, FixSpreadStrategy, StrategyMetrics},
+    engine::{
+        hm, Money, OrderBook, Session, SessionCalendar, Ticker, TradingAccount, MOEX_EQUITIES,
+    },
+    management::OrderManagementSystem,
+};
+use pretty_assertions::assert_eq;
+
+// Sample data starts at 07:05:00 UTC (10:05 MSK) and lasts for 2.5 seconds
+const HALTED: SessionCalendar = SessionCalendar {
+    name: "halted",
+    utc_offset: MOEX_EQUITIES.utc_offset,
+    sessions: &[Session {
+        name: "main",
+        open: hm(10, 0),
+        close: hm(18, 40),
+        breaks: &[(hm(10, 0), hm(11, 0))],
+    }],
+    clearing: None,
+};
+
+fn run(calendar: SessionCalendar, flatten_before_close: Option<u64>) -> (StrategyMetrics, i32) {
+    let ticker = Ticker {
+        calendar,
+        ..Ticker::default()
+    };
+    let mut strat = FixSpreadStrategy::new(ticker);
+    strat.buy_criterion = 0.0;
+    strat.sell_criterion = 0.0;
+    strat.buy_position_limit = 100;
+    strat.sell_position_limit = -100;
+    strat.qty = 10;
+    strat.flatten_before_close = flatten_before_close;
+    let mut ob = OrderBook::new();
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    let metrics = strategy_flow(&mut oms, &mut ob, "data/ob.csv", "data/orders.csv");
+    (metrics, oms.position())
+}
+
+#[test]
+fn no_quoting_in_break() {
+    let (metrics, position) = run(HALTED, None);
+    assert_eq!(metrics.trade_count, 0);
+    assert_eq!(position, 0);
+    assert_eq!(metrics.sessions.len(), 1);
+    assert_eq!(metrics.sessions[0].session, "main");
+}
+
+const SPLIT: SessionCalendar = SessionCalendar {
+    name: "split",
+    utc_offset: 0,
+    sessions: &[
+        Session {
+            name: "first",
+            open: hm(7, 0),
+            close: hm(7, 5) + 1_000_000_000,
+            breaks: &[],
+        },
+        Session {
+            name: "second",
+            open: hm(7, 5) + 1_000_000_000,
+            close: hm(8, 0),
+            breaks: &[],
+        },
+    ],
+    clearing: None,
+};
+
+#[test]
+fn sessions_add_up() {
+    let (metrics, _) = run(SPLIT, None);
+    let names = metrics
+        .sessions
+        .iter()
+        .map(|s| s.session)
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["first", "second"]);
+    let trade_count = metrics.sessions.iter().map(|s| s.trade_count).sum::<u32>();
+    let volume = metrics.sessions.iter().map(|s| s.volume).sum::<f32>();
+    assert_eq!(trade_count, metrics.trade_count);
+    assert_eq!(volume, metrics.volume);
+}
+
+const SHORT: SessionCalendar = SessionCalendar {
+    name: "short",
+    utc_offset: 0,
+    sessions: &[Session {
+        name: "main",
+        open: hm(7, 0),
+        close: hm(7, 5) + 2_000_000_000,
+        breaks: &[],
+    }],
+    clearing: None,
+};
+
+#[test]
+fn flatten_before_close() {
+    let (metrics, position) = run(SHORT, None);
+    assert_ne!(position, 0);
+    let (flat_metrics, position) = run(SHORT, Some(1_000_000_000));
+    assert_eq!(position, 0);
+    assert!(flat_metrics.trade_count > 0);
+    assert_ne!(flat_metrics, metrics);
+}
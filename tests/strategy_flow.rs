@@ -1,35 +1,24 @@
 use orderbook::{
-    backtest::{strategy_flow, FixSpreadStrategy, StrategyMetrics},
-    engine::{OrderBook, Ticker, TradingAccount},
+    backtest::{strategy_flow, FixSpreadStrategy, MessageMetrics, SessionMetrics, StrategyMetrics},
+    data::{generate_csv, GeneratorConfig},
+    engine::{FeeSchedule, Money, OrderBook, Ticker, TradingAccount, ALWAYS_OPEN},
     management::OrderManagementSystem,
 };
 use pretty_assertions::assert_eq;
 use rstest::rstest;
 
-fn metrics_1() -> StrategyMetrics {
-    StrategyMetrics {
-        pnl_abs: -351.0,
-        pnl_bps: -0.14447868,
-        volume: 24294244.0,
-        trade_count: 1068,
-    }
-}
-
-#[rstest]
-#[case((-0.0002, 0.0002), metrics_1())]
-fn snap_to_event_test(#[case] criterions: (f32, f32), #[case] expected: StrategyMetrics) {
-    let ob_path = "/opt/Zenpy/jupyter/data/voskhod/RUST_OB/ob/ob_ALRS.2024-01-29.csv";
-    let orders_path = "/opt/Zenpy/jupyter/data/voskhod/RUST_OB/orders/orders_ALRS.2024-01-29.csv";
+fn run(criterions: (f32, f32), ob_path: &str, orders_path: &str) -> StrategyMetrics {
     let mut ob = OrderBook::new();
     let ticker = Ticker {
         ticker_id: 0,
         tick_size: 1.0,
         step_price: 0.1,
-        taker_fee: 0.0,
-        maker_fee: 0.0,
+        fees: FeeSchedule::NONE,
+        calendar: ALWAYS_OPEN,
+        initial_margin: Money::ZERO,
     };
     let mut strat = FixSpreadStrategy::new(ticker);
-    let initial_balance = 0.0;
+    let initial_balance = Money::ZERO;
     strat.buy_criterion = criterions.0;
     strat.sell_criterion = criterions.1;
     strat.buy_position_limit = 100;
@@ -41,6 +30,51 @@ fn snap_to_event_test(#[case] criterions: (f32, f32), #[case] expected: Strategy
     // Setup OMS
     let mut oms = OrderManagementSystem::new(&mut strat, money_account);
 
-    let metrics = strategy_flow(&mut oms, &mut ob, ob_path, orders_path);
+    strategy_flow(&mut oms, &mut ob, ob_path, orders_path)
+}
+
+fn synthetic_metrics() -> StrategyMetrics {
+    StrategyMetrics {
+        pnl_abs: 41.55,
+        pnl_bps: 1.0672323,
+        volume: 389324.8,
+        trade_count: 6,
+        fees: 0.0,
+        messages: MessageMetrics {
+            messages: 106,
+            throttled: 0,
+            order_to_trade: 17.666666,
+            messages_per_sec: 1.8761061,
+        },
+        sessions: vec![SessionMetrics {
+            day: 19662,
+            session: "all day",
+            pnl_abs: 41.55,
+            pnl_bps: 1.0672323,
+            volume: 389324.8,
+            trade_count: 6,
+        }],
+    }
+}
+
+// One minute of generated flow, no external data needed
+#[rstest]
+#[case((-0.0001, 0.0001), synthetic_metrics())]
+fn synthetic_flow_test(#[case] criterions: (f32, f32), #[case] expected: StrategyMetrics) {
+    let dir = std::env::temp_dir();
+    let ob_path = dir.join(format!("orderbook_{}_flow_ob.csv", std::process::id()));
+    let orders_path = dir.join(format!("orderbook_{}_flow_orders.csv", std::process::id()));
+    let config = GeneratorConfig {
+        duration: 60_000_000_000,
+        ..GeneratorConfig::default()
+    };
+    generate_csv(config, &ob_path, &orders_path).unwrap();
+    let metrics = run(
+        criterions,
+        ob_path.to_str().unwrap(),
+        orders_path.to_str().unwrap(),
+    );
+    std::fs::remove_file(&ob_path).unwrap();
+    std::fs::remove_file(&orders_path).unwrap();
     assert_eq!(metrics, expected);
 }
@@ -0,0 +1,124 @@
+mod common;
+use common::full_ob;
+use orderbook::{
+    backtest::{strategy_flow, FixSpreadStrategy, StrategyMetrics},
+    data::{generate_csv, GeneratorConfig},
+    engine::indicators::Midprice,
+    engine::{Money, OrderBook, Ticker, TradingAccount},
+    management::{OrderManagementSystem, QuoteId, RateLimit, Throttle, ThrottleMode},
+};
+use pretty_assertions::assert_eq;
+use rstest::rstest;
+
+// 2 messages per 1000 epochs
+const LIMIT: RateLimit = RateLimit {
+    messages: 2,
+    window: 1000,
+};
+
+fn active_ids(oms: &OrderManagementSystem<FixSpreadStrategy>) -> (Option<u64>, Option<u64>) {
+    (
+        oms.active_buy_order.map(|order| order.id),
+        oms.active_sell_order.map(|order| order.id),
+    )
+}
+
+#[rstest]
+// Dropped amendments leave the old ask to amend on the next decision
+#[case(ThrottleMode::Drop, (Some(1), Some(2)), (2, 4))]
+#[case(ThrottleMode::Queue, (Some(3), Some(4)), (4, 5))]
+fn amendments_over_limit(
+    #[case] mode: ThrottleMode,
+    #[case] released: (Option<u64>, Option<u64>),
+    #[case] messages: (u64, u64),
+) {
+    let mut strat = FixSpreadStrategy::new(Ticker::default());
+    strat.buy_criterion = -0.01;
+    strat.sell_criterion = 0.01;
+    strat.buy_position_limit = 10;
+    strat.sell_position_limit = -10;
+    strat.qty = 5;
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    oms.throttle = Throttle::new(LIMIT, mode);
+    let mut ob = full_ob();
+    let m = Midprice::evaluate(&ob);
+    oms.send_orders(&mut ob, m, Some(QuoteId::Given(1)), Some(QuoteId::Given(2)));
+    assert_eq!(active_ids(&oms), (Some(1), Some(2)));
+
+    // Budget is used up by the new orders, the resting quotes stay
+    oms.account.clock = 10;
+    oms.strategy.buy_criterion = -0.02;
+    oms.strategy.sell_criterion = 0.02;
+    oms.send_orders(&mut ob, m, Some(QuoteId::Given(3)), Some(QuoteId::Given(4)));
+    assert_eq!(active_ids(&oms), (Some(1), Some(2)));
+    assert_eq!(oms.throttle.throttled, 2);
+
+    // Held amendments go out once the window refills
+    oms.account.clock = 1000;
+    oms.release_held(&mut ob);
+    assert_eq!(active_ids(&oms), released);
+    assert_eq!(oms.throttle.messages, messages.0);
+
+    // A fresh decision amends right away
+    oms.account.clock = 2000;
+    oms.strategy.buy_criterion = -0.03;
+    oms.send_orders(&mut ob, m, Some(QuoteId::Given(5)), Some(QuoteId::Given(6)));
+    assert_eq!(oms.active_buy_order.map(|order| order.id), Some(5));
+    oms.release_held(&mut ob);
+    assert_eq!(oms.throttle.messages, messages.1);
+}
+
+fn run(throttle: Throttle, name: &str) -> StrategyMetrics {
+    let dir = std::env::temp_dir();
+    let ob_path = dir.join(format!("orderbook_{}_{name}_ob.csv", std::process::id()));
+    let orders_path = dir.join(format!(
+        "orderbook_{}_{name}_orders.csv",
+        std::process::id()
+    ));
+    let config = GeneratorConfig {
+        duration: 60_000_000_000,
+        ..GeneratorConfig::default()
+    };
+    generate_csv(config, &ob_path, &orders_path).unwrap();
+    let mut strat = FixSpreadStrategy::new(Ticker::default());
+    strat.buy_criterion = -0.0001;
+    strat.sell_criterion = 0.0001;
+    strat.buy_position_limit = 100;
+    strat.sell_position_limit = -100;
+    strat.qty = 100;
+    let mut oms = OrderManagementSystem::new(&mut strat, TradingAccount::new(Money::ZERO));
+    oms.throttle = throttle;
+    let mut ob = OrderBook::new();
+    let metrics = strategy_flow(
+        &mut oms,
+        &mut ob,
+        ob_path.to_str().unwrap(),
+        orders_path.to_str().unwrap(),
+    );
+    std::fs::remove_file(&ob_path).unwrap();
+    std::fs::remove_file(&orders_path).unwrap();
+    metrics
+}
+
+#[rstest]
+#[case(ThrottleMode::Drop)]
+#[case(ThrottleMode::Queue)]
+fn throttled_flow_sends_less(#[case] mode: ThrottleMode) {
+    let free = run(Throttle::default(), &format!("throttle_free_{mode:?}"));
+    // One message per second
+    let limit = RateLimit {
+        messages: 1,
+        window: 1_000_000_000,
+    };
+    let throttled = run(Throttle::new(limit, mode), &format!("throttle_{mode:?}"));
+    assert_eq!(free.messages.throttled, 0);
+    assert!(throttled.messages.throttled > 0);
+    assert!(throttled.messages.messages < free.messages.messages);
+    assert!(throttled.messages.messages_per_sec < free.messages.messages_per_sec);
+    assert!(
+        (throttled.messages.order_to_trade
+            - throttled.messages.messages as f32 / throttled.trade_count as f32)
+            .abs()
+            < 1e-6
+    );
+}
@@ -0,0 +1,195 @@
+use orderbook::{
+    data::{
+        open_market_data, open_validated, validate_file, Action, Anomaly, CleaningPolicy,
+        MarketEvent, Validator,
+    },
+    engine::{LimitOrder, Side, Snap, Trade},
+};
+use pretty_assertions::assert_eq;
+use rstest::rstest;
+
+fn snap(exch_epoch: u64, bid: u32, ask: u32, qty: u32) -> Snap {
+    Snap {
+        exch_epoch,
+        vec: vec![
+            LimitOrder {
+                side: Side::Bid,
+                price: bid,
+                qty,
+            },
+            LimitOrder {
+                side: Side::Bid,
+                price: bid - 1,
+                qty: 10,
+            },
+            LimitOrder {
+                side: Side::Ask,
+                price: ask,
+                qty: 10,
+            },
+            LimitOrder {
+                side: Side::Ask,
+                price: ask + 1,
+                qty: 10,
+            },
+        ],
+    }
+}
+
+fn dirty_stream() -> Vec<Snap> {
+    vec![
+        snap(10, 99, 101, 10),
+        // Zero qty level
+        snap(20, 99, 101, 0),
+        // Crossed
+        Snap {
+            exch_epoch: 30,
+            vec: vec![
+                LimitOrder {
+                    side: Side::Bid,
+                    price: 101,
+                    qty: 10,
+                },
+                LimitOrder {
+                    side: Side::Bid,
+                    price: 98,
+                    qty: 10,
+                },
+                LimitOrder {
+                    side: Side::Ask,
+                    price: 100,
+                    qty: 10,
+                },
+                LimitOrder {
+                    side: Side::Ask,
+                    price: 102,
+                    qty: 10,
+                },
+            ],
+        },
+        // Duplicated epoch
+        snap(30, 99, 101, 10),
+        // Out of order
+        snap(25, 99, 101, 10),
+        snap(40, 99, 101, 10),
+    ]
+}
+
+// Dropping the crossed snapshot leaves the next one at 30 unique
+#[rstest]
+#[case(CleaningPolicy::report_only(), 6, 0, 0, 1)]
+#[case(CleaningPolicy::default(), 6, 4, 0, 1)]
+#[case(CleaningPolicy::drop_all(), 3, 0, 3, 0)]
+fn snap_policy_test(
+    #[case] policy: CleaningPolicy,
+    #[case] expected_kept: usize,
+    #[case] expected_repaired: usize,
+    #[case] expected_dropped: usize,
+    #[case] expected_duplicates: usize,
+) {
+    let mut validator = Validator::new(policy);
+    let cleaned = validator.clean(dirty_stream().into_iter().map(Ok));
+    let report = validator.report;
+    assert_eq!(cleaned.len(), expected_kept);
+    assert_eq!(report.kept, expected_kept);
+    assert_eq!(report.repaired, expected_repaired);
+    assert_eq!(report.dropped, expected_dropped);
+    assert_eq!(report.count(Anomaly::ZeroQty), 1);
+    assert_eq!(report.count(Anomaly::CrossedBook), 1);
+    assert_eq!(report.count(Anomaly::DuplicateEpoch), expected_duplicates);
+    assert_eq!(report.count(Anomaly::OutOfOrder), 1);
+}
+
+#[test]
+fn dropped_records_do_not_move_the_epoch() {
+    let mut crossed = snap(1_000_000, 99, 101, 10);
+    crossed.vec[0].price = 102;
+    let stream = vec![
+        snap(10, 99, 101, 10),
+        // Far in the future and dropped
+        crossed,
+        snap(20, 99, 101, 10),
+        snap(30, 99, 101, 10),
+    ];
+    let mut validator = Validator::new(CleaningPolicy::drop_all());
+    let cleaned = validator.clean(stream.into_iter().map(Ok));
+    assert_eq!(
+        cleaned
+            .iter()
+            .map(|snap| snap.exch_epoch)
+            .collect::<Vec<_>>(),
+        vec![10, 20, 30]
+    );
+    assert_eq!(validator.report.count(Anomaly::OutOfOrder), 0);
+}
+
+#[test]
+fn snap_repair_test() {
+    let mut validator = Validator::new(CleaningPolicy::default());
+    let cleaned = validator.clean(dirty_stream().into_iter().map(Ok));
+    let epochs = cleaned.iter().map(|s| s.exch_epoch).collect::<Vec<_>>();
+    assert_eq!(epochs, vec![10, 20, 30, 31, 32, 40]);
+    assert!(cleaned[1].vec.iter().all(|l| l.qty > 0));
+    // Crossing levels removed from both sides
+    let crossed = &cleaned[2].vec;
+    assert_eq!(crossed.len(), 2);
+    assert_eq!((crossed[0].price, crossed[1].price), (98, 102));
+}
+
+#[test]
+fn trade_file_test() {
+    let path = std::env::temp_dir().join(format!("orderbook_{}_trades.csv", std::process::id()));
+    std::fs::write(
+        &path,
+        "exch_epoch,side,price,qty\n10,0,100,5\n10,1,101,5\n11,7,100,5\n12,1,101,0\n9,0,100,1\n",
+    )
+    .unwrap();
+    let policy = CleaningPolicy {
+        out_of_order: Action::Drop,
+        ..Default::default()
+    };
+    let (trades, report) = validate_file::<Trade>(&path, policy).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    // Several prints may share an epoch
+    assert_eq!(report.count(Anomaly::DuplicateEpoch), 0);
+    assert_eq!(report.count(Anomaly::Malformed), 1);
+    assert_eq!(report.count(Anomaly::ZeroQty), 1);
+    assert_eq!(report.count(Anomaly::OutOfOrder), 1);
+    assert_eq!(trades.len(), 2);
+    assert_eq!(report.total, 5);
+    assert_eq!(
+        (report.first_epoch, report.last_epoch),
+        (Some(10), Some(10))
+    );
+}
+
+#[test]
+fn streamed_as_validated() {
+    let path = std::env::temp_dir().join(format!(
+        "orderbook_{}_streamed_trades.csv",
+        std::process::id()
+    ));
+    std::fs::write(
+        &path,
+        "exch_epoch,side,price,qty\n10,0,100,5\n11,7,100,5\n9,0,100,1\n12,1,101,5\n",
+    )
+    .unwrap();
+    let policy = CleaningPolicy::default();
+    let (expected, expected_report) = validate_file::<Trade>(&path, policy).unwrap();
+    let (trades, report) = open_validated::<Trade>(&path, policy).unwrap();
+    let trades = trades.collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(report, expected_report);
+    assert_eq!(trades, expected);
+    // The out of order print is shifted behind the first one
+    let epochs = trades.iter().map(|t| t.exch_epoch).collect::<Vec<_>>();
+    assert_eq!(epochs, vec![10, 10, 12]);
+
+    // A malformed row no longer ends the replay
+    let prints = open_market_data("data/ob.csv", &path, policy)
+        .unwrap()
+        .map_while(Result::ok)
+        .filter(|event| matches!(event, MarketEvent::Trade(_)))
+        .count();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(prints, 3);
+}